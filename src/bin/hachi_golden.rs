@@ -0,0 +1,166 @@
+//! Headless runner for golden run files, see [`hachi_emu::GoldenRun`].
+
+use std::{env, fs::File, io::BufWriter, path::Path, process};
+
+use hachi_emu::{
+    BIG_FONT, Chip8Variant, Emulator, GOLDEN_EXTENSION, GoldenRun, Movie, STANDARD_FONT,
+    UnknownVariantError, run_corpus, screen_text, state_hash, write_pbm,
+};
+
+fn usage(program: &str) -> String {
+    format!(
+        "Usage:
+  {program} <folder>...                     Check every *.{GOLDEN_EXTENSION} file in the folders
+  {program} --update <file>...              Rerun golden runs and rewrite their expected state
+  {program} --record <rom> <cycles> [variant] [seed]
+                                            Print a new golden run for a ROM
+  {program} --screen <file> [pbm-file]      Print the screen a golden run ends on as text, and
+                                            save it as a PBM image if a path is given
+  {program} --movie <movie> <rom>           Replay a movie recorded in the emulator, printing
+                                            the screen and state hash it ends on"
+    )
+}
+
+fn main() {
+    let mut args = env::args();
+    let program = args.next().unwrap_or_else(|| String::from("hachi_golden"));
+    let args: Vec<String> = args.collect();
+
+    let result = match args.first().map(String::as_str) {
+        None | Some("-h" | "--help") => {
+            println!("{}", usage(&program));
+            return;
+        }
+        Some("--update") => update(&args[1..]),
+        Some("--record") => record(&args[1..]),
+        Some("--screen") => screen(&args[1..]),
+        Some("--movie") => movie(&args[1..]),
+        Some(_) => check(&args),
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("{error}\n\n{}", usage(&program));
+            process::exit(2);
+        }
+    }
+}
+
+/// Checks every golden run in the folders, returning whether they all passed.
+fn check(folders: &[String]) -> Result<bool, String> {
+    let mut failures = 0;
+    let mut total = 0;
+    for folder in folders {
+        let results =
+            run_corpus(Path::new(folder)).map_err(|error| format!("{folder}: {error}"))?;
+        for entry in results {
+            total += 1;
+            match entry.result {
+                Ok(()) => println!("ok    {}", entry.path.display()),
+                Err(error) => {
+                    failures += 1;
+                    println!("FAIL  {}: {error}", entry.path.display());
+                }
+            }
+        }
+    }
+
+    println!("{} passed, {failures} failed", total - failures);
+    Ok(failures == 0)
+}
+
+fn update(files: &[String]) -> Result<bool, String> {
+    for file in files {
+        let path = Path::new(file);
+        let result = GoldenRun::load(path).and_then(|mut run| {
+            let rom = run.find_rom(path)?;
+            run.state_sha1 = run.execute(&rom)?;
+            run.save(path)
+        });
+        result.map_err(|error| format!("{file}: {error}"))?;
+        println!("updated {file}");
+    }
+    Ok(true)
+}
+
+fn record(args: &[String]) -> Result<bool, String> {
+    let [rom_path, cycles, rest @ ..] = args else {
+        return Err(String::from("--record needs a ROM and a cycle count"));
+    };
+
+    let rom = std::fs::read(rom_path).map_err(|error| format!("{rom_path}: {error}"))?;
+    let cycles = cycles
+        .parse()
+        .map_err(|_| format!("invalid cycle count \"{cycles}\""))?;
+    let variant = variant(rest.first())?;
+    let seed = match rest.get(1) {
+        Some(seed) => seed
+            .parse()
+            .map_err(|_| format!("invalid seed \"{seed}\""))?,
+        None => 0,
+    };
+
+    let mut run = GoldenRun::record(&rom, variant, seed, cycles, Vec::new())
+        .map_err(|error| error.to_string())?;
+    run.rom = Path::new(rom_path).file_name().map(Into::into);
+    print!(
+        "{}",
+        toml::to_string_pretty(&run).map_err(|error| error.to_string())?
+    );
+    Ok(true)
+}
+
+/// Parses an optional variant name, defaulting to the default variant.
+fn variant(name: Option<&String>) -> Result<Chip8Variant, String> {
+    match name {
+        Some(name) => name
+            .parse()
+            .map_err(|error: UnknownVariantError| error.to_string()),
+        None => Ok(Chip8Variant::default()),
+    }
+}
+
+fn movie(args: &[String]) -> Result<bool, String> {
+    let [movie_path, rom_path] = args else {
+        return Err(String::from("--movie needs a movie file and a ROM"));
+    };
+
+    let movie =
+        Movie::load(Path::new(movie_path)).map_err(|error| format!("{movie_path}: {error}"))?;
+    let rom = std::fs::read(rom_path).map_err(|error| format!("{rom_path}: {error}"))?;
+
+    let mut emulator = Emulator::new();
+    emulator.load_font(&STANDARD_FONT);
+    emulator.load_big_font(&BIG_FONT);
+    movie
+        .restart(&mut emulator, &rom)
+        .map_err(|error| format!("{rom_path}: {error}"))?;
+    if let Err(reason) = movie.replay(&mut emulator) {
+        println!("Stopped early: {reason:?}");
+    }
+
+    print!("{}", screen_text(&emulator));
+    println!("state_sha1 = \"{}\"", state_hash(&emulator));
+    Ok(true)
+}
+
+fn screen(args: &[String]) -> Result<bool, String> {
+    let [file, rest @ ..] = args else {
+        return Err(String::from("--screen needs a golden run file"));
+    };
+
+    let path = Path::new(file);
+    let emulator = GoldenRun::load(path)
+        .and_then(|run| run.run(&run.find_rom(path)?))
+        .map_err(|error| format!("{file}: {error}"))?;
+    print!("{}", screen_text(&emulator));
+
+    if let Some(pbm_path) = rest.first() {
+        File::create(pbm_path)
+            .and_then(|file| write_pbm(&emulator, BufWriter::new(file)))
+            .map_err(|error| format!("{pbm_path}: {error}"))?;
+    }
+    Ok(true)
+}
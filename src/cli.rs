@@ -0,0 +1,295 @@
+use std::path::PathBuf;
+
+use hachi_emu::{
+    Chip8Variant, EmulationConfig, KnownRom, MachineCodePolicy, PalettePreset, ParseColorError,
+    Profile, Quirks, Rgb, RngAlgorithm, Timing, UnknownOpcodePolicy, UnknownPaletteError,
+    UnknownPolicyError, UnknownRngError, UnknownTimingError, UnknownVariantError,
+};
+
+pub struct Options {
+    /// The ROM to run, or `None` to show the splash screen until one is dropped on the window.
+    pub rom_path: Option<PathBuf>,
+    pub config_path: Option<PathBuf>,
+    /// The variant given with `--variant`, if any.
+    pub variant: Option<Chip8Variant>,
+    /// Quirks given with `--quirk`, in order.
+    pub quirks: Vec<(String, bool)>,
+    /// Whether to look the ROM up in the ROM database.
+    pub rom_db: bool,
+    pub timing: Timing,
+    /// The random number generator given with `--rng`, if any.
+    pub rng: Option<RngAlgorithm>,
+    pub machine_code: MachineCodePolicy,
+    pub unknown_opcodes: UnknownOpcodePolicy,
+    pub stack_limit: Option<usize>,
+    /// Theme file applied on top of the config's display settings.
+    pub theme: Option<PathBuf>,
+    /// Palette preset, overriding the config's palette.
+    pub palette: Option<PalettePreset>,
+    /// Color for lit pixels, overriding the config's palette.
+    pub foreground: Option<Rgb>,
+    /// Color for unlit pixels, overriding the config's palette.
+    pub background: Option<Rgb>,
+    pub mmio_base: Option<usize>,
+    pub monitor: Option<usize>,
+    pub canaries: bool,
+    /// Whether to start with sound muted.
+    pub mute: bool,
+    /// A movie to play back from the start of the ROM.
+    pub movie: Option<PathBuf>,
+}
+
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [options] [<rom-file>]
+
+Without a ROM, a splash screen is shown until one is dropped on the window.
+
+Options:
+  --config <path>             Use the given config file instead of the default one
+  --variant <name>            Behave like the given interpreter (default: {})
+  --quirk <name>[=on|off]     Enable or disable an interpreter quirk, overriding the variant
+                              (may be repeated)
+  --timing <fixed|vip>        Run a fixed number of instructions per second (the default), or
+                              take as long over each one as the COSMAC VIP did
+  --rng <uniform|vip-lfsr>    Generate CXNN's random numbers with a modern generator, or with an
+                              LFSR like the COSMAC VIP's, overriding the variant's choice
+  --machine-code <policy>     What to do when the program calls a 0NNN machine code routine:
+                              ignore, log (and carry on) or halt (default: {})
+  --unknown-opcode <policy>   What to do on opcodes the interpreter doesn't recognize: log (and
+                              carry on), pause or abort (default: {})
+  --no-rom-db                 Don't apply the settings known ROMs need from the ROM database;
+                              giving --variant also skips it
+  --stack-limit <levels>      Stop with an error when subroutine calls nest deeper than this,
+                              e.g. 12 or 16 like the original interpreters
+  --theme <path>              Apply a theme file's display settings (palette, scaler, CRT
+                              effects, bezel and pixel grid)
+  --palette <name>            Use a built-in palette, such as protanopia (see the list below)
+  --foreground <color>        Color for lit pixels as RRGGBB, e.g. 33FF33 for green phosphor
+  --background <color>        Color for unlit pixels as RRGGBB
+  --mmio <address>            Map host values (clock, frame counter, pointer) into memory at the
+                              given address, for homebrew experiments
+  --monitor <index>           Open the window on the given monitor from the config's
+                              [window] monitors list
+  --mute                      Start with sound muted, toggled with the mute hotkey
+  --movie <path>              Play back a movie recorded with the record movie hotkey
+  --canaries                  Fill unused memory with a canary pattern and report stack and
+                              memory high-water marks on exit
+  -h, --help                  Show this message
+
+Variants: {}
+Palettes: {}
+Quirks: {}
+Octo's quirk names are accepted too: {}",
+        Chip8Variant::default().name(),
+        MachineCodePolicy::default().name(),
+        UnknownOpcodePolicy::default().name(),
+        Chip8Variant::ALL.map(Chip8Variant::name).join(", "),
+        PalettePreset::ALL.map(PalettePreset::name).join(", "),
+        Quirks::NAMES.join(", "),
+        Quirks::OCTO_NAMES
+            .iter()
+            .map(|(name, _, _)| *name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Parses the command line arguments (excluding the program name). Returns `Ok(None)` if help was
+/// requested.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut args = args.into_iter();
+    let mut rom_path = None;
+    let mut config_path = None;
+    let mut variant = None;
+    let mut rom_db = true;
+    let mut timing = Timing::default();
+    let mut rng = None;
+    let mut machine_code = MachineCodePolicy::default();
+    let mut unknown_opcodes = UnknownOpcodePolicy::default();
+    let mut stack_limit = None;
+    let mut theme = None;
+    let mut palette = None;
+    let mut foreground = None;
+    let mut background = None;
+    let mut quirks = Vec::new();
+    let mut mmio_base = None;
+    let mut monitor = None;
+    let mut canaries = false;
+    let mut mute = false;
+    let mut movie = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--config" => config_path = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--quirk" => {
+                let quirk = value(&mut args, &arg)?;
+                let (name, enabled) = parse_toggle(&quirk)?;
+                Quirks::default()
+                    .set(name, enabled)
+                    .map_err(|error| error.to_string())?;
+                quirks.push((name.to_string(), enabled));
+            }
+            "--variant" => {
+                variant = Some(
+                    value(&mut args, &arg)?
+                        .parse()
+                        .map_err(|error: UnknownVariantError| error.to_string())?,
+                );
+            }
+            "--timing" => {
+                timing = value(&mut args, &arg)?
+                    .parse()
+                    .map_err(|error: UnknownTimingError| error.to_string())?;
+            }
+            "--rng" => {
+                rng = Some(
+                    value(&mut args, &arg)?
+                        .parse()
+                        .map_err(|error: UnknownRngError| error.to_string())?,
+                );
+            }
+            "--machine-code" => {
+                machine_code = value(&mut args, &arg)?
+                    .parse()
+                    .map_err(|error: UnknownPolicyError| error.to_string())?;
+            }
+            "--unknown-opcode" => {
+                unknown_opcodes = value(&mut args, &arg)?
+                    .parse()
+                    .map_err(|error: UnknownPolicyError| error.to_string())?;
+            }
+            "--stack-limit" => {
+                let levels = value(&mut args, &arg)?;
+                stack_limit = Some(
+                    levels
+                        .parse()
+                        .map_err(|_| format!("invalid stack limit \"{levels}\""))?,
+                );
+            }
+            "--theme" => theme = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--palette" => {
+                palette = Some(
+                    value(&mut args, &arg)?
+                        .parse()
+                        .map_err(|error: UnknownPaletteError| error.to_string())?,
+                );
+            }
+            "--foreground" => foreground = Some(parse_color(&value(&mut args, &arg)?)?),
+            "--background" => background = Some(parse_color(&value(&mut args, &arg)?)?),
+            "--mmio" => mmio_base = Some(parse_address(&value(&mut args, &arg)?)?),
+            "--canaries" => canaries = true,
+            "--mute" => mute = true,
+            "--movie" => movie = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--no-rom-db" => rom_db = false,
+            "--monitor" => {
+                let index = value(&mut args, &arg)?;
+                monitor = Some(
+                    index
+                        .parse()
+                        .map_err(|_| format!("invalid monitor index \"{index}\""))?,
+                );
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ if rom_path.is_none() => rom_path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    Ok(Some(Options {
+        rom_path,
+        config_path,
+        variant,
+        quirks,
+        rom_db,
+        timing,
+        rng,
+        machine_code,
+        unknown_opcodes,
+        stack_limit,
+        theme,
+        palette,
+        foreground,
+        background,
+        mmio_base,
+        monitor,
+        canaries,
+        mute,
+        movie,
+    }))
+}
+
+impl Options {
+    /// Whether the ROM database should be consulted, which only matters without `--variant`.
+    pub fn uses_rom_db(&self) -> bool {
+        self.rom_db && self.variant.is_none()
+    }
+
+    /// The profile to run with: the given variant, else the ROM database's settings for the ROM
+    /// if it's `known`, else the default variant. The `configured` quirks and random number
+    /// generator are set on top, and those from the command line always take precedence,
+    /// regardless of argument order.
+    pub fn profile(
+        &self,
+        known: Option<&KnownRom>,
+        configured: &EmulationConfig,
+    ) -> Result<Profile, String> {
+        let mut profile = match (self.variant, known) {
+            (Some(variant), _) => variant.profile(),
+            (None, Some(known)) => known.profile().map_err(|error| error.to_string())?,
+            (None, None) => Chip8Variant::default().profile(),
+        };
+        profile.timing = self.timing;
+        profile.rng = self.rng.or(configured.rng).unwrap_or(profile.rng);
+        let quirks = configured
+            .quirks
+            .iter()
+            .map(|(name, enabled)| (name.as_str(), *enabled))
+            .chain(
+                self.quirks
+                    .iter()
+                    .map(|(name, enabled)| (name.as_str(), *enabled)),
+            );
+        for (name, enabled) in quirks {
+            profile
+                .quirks
+                .set(name, enabled)
+                .map_err(|error| error.to_string())?;
+        }
+        Ok(profile)
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{option} requires a value"))
+}
+
+/// Splits `name[=on|off]` into the name and whether it's enabled.
+fn parse_toggle(text: &str) -> Result<(&str, bool), String> {
+    match text.split_once('=') {
+        None => Ok((text, true)),
+        Some((name, "on" | "true" | "1")) => Ok((name, true)),
+        Some((name, "off" | "false" | "0")) => Ok((name, false)),
+        Some((_, value)) => Err(format!("expected on or off, found \"{value}\"")),
+    }
+}
+
+/// Parses a color written as `RRGGBB`, with the `#` the config file uses being optional since
+/// shells treat it as the start of a comment.
+fn parse_color(text: &str) -> Result<Rgb, String> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    format!("#{hex}")
+        .parse()
+        .map_err(|error: ParseColorError| error.to_string())
+}
+
+/// Parses an address written either in decimal or in hex with a `0x` prefix.
+fn parse_address(text: &str) -> Result<usize, String> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid address \"{text}\""))
+}
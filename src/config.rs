@@ -0,0 +1,321 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ConfigError,
+    frontend::{
+        AudioOutput, BezelConfig, CrtConfig, DEFAULT_PALETTE, GamepadButton, Hotkeys, Keymap, Rgb,
+        Rotation, Scaler, TouchConfig, TurboConfig, Waveform, default_gamepad_buttons,
+        deserialize_gamepad_buttons,
+    },
+    rng::RngAlgorithm,
+    rules::Rule,
+};
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// User settings for the windowed frontend, stored as TOML.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub hotkeys: Hotkeys,
+    /// The keyboard keys that press each CHIP-8 keypad key.
+    pub keymap: Keymap,
+    pub gamepad: GamepadConfig,
+    pub touch: TouchConfig,
+    /// Extra sets of controls by name, such as one for a second player.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub input_profiles: BTreeMap<String, InputProfile>,
+    pub turbo: TurboConfig,
+    pub emulation: EmulationConfig,
+    pub display: DisplayConfig,
+    pub audio: AudioConfig,
+    pub recording: RecordingConfig,
+    pub window: WindowConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmulationConfig {
+    /// How many slices each frame's instruction budget is split into. The timers are ticked
+    /// between slices, so higher values spread instructions more evenly between timer ticks
+    /// rather than executing them in a single burst after them.
+    pub frame_slices: u32,
+    /// Quirks to set on top of the variant's, by name. Octo's names such as `shiftQuirks` work
+    /// too, so an Octo project's options can be pasted in. `--quirk` takes precedence.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub quirks: BTreeMap<String, bool>,
+    /// The random number generator CXNN uses instead of the variant's. `--rng` takes precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rng: Option<RngAlgorithm>,
+}
+
+impl Default for EmulationConfig {
+    fn default() -> Self {
+        EmulationConfig {
+            frame_slices: 1,
+            quirks: BTreeMap::new(),
+            rng: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// The algorithm used to upscale the screen before it's stretched to the window.
+    pub scaler: Scaler,
+    /// The colors for unlit pixels, pixels lit on the first plane, on XO-CHIP's second plane,
+    /// and on both. A preset's name can be given instead.
+    #[serde(deserialize_with = "crate::frontend::deserialize_palette")]
+    pub palette: [Rgb; 4],
+    /// Multiplies the output colors, for monitors on which some palettes look washed out.
+    pub brightness: f32,
+    /// Gamma correction for the output colors, with values above 1 lightening dark colors.
+    pub gamma: f32,
+    /// Only scales the screen by whole multiples, keeping every pixel square and the same size
+    /// at the cost of wider borders.
+    pub integer_scaling: bool,
+    /// How far the screen is turned clockwise, for rotated displays. The keypad's directions
+    /// turn with it.
+    pub rotation: Rotation,
+    /// Flashes a border around the window, in the color of lit pixels, while the sound timer is
+    /// running, so the beeper can be seen as well as heard.
+    pub visual_bell: bool,
+    /// Draws faint lines between the logical pixels when they're big enough, toggled with the
+    /// pixel grid hotkey.
+    pub pixel_grid: bool,
+    /// Shows a keypad beside or below the screen that can be clicked or touched, toggled with
+    /// the keypad panel hotkey.
+    pub keypad_panel: bool,
+    /// How many times larger than the emulated screen screenshots are saved.
+    pub screenshot_scale: u32,
+    /// How many frames pixels take to fade out after they turn off, hiding the flicker of XOR
+    /// drawing. 0 turns them off instantly.
+    pub decay_frames: u32,
+    /// How many of the latest frames are averaged together when presenting, so that sprites
+    /// drawn on alternate frames don't flicker. 1 shows each frame as it is.
+    pub blend_frames: u32,
+    /// The CRT post-processing pass.
+    pub crt: CrtConfig,
+    /// Artwork shown around the screen.
+    pub bezel: BezelConfig,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            scaler: Scaler::default(),
+            palette: DEFAULT_PALETTE,
+            brightness: 1.0,
+            gamma: 1.0,
+            integer_scaling: false,
+            rotation: Rotation::None,
+            visual_bell: false,
+            pixel_grid: false,
+            keypad_panel: false,
+            screenshot_scale: 4,
+            decay_frames: 0,
+            blend_frames: 1,
+            crt: CrtConfig::default(),
+            bezel: BezelConfig::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// The shape of the beep played while the sound timer runs. XO-CHIP audio patterns are
+    /// played as they are.
+    pub waveform: Waveform,
+    /// The beep's frequency in hertz.
+    pub frequency: f32,
+    /// How loud all sound is, from 0 to 1.
+    pub volume: f32,
+    /// Where sound is played.
+    pub output: AudioOutput,
+    /// A WAV file looped as the beep instead of the waveform, at its own pitch.
+    pub sample: Option<PathBuf>,
+    /// How many samples the audio device is given at a time, with the SDL2 output or a backend
+    /// that opens its own device, such as one reading a [`Mixer`](crate::Mixer). Larger buffers
+    /// avoid crackling on slow machines, smaller ones keep the beep closer in time to the sound
+    /// timer. macroquad's buffer is fixed, so it's ignored there with a warning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buffer_size: Option<u32>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            waveform: Waveform::default(),
+            frequency: 500.0,
+            volume: 1.0,
+            output: AudioOutput::default(),
+            buffer_size: None,
+            sample: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    /// Whether to read a game controller.
+    pub enabled: bool,
+    /// The controller's joystick device.
+    pub device: PathBuf,
+    /// The keypad key each button presses.
+    #[serde(deserialize_with = "deserialize_gamepad_buttons")]
+    pub buttons: BTreeMap<GamepadButton, u8>,
+    /// How far the stick has to be pushed to press a key, from 0 to 1.
+    pub deadzone: f32,
+}
+
+impl GamepadConfig {
+    /// This config with the stick pressing `keys` when pushed up, down, left and right.
+    pub fn with_stick(&self, keys: [u8; 4]) -> GamepadConfig {
+        let mut config = self.clone();
+        let directions = [
+            GamepadButton::StickUp,
+            GamepadButton::StickDown,
+            GamepadButton::StickLeft,
+            GamepadButton::StickRight,
+        ];
+        for (direction, key_value) in directions.into_iter().zip(keys) {
+            config.buttons.insert(direction, key_value);
+        }
+        config
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig {
+            enabled: true,
+            device: PathBuf::from("/dev/input/js0"),
+            buttons: default_gamepad_buttons(),
+            deadzone: 0.5,
+        }
+    }
+}
+
+/// Controls that press keypad keys alongside the main keymap and gamepad, so that a second
+/// player can have their own keys or controller for their half of the keypad.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputProfile {
+    pub enabled: bool,
+    /// The keyboard keys that press each keypad key, added to the main keymap.
+    pub keymap: Keymap,
+    /// A controller of the profile's own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamepad: Option<GamepadConfig>,
+}
+
+impl Default for InputProfile {
+    fn default() -> Self {
+        InputProfile {
+            enabled: true,
+            keymap: Keymap::empty(),
+            gamepad: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    /// How many times larger than the emulated screen GIFs and videos are.
+    pub scale: u32,
+    /// The ffmpeg executable videos are encoded with.
+    pub ffmpeg: String,
+    /// The video file's extension, from which ffmpeg picks the format, e.g. `mp4` or `webm`.
+    pub video_extension: String,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        RecordingConfig {
+            scale: 4,
+            ffmpeg: String::from("ffmpeg"),
+            video_extension: String::from("mp4"),
+        }
+    }
+}
+
+/// Where the window opens, saved on exit so the emulator reopens where it was left.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    /// The window's top left corner in desktop coordinates, if it's been saved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<(u32, u32)>,
+    /// Whether to save the window's size and position on exit.
+    pub remember: bool,
+    /// Whether the emulator fills the screen, toggled with the fullscreen hotkey.
+    pub fullscreen: bool,
+    /// The top left corners of the user's monitors in desktop coordinates, used to place the
+    /// window with `--monitor`, since the windowing backend can't list the monitors itself.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub monitors: Vec<(u32, u32)>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 64 * 12,
+            height: 32 * 12,
+            position: None,
+            remember: true,
+            fullscreen: false,
+            monitors: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config at `path`, falling back to the defaults if the file doesn't exist.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The platform-specific location of the user's config file.
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(CONFIG_FILE_NAME))
+    }
+}
+
+/// The directory HachiEmu stores its settings and other per-user data in.
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("hachi_emu"));
+    }
+
+    if cfg!(windows) {
+        env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("HachiEmu"))
+    } else {
+        env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".config").join("hachi_emu"))
+    }
+}
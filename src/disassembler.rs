@@ -0,0 +1,80 @@
+/// Renders an instruction in a Cowgod-style assembly mnemonic, e.g. `LD V3, 0x1F`. Instructions
+/// that don't decode are rendered as raw data.
+pub fn disassemble(instruction: u16) -> String {
+    let x = (instruction & 0x0F00) >> 8;
+    let y = (instruction & 0x00F0) >> 4;
+    let n = instruction & 0x000F;
+    let nn = instruction & 0x00FF;
+    let nnn = instruction & 0x0FFF;
+
+    let nibbles = ((instruction & 0xF000) >> 12, x, y, n);
+
+    match nibbles {
+        (0x0, 0x0, 0xC, _) => format!("SCD {n:#X}"),
+        (0x0, 0x0, 0xD, _) => format!("SCU {n:#X}"),
+        (0x0, 0x0, 0xE, 0x0) => String::from("CLS"),
+        (0x0, 0x0, 0xE, 0xE) => String::from("RET"),
+        (0x0, 0x0, 0xF, 0xB) => String::from("SCR"),
+        (0x0, 0x0, 0xF, 0xC) => String::from("SCL"),
+        (0x0, 0x0, 0xF, 0xD) => String::from("EXIT"),
+        (0x0, 0x0, 0xF, 0xE) => String::from("LOW"),
+        (0x0, 0x0, 0xF, 0xF) => String::from("HIGH"),
+        (0x0, 0x0, 0x1, 0x0) => String::from("MEGAOFF"),
+        (0x0, 0x0, 0x1, 0x1) => String::from("MEGAON"),
+        (0x0, 0x0, 0xB, _) => format!("SCRU {n:#X}"),
+        // The low 16 bits of the address are in the following word
+        (0x0, 0x1, _, _) => format!("LDHI I, {nn:#04X}"),
+        (0x0, 0x2, _, _) => format!("LDPAL {nn:#04X}"),
+        (0x0, 0x3, _, _) => format!("SPRW {nn:#04X}"),
+        (0x0, 0x4, _, _) => format!("SPRH {nn:#04X}"),
+        (0x0, 0x5, _, _) => format!("ALPHA {nn:#04X}"),
+        (0x0, 0x6, 0x0, _) => format!("DIGISND {n:#X}"),
+        (0x0, 0x7, 0x0, 0x0) => String::from("STOPSND"),
+        (0x0, 0x8, 0x0, _) => format!("BMODE {n:#X}"),
+        (0x0, 0x9, _, _) => format!("CCOL {nn:#04X}"),
+        (0x0, _, _, _) => format!("SYS {nnn:#05X}"),
+        (0x1, _, _, _) => format!("JP {nnn:#05X}"),
+        (0x2, _, _, _) => format!("CALL {nnn:#05X}"),
+        (0x3, _, _, _) => format!("SE V{x:X}, {nn:#04X}"),
+        (0x4, _, _, _) => format!("SNE V{x:X}, {nn:#04X}"),
+        (0x5, _, _, 0x0) => format!("SE V{x:X}, V{y:X}"),
+        (0x5, _, _, 0x2) => format!("LD [I], V{x:X}-V{y:X}"),
+        (0x5, _, _, 0x3) => format!("LD V{x:X}-V{y:X}, [I]"),
+        (0x6, _, _, _) => format!("LD V{x:X}, {nn:#04X}"),
+        (0x7, _, _, _) => format!("ADD V{x:X}, {nn:#04X}"),
+        (0x8, _, _, 0x0) => format!("LD V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x1) => format!("OR V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x2) => format!("AND V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x3) => format!("XOR V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x4) => format!("ADD V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x5) => format!("SUB V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x6) => format!("SHR V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x7) => format!("SUBN V{x:X}, V{y:X}"),
+        (0x8, _, _, 0xE) => format!("SHL V{x:X}, V{y:X}"),
+        (0x9, _, _, 0x0) => format!("SNE V{x:X}, V{y:X}"),
+        (0xA, _, _, _) => format!("LD I, {nnn:#05X}"),
+        (0xB, _, _, _) => format!("JP V0, {nnn:#05X}"),
+        (0xC, _, _, _) => format!("RND V{x:X}, {nn:#04X}"),
+        (0xD, _, _, _) => format!("DRW V{x:X}, V{y:X}, {n:#X}"),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{x:X}"),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{x:X}"),
+        // The address is in the following word
+        (0xF, 0x0, 0x0, 0x0) => String::from("LD I, LONG"),
+        (0xF, _, 0x0, 0x1) => format!("PLANE {x:#X}"),
+        (0xF, 0x0, 0x0, 0x2) => String::from("AUDIO"),
+        (0xF, _, 0x0, 0x7) => format!("LD V{x:X}, DT"),
+        (0xF, _, 0x0, 0xA) => format!("LD V{x:X}, K"),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{x:X}"),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{x:X}"),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{x:X}"),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{x:X}"),
+        (0xF, _, 0x3, 0x0) => format!("LD HF, V{x:X}"),
+        (0xF, _, 0x3, 0xA) => format!("PITCH V{x:X}"),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{x:X}"),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{x:X}"),
+        (0xF, _, 0x6, 0x5) => format!("LD V{x:X}, [I]"),
+        (0xF, _, 0x7, 0x5) => format!("LD R, V{x:X}"),
+        (0xF, _, 0x8, 0x5) => format!("LD V{x:X}, R"),
+        _ => format!("DW {instruction:#06X}"),
+    }
+}
@@ -0,0 +1,244 @@
+use bit_set::BitSet;
+
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Width of the SUPER-CHIP hires screen.
+pub const HIRES_WIDTH: usize = 128;
+/// Height of the SUPER-CHIP hires screen.
+pub const HIRES_HEIGHT: usize = 64;
+/// Height of the two-page hires CHIP-8 screen, which is as wide as the lores one.
+pub const TWO_PAGE_HEIGHT: usize = 64;
+/// Height of the ETI-660's screen, which is as wide as the lores one.
+pub const ETI_660_HEIGHT: usize = 48;
+/// Number of XO-CHIP bitplanes. Together they select one of four colors for each pixel.
+pub const PLANES: usize = 2;
+
+/// The screen geometries programs can switch between.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// The standard 64x32 screen.
+    #[default]
+    Lores,
+    /// SUPER-CHIP's 128x64 screen.
+    Hires,
+    /// The 64x64 screen of the two-page hires CHIP-8 interpreter for the COSMAC VIP.
+    TwoPage,
+    /// The ETI-660's 64x48 screen.
+    Eti660,
+}
+
+impl DisplayMode {
+    /// The screen's size in this mode's pixels.
+    pub fn size(self) -> (usize, usize) {
+        match self {
+            DisplayMode::Lores => (SCREEN_WIDTH, SCREEN_HEIGHT),
+            DisplayMode::Hires => (HIRES_WIDTH, HIRES_HEIGHT),
+            DisplayMode::TwoPage => (SCREEN_WIDTH, TWO_PAGE_HEIGHT),
+            DisplayMode::Eti660 => (SCREEN_WIDTH, ETI_660_HEIGHT),
+        }
+    }
+
+    /// How many framebuffer pixels wide and high each of this mode's pixels is. The ETI-660's
+    /// screen doesn't divide the framebuffer evenly, so its pixels are 2x1 blocks in the top 48
+    /// rows.
+    fn scale(self) -> (usize, usize) {
+        let (width, height) = self.size();
+        (HIRES_WIDTH / width, HIRES_HEIGHT / height)
+    }
+}
+
+/// The framebuffer. It's always stored at hires resolution, with each lores pixel covering a
+/// 2x2 block, which is how SUPER-CHIP can scroll lores content by half a pixel. Two-page hires
+/// pixels likewise cover 2x1 blocks.
+///
+/// XO-CHIP adds a second bitplane. Drawing, clearing and scrolling only affect the planes
+/// selected with FN01; everything else only ever uses the first plane.
+#[derive(Clone, Debug)]
+pub(crate) struct Display {
+    planes: [BitSet; PLANES],
+    /// Bit N set means plane N is selected.
+    selected: u8,
+    mode: DisplayMode,
+}
+
+impl Display {
+    pub fn new() -> Display {
+        Display {
+            planes: std::array::from_fn(|_| BitSet::with_capacity(HIRES_WIDTH * HIRES_HEIGHT)),
+            selected: 1,
+            mode: DisplayMode::Lores,
+        }
+    }
+
+    /// Clears the selected planes.
+    pub fn clear(&mut self) {
+        for plane in self.selected_planes() {
+            self.planes[plane].clear();
+        }
+    }
+
+    /// Clears every plane, regardless of the selection.
+    pub fn clear_all(&mut self) {
+        for plane in &mut self.planes {
+            plane.clear();
+        }
+    }
+
+    /// Clears every plane, selects just the first one again and returns to `mode`.
+    pub fn reset(&mut self, mode: DisplayMode) {
+        self.clear_all();
+        self.selected = 1;
+        self.mode = mode;
+    }
+
+    pub fn mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: DisplayMode) {
+        self.mode = mode;
+    }
+
+    /// Whether SUPER-CHIP's 128x64 mode is active.
+    pub fn hires(&self) -> bool {
+        self.mode == DisplayMode::Hires
+    }
+
+    /// The selected planes as a bitmask.
+    pub fn selection(&self) -> u8 {
+        self.selected
+    }
+
+    pub fn select(&mut self, mask: u8) {
+        self.selected = mask & ((1 << PLANES) - 1);
+    }
+
+    /// Indices of the selected planes, in order.
+    pub fn selected_planes(&self) -> impl Iterator<Item = usize> + use<> {
+        let selected = self.selected;
+        (0..PLANES).filter(move |plane| selected & (1 << plane) != 0)
+    }
+
+    /// The size of the screen in the current mode's pixels.
+    pub fn size(&self) -> (usize, usize) {
+        self.mode.size()
+    }
+
+    /// Whether the pixel at (`x`, `y`) in the current mode's coordinates is lit on any plane.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let (scale_x, scale_y) = self.mode.scale();
+        self.color_raw(x * scale_x, y * scale_y) != 0
+    }
+
+    /// Flips the pixel at (`x`, `y`) in the current mode's coordinates on `plane`, returning
+    /// whether any lit framebuffer pixel was turned off.
+    pub fn toggle(&mut self, plane: usize, x: usize, y: usize) -> bool {
+        let (scale_x, scale_y) = self.mode.scale();
+        let mut collided = false;
+        for raw_y in y * scale_y..(y + 1) * scale_y {
+            for raw_x in x * scale_x..(x + 1) * scale_x {
+                let bit = raw_y * HIRES_WIDTH + raw_x;
+                if !self.planes[plane].remove(bit) {
+                    self.planes[plane].insert(bit);
+                } else {
+                    collided = true;
+                }
+            }
+        }
+        collided
+    }
+
+    /// The resolution the screen has to be shown at to be exact. This is the current mode's
+    /// size, except when lores content has been scrolled by half a pixel.
+    pub fn resolution(&self) -> (usize, usize) {
+        if self.is_block_aligned() {
+            self.size()
+        } else {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        }
+    }
+
+    /// The color of the pixel at (`x`, `y`) at [`Display::resolution`], see
+    /// [`Display::color_raw`].
+    pub fn sample(&self, x: usize, y: usize) -> u8 {
+        let (scale_x, scale_y) = if self.is_block_aligned() {
+            self.mode.scale()
+        } else {
+            (1, 1)
+        };
+        self.color_raw(x * scale_x, y * scale_y)
+    }
+
+    /// Whether every block of framebuffer pixels making up one of the current mode's pixels is a
+    /// single color.
+    fn is_block_aligned(&self) -> bool {
+        let (scale_x, scale_y) = self.mode.scale();
+        (0..HIRES_HEIGHT).step_by(scale_y).all(|y| {
+            (0..HIRES_WIDTH).step_by(scale_x).all(|x| {
+                let color = self.color_raw(x, y);
+                (y..y + scale_y).all(|raw_y| {
+                    (x..x + scale_x).all(|raw_x| self.color_raw(raw_x, raw_y) == color)
+                })
+            })
+        })
+    }
+
+    /// Moves the selected planes down by `rows` framebuffer pixels. Rows scrolled in at the
+    /// top are unlit.
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.shift(0, rows as isize);
+    }
+
+    /// Moves the selected planes up by `rows` framebuffer pixels. Rows scrolled in at the bottom
+    /// are unlit.
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.shift(0, -(rows as isize));
+    }
+
+    /// Moves the selected planes right by `columns` framebuffer pixels.
+    pub fn scroll_right(&mut self, columns: usize) {
+        self.shift(columns as isize, 0);
+    }
+
+    /// Moves the selected planes left by `columns` framebuffer pixels.
+    pub fn scroll_left(&mut self, columns: usize) {
+        self.shift(-(columns as isize), 0);
+    }
+
+    fn shift(&mut self, dx: isize, dy: isize) {
+        for plane in self.selected_planes() {
+            let mut shifted = BitSet::with_capacity(HIRES_WIDTH * HIRES_HEIGHT);
+            for bit in &self.planes[plane] {
+                let x = (bit % HIRES_WIDTH) as isize + dx;
+                let y = (bit / HIRES_WIDTH) as isize + dy;
+                if (0..HIRES_WIDTH as isize).contains(&x) && (0..HIRES_HEIGHT as isize).contains(&y)
+                {
+                    shifted.insert(y as usize * HIRES_WIDTH + x as usize);
+                }
+            }
+            self.planes[plane] = shifted;
+        }
+    }
+
+    /// The color of the framebuffer pixel at (`x`, `y`), regardless of mode: bit N is set if
+    /// the pixel is lit on plane N.
+    pub fn color_raw(&self, x: usize, y: usize) -> u8 {
+        (0..PLANES)
+            .filter(|plane| self.get_raw(*plane, x, y))
+            .fold(0, |color, plane| color | (1 << plane))
+    }
+
+    /// Whether the framebuffer pixel at (`x`, `y`) is lit on `plane`, regardless of mode.
+    pub fn get_raw(&self, plane: usize, x: usize, y: usize) -> bool {
+        x < HIRES_WIDTH && y < HIRES_HEIGHT && self.planes[plane].contains(y * HIRES_WIDTH + x)
+    }
+
+    pub fn set_raw(&mut self, plane: usize, x: usize, y: usize, lit: bool) {
+        let bit = y * HIRES_WIDTH + x;
+        if lit {
+            self.planes[plane].insert(bit);
+        } else {
+            self.planes[plane].remove(bit);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::{collections::BTreeSet, fmt};
+
+/// The platform a ROM most likely targets, based on the instructions it uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "CHIP-8"),
+            Platform::SuperChip => write!(f, "SUPER-CHIP"),
+            Platform::XoChip => write!(f, "XO-CHIP"),
+        }
+    }
+}
+
+/// The results of a quick static pass over a ROM, used to explain why it might not run correctly.
+#[derive(Clone, Debug, Default)]
+pub struct RomReport {
+    pub platform: Option<Platform>,
+    /// Number of distinct instructions reachable from the entry point.
+    pub reachable_instructions: usize,
+    /// (address, instruction) pairs for instructions that don't decode on any supported platform.
+    pub unknown_opcodes: Vec<(usize, u16)>,
+    /// (address, instruction) pairs for jumps and calls to odd addresses.
+    pub odd_jumps: Vec<(usize, u16)>,
+    pub recommendations: Vec<String>,
+}
+
+/// Statically analyzes a ROM loaded at `load_address`, following jumps, calls and skips from the
+/// entry point so that embedded sprite data isn't mistaken for code.
+pub fn diagnose(rom: &[u8], load_address: usize) -> RomReport {
+    let mut report = RomReport::default();
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![load_address];
+
+    let mut uses_shifts = false;
+    let mut uses_load_store = false;
+    let mut uses_jump_offset = false;
+    let mut uses_logic_ops = false;
+    let mut platform = Platform::Chip8;
+
+    while let Some(address) = pending.pop() {
+        if address < load_address || !visited.insert(address) {
+            continue;
+        }
+
+        let offset = address - load_address;
+        if offset + 1 >= rom.len() {
+            continue;
+        }
+
+        let instruction = ((rom[offset] as u16) << 8) | rom[offset + 1] as u16;
+        let next = address + 2;
+        let nnn = (instruction & 0x0FFF) as usize;
+
+        let Some(required) = classify(instruction) else {
+            report.unknown_opcodes.push((address, instruction));
+            continue;
+        };
+        platform = platform.max(required);
+
+        match instruction & 0xF000 {
+            0x0000 if instruction == 0x00EE || instruction == 0x00FD => {}
+            0x1000 | 0x2000 => {
+                if !nnn.is_multiple_of(2) {
+                    report.odd_jumps.push((address, instruction));
+                }
+                pending.push(nnn);
+                if instruction & 0xF000 == 0x2000 {
+                    pending.push(next);
+                }
+            }
+            // The second word is the address, not an instruction
+            0xF000 if instruction == 0xF000 => pending.push(next + 2),
+            0x3000 | 0x4000 | 0x5000 | 0x9000 | 0xE000 => {
+                pending.push(next);
+                // Skipping XO-CHIP's F000 NNNN jumps over both of its words
+                let skipped_long =
+                    rom.get(next - load_address..next - load_address + 2) == Some(&[0xF0, 0x00]);
+                pending.push(if skipped_long { next + 4 } else { next + 2 });
+            }
+            // The target depends on a register, so it can't be followed statically
+            0xB000 => uses_jump_offset = true,
+            _ => {
+                match instruction & 0xF00F {
+                    0x8006 | 0x800E => uses_shifts = true,
+                    0x8001..=0x8003 => uses_logic_ops = true,
+                    _ => {}
+                }
+                if matches!(instruction & 0xF0FF, 0xF055 | 0xF065) {
+                    uses_load_store = true;
+                }
+                pending.push(next);
+            }
+        }
+    }
+
+    report.reachable_instructions = visited.len();
+    report.platform = Some(platform);
+
+    if platform != Platform::Chip8 {
+        report.recommendations.push(format!(
+            "Uses {platform} instructions; run it with {platform} support enabled."
+        ));
+    }
+    if uses_shifts {
+        report.recommendations.push(String::from(
+            "Uses 8XY6/8XYE shifts; original CHIP-8 ROMs may expect them to shift VY (the shift-vy quirk).",
+        ));
+    }
+    if uses_load_store {
+        report.recommendations.push(String::from(
+            "Uses FX55/FX65; original CHIP-8 ROMs may expect them to increment I (the memory-increment quirk).",
+        ));
+    }
+    if uses_jump_offset {
+        report.recommendations.push(String::from(
+            "Uses BNNN jumps; original CHIP-8 ROMs may expect them to be offset by V0 (the jump-v0 quirk).",
+        ));
+    }
+    if uses_logic_ops && platform == Platform::Chip8 {
+        report.recommendations.push(String::from(
+            "Uses 8XY1/8XY2/8XY3; original CHIP-8 ROMs may expect VF to be reset by these (the vf-reset quirk).",
+        ));
+    }
+    if !report.odd_jumps.is_empty() {
+        report.recommendations.push(String::from(
+            "Jumps to odd addresses; the ROM may be self-modifying or expect a different load address.",
+        ));
+    }
+    if !report.unknown_opcodes.is_empty() {
+        report.recommendations.push(String::from(
+            "Contains unknown opcodes in reachable code; it may target an unsupported platform.",
+        ));
+    }
+
+    report
+}
+
+/// Returns the earliest platform that defines `instruction`, or None if none of them do.
+fn classify(instruction: u16) -> Option<Platform> {
+    let nibbles = (
+        (instruction & 0xF000) >> 12,
+        (instruction & 0x0F00) >> 8,
+        (instruction & 0x00F0) >> 4,
+        (instruction & 0x000F),
+    );
+
+    match nibbles {
+        (0x0, 0x0, 0xE, 0x0) | (0x0, 0x0, 0xE, 0xE) => Some(Platform::Chip8),
+        (0x0, 0x0, 0xC, _) | (0x0, 0x0, 0xF, 0xB..=0xF) => Some(Platform::SuperChip),
+        (0x0, 0x0, 0xD, _) => Some(Platform::XoChip),
+        (0x0, _, _, _) => Some(Platform::Chip8), // machine code routine
+        (0x5, _, _, 0x0) => Some(Platform::Chip8),
+        (0x5, _, _, 0x2) | (0x5, _, _, 0x3) => Some(Platform::XoChip),
+        (0x8, _, _, 0x0..=0x7) | (0x8, _, _, 0xE) => Some(Platform::Chip8),
+        (0x9, _, _, 0x0) => Some(Platform::Chip8),
+        (0x1..=0x4, _, _, _) | (0x6 | 0x7, _, _, _) | (0xA..=0xD, _, _, _) => Some(Platform::Chip8),
+        (0xE, _, 0x9, 0xE) | (0xE, _, 0xA, 0x1) => Some(Platform::Chip8),
+        (0xF, 0x0, 0x0, 0x0) | (0xF, _, 0x0, 0x1) | (0xF, 0x0, 0x0, 0x2) => Some(Platform::XoChip),
+        (0xF, _, 0x3, 0xA) => Some(Platform::XoChip),
+        (0xF, _, 0x3, 0x0) | (0xF, _, 0x7, 0x5) | (0xF, _, 0x8, 0x5) => Some(Platform::SuperChip),
+        (0xF, _, 0x0, 0x7 | 0xA)
+        | (0xF, _, 0x1, 0x5 | 0x8 | 0xE)
+        | (0xF, _, 0x2, 0x9)
+        | (0xF, _, 0x3, 0x3)
+        | (0xF, _, 0x5, 0x5)
+        | (0xF, _, 0x6, 0x5) => Some(Platform::Chip8),
+        _ => None,
+    }
+}
+
+impl fmt::Display for RomReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(platform) = self.platform {
+            writeln!(f, "Likely platform: {platform}")?;
+        }
+        writeln!(f, "Reachable instructions: {}", self.reachable_instructions)?;
+
+        for (address, instruction) in &self.unknown_opcodes {
+            writeln!(f, "Unknown opcode {instruction:04X} at {address:#05X}")?;
+        }
+        for (address, instruction) in &self.odd_jumps {
+            writeln!(f, "Odd-aligned jump {instruction:04X} at {address:#05X}")?;
+        }
+        for recommendation in &self.recommendations {
+            writeln!(f, "* {recommendation}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,445 @@
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The ROM doesn't fit in the memory available to programs.
+    RomTooLarge { size: usize, max: usize },
+    /// The ROM contains no data.
+    EmptyRom,
+    /// The ROM has an odd number of bytes, so it can't consist entirely of instructions.
+    OddLength { size: usize },
+    /// The data would extend past the end of emulator memory.
+    OutOfBounds {
+        address: usize,
+        size: usize,
+        memory_size: usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::RomTooLarge { size, max } => {
+                write!(
+                    f,
+                    "ROM is {size} bytes, but at most {max} bytes can be loaded"
+                )
+            }
+            LoadError::EmptyRom => write!(f, "ROM is empty"),
+            LoadError::OddLength { size } => write!(
+                f,
+                "ROM is {size} bytes; an odd length usually means the file is truncated or isn't a CHIP-8 program"
+            ),
+            LoadError::OutOfBounds {
+                address,
+                size,
+                memory_size,
+            } => write!(
+                f,
+                "{size} bytes at {address:#05X} would overflow memory ({memory_size} bytes)"
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// An error raised by the interpreter while executing a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// 00EE was executed with no subroutine to return from.
+    StackUnderflow { address: usize },
+    /// 2NNN was executed with the stack already at the limit set with
+    /// [`Emulator::set_stack_limit`](crate::Emulator::set_stack_limit).
+    StackOverflow { address: usize, limit: usize },
+    /// A 0NNN machine code routine was called, which isn't supported.
+    MachineCodeCall { address: usize, instruction: u16 },
+    /// The program counter ran off the end of memory.
+    ProgramCounterOutOfBounds { address: usize },
+    /// An opcode the interpreter doesn't recognize was executed, see
+    /// [`UnknownOpcodePolicy::Abort`](crate::UnknownOpcodePolicy::Abort).
+    UnknownOpcode { address: usize, instruction: u16 },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::StackUnderflow { address } => {
+                write!(
+                    f,
+                    "returned from subroutine with an empty stack at {address:#05X}"
+                )
+            }
+            RuntimeError::StackOverflow { address, limit } => {
+                write!(
+                    f,
+                    "called a subroutine with the stack full ({limit} levels) at {address:#05X}"
+                )
+            }
+            RuntimeError::MachineCodeCall {
+                address,
+                instruction,
+            } => write!(
+                f,
+                "attempted to call machine code routine {instruction:#06X} at {address:#05X}"
+            ),
+            RuntimeError::ProgramCounterOutOfBounds { address } => {
+                write!(f, "program counter out of bounds at {address:#05X}")
+            }
+            RuntimeError::UnknownOpcode {
+                address,
+                instruction,
+            } => write!(f, "unknown opcode {instruction:#06X} at {address:#05X}"),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "{error}"),
+            ConfigError::Parse(error) => write!(f, "invalid config: {error}"),
+            ConfigError::Serialize(error) => write!(f, "failed to write config: {error}"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(error: std::io::Error) -> Self {
+        ConfigError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        ConfigError::Parse(error)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(error: toml::ser::Error) -> Self {
+        ConfigError::Serialize(error)
+    }
+}
+
+/// A save state couldn't be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The data doesn't start with the save state header.
+    InvalidHeader,
+    /// The save state was written by an incompatible version of HachiEmu.
+    UnsupportedVersion(u8),
+    /// The data ended before the save state was complete.
+    Truncated,
+    /// The save state is well formed but describes an impossible machine state.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::InvalidHeader => write!(f, "not a HachiEmu save state"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save state version {version}")
+            }
+            SnapshotError::Truncated => write!(f, "save state is truncated"),
+            SnapshotError::Invalid(reason) => write!(f, "invalid save state: {reason}"),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+/// A WAV file couldn't be loaded.
+#[derive(Debug)]
+pub enum WavError {
+    Io(std::io::Error),
+    /// The file isn't a well-formed WAV file.
+    Invalid(&'static str),
+    /// The samples are in an encoding that isn't supported, given as the WAV format code and
+    /// bits per sample.
+    Unsupported {
+        encoding: u16,
+        bits: u16,
+    },
+}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavError::Io(error) => write!(f, "{error}"),
+            WavError::Invalid(reason) => write!(f, "invalid WAV file: {reason}"),
+            WavError::Unsupported { encoding, bits } => write!(
+                f,
+                "unsupported WAV sample format {encoding} with {bits} bits per sample"
+            ),
+        }
+    }
+}
+
+impl Error for WavError {}
+
+impl From<std::io::Error> for WavError {
+    fn from(error: std::io::Error) -> Self {
+        WavError::Io(error)
+    }
+}
+
+/// A quirk was referred to by a name that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownQuirkError(pub String);
+
+impl fmt::Display for UnknownQuirkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown quirk \"{}\"", self.0)
+    }
+}
+
+impl Error for UnknownQuirkError {}
+
+/// A policy was referred to by a name that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPolicyError(pub String);
+
+impl fmt::Display for UnknownPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown policy \"{}\"", self.0)
+    }
+}
+
+impl Error for UnknownPolicyError {}
+
+/// A timing mode was referred to by a name that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownTimingError(pub String);
+
+impl fmt::Display for UnknownTimingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown timing \"{}\"", self.0)
+    }
+}
+
+impl Error for UnknownTimingError {}
+
+/// A random number generator was referred to by a name that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownRngError(pub String);
+
+impl fmt::Display for UnknownRngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown random number generator \"{}\"", self.0)
+    }
+}
+
+impl Error for UnknownRngError {}
+
+/// A palette preset was referred to by a name that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPaletteError(pub String);
+
+impl fmt::Display for UnknownPaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown palette \"{}\"", self.0)
+    }
+}
+
+impl Error for UnknownPaletteError {}
+
+/// A keyboard layout was referred to by a name that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLayoutError(pub String);
+
+impl fmt::Display for UnknownLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown keyboard layout \"{}\"", self.0)
+    }
+}
+
+impl Error for UnknownLayoutError {}
+
+/// A variant was referred to by a name that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVariantError(pub String);
+
+impl fmt::Display for UnknownVariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown variant \"{}\"", self.0)
+    }
+}
+
+impl Error for UnknownVariantError {}
+
+/// A color in the config file isn't written as `#RRGGBB`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(pub String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid color \"{}\", expected #RRGGBB", self.0)
+    }
+}
+
+impl Error for ParseColorError {}
+
+/// A rotation in the config file isn't 0, 90, 180 or 270 degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidRotationError(pub u16);
+
+impl fmt::Display for InvalidRotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid rotation {}, expected 0, 90, 180 or 270", self.0)
+    }
+}
+
+impl Error for InvalidRotationError {}
+
+/// A rule condition in the config file couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseConditionError {
+    pub text: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ParseConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid condition \"{}\": {}", self.text, self.reason)
+    }
+}
+
+impl Error for ParseConditionError {}
+
+/// A golden run couldn't be loaded, executed or didn't produce the expected state.
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// No ROM with the recorded hash could be found.
+    RomNotFound {
+        sha1: String,
+    },
+    /// The ROM named by the golden run doesn't have the recorded hash.
+    RomMismatch {
+        expected: String,
+        actual: String,
+    },
+    Load(LoadError),
+    /// The run finished in a different state than recorded.
+    StateMismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Io(error) => write!(f, "{error}"),
+            GoldenError::Parse(error) => write!(f, "failed to parse golden run: {error}"),
+            GoldenError::Serialize(error) => write!(f, "failed to write golden run: {error}"),
+            GoldenError::RomNotFound { sha1 } => write!(f, "no ROM found with SHA-1 {sha1}"),
+            GoldenError::RomMismatch { expected, actual } => {
+                write!(f, "ROM has SHA-1 {actual}, expected {expected}")
+            }
+            GoldenError::Load(error) => write!(f, "failed to load ROM: {error}"),
+            GoldenError::StateMismatch { expected, actual } => {
+                write!(f, "final state hash is {actual}, expected {expected}")
+            }
+        }
+    }
+}
+
+impl Error for GoldenError {}
+
+impl From<std::io::Error> for GoldenError {
+    fn from(error: std::io::Error) -> Self {
+        GoldenError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for GoldenError {
+    fn from(error: toml::de::Error) -> Self {
+        GoldenError::Parse(error)
+    }
+}
+
+impl From<toml::ser::Error> for GoldenError {
+    fn from(error: toml::ser::Error) -> Self {
+        GoldenError::Serialize(error)
+    }
+}
+
+impl From<LoadError> for GoldenError {
+    fn from(error: LoadError) -> Self {
+        GoldenError::Load(error)
+    }
+}
+
+/// A movie file couldn't be read or written, or doesn't match the ROM.
+#[derive(Debug)]
+pub enum MovieError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The movie was recorded on a different ROM.
+    RomMismatch {
+        expected: String,
+        actual: String,
+    },
+    /// The ROM couldn't be loaded to play the movie back.
+    Load(LoadError),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::Io(error) => write!(f, "{error}"),
+            MovieError::Parse(error) => write!(f, "failed to parse movie: {error}"),
+            MovieError::Serialize(error) => write!(f, "failed to write movie: {error}"),
+            MovieError::RomMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "ROM has SHA-1 {actual}, but the movie was recorded on {expected}"
+                )
+            }
+            MovieError::Load(error) => write!(f, "failed to load ROM: {error}"),
+        }
+    }
+}
+
+impl Error for MovieError {}
+
+impl From<std::io::Error> for MovieError {
+    fn from(error: std::io::Error) -> Self {
+        MovieError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for MovieError {
+    fn from(error: toml::de::Error) -> Self {
+        MovieError::Parse(error)
+    }
+}
+
+impl From<toml::ser::Error> for MovieError {
+    fn from(error: toml::ser::Error) -> Self {
+        MovieError::Serialize(error)
+    }
+}
+
+impl From<LoadError> for MovieError {
+    fn from(error: LoadError) -> Self {
+        MovieError::Load(error)
+    }
+}
@@ -0,0 +1,1374 @@
+mod audio;
+mod audio_backend;
+mod bezel;
+mod blend;
+mod crt;
+mod gamepad;
+mod gif_recorder;
+mod hotkeys;
+mod hud;
+mod keymap;
+mod keypad_panel;
+mod keys;
+mod menu;
+mod palette;
+mod phosphor;
+mod rotation;
+mod scaler;
+mod screenshot;
+mod theme;
+mod touch;
+mod turbo;
+mod video_recorder;
+mod wav_recorder;
+
+pub use audio::Waveform;
+#[cfg(feature = "sdl2")]
+pub use audio_backend::Sdl2Backend;
+pub use audio_backend::{
+    AudioBackend, AudioChannel, AudioOutput, Clip, MacroquadBackend, Mixer, SilentBackend,
+};
+pub use bezel::BezelConfig;
+pub use crt::CrtConfig;
+pub use gamepad::GamepadButton;
+pub(crate) use gamepad::{default_gamepad_buttons, deserialize_gamepad_buttons};
+pub use hotkeys::{Binding, Conflict, HotkeyAction, Hotkeys};
+pub use keymap::{KeyboardLayout, Keymap};
+pub use palette::{DEFAULT_PALETTE, PalettePreset, Rgb};
+pub(crate) use palette::{deserialize_optional_palette, deserialize_palette};
+pub use rotation::Rotation;
+pub use scaler::Scaler;
+pub use theme::Theme;
+pub use touch::{TouchConfig, TouchRegion};
+pub use turbo::TurboConfig;
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use macroquad::{prelude::*, texture::Image};
+
+use crate::{
+    Chip8Variant, Emulator, HaltReason, MEGACHIP_HEIGHT, MEGACHIP_WIDTH, NUM_INPUT_KEYS, RPL_FLAGS,
+    SCREEN_HEIGHT, SCREEN_WIDTH, SPLASH_ROM,
+    config::{AudioConfig, Config},
+    mmio::Pointer,
+    movie::{MOVIE_EXTENSION, Movie, MoviePlayback},
+    rom_db::RomDatabase,
+    rules::{RuleAction, RuleEngine},
+    screen_dump::{screen_text, write_pbm},
+    snapshot::Snapshot,
+};
+use audio::Audio;
+use bezel::Bezel;
+use blend::FrameBlender;
+use crt::Crt;
+use gamepad::GamepadPort;
+use gif_recorder::GifRecorder;
+use hud::{Hud, status_markers};
+use menu::{Menu, MenuResult};
+use palette::ColorLevels;
+use phosphor::Phosphor;
+use turbo::Turbo;
+use video_recorder::{VideoRecorder, add_sound};
+use wav_recorder::WavRecorder;
+
+/// The largest multiple of 64x32 the window size hotkeys go up to.
+const MAX_WINDOW_SCALE: i32 = 16;
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 8.0;
+const NOTIFICATION_SECONDS: f32 = 2.0;
+const FONT_SIZE: f32 = 20.0;
+const MARGIN: f32 = 8.0;
+/// The grid is only drawn once pixels are at least this big, so it doesn't drown them out.
+const MIN_GRID_CELL: f32 = 6.0;
+const GRID_COLOR: Color = Color::new(0.5, 0.5, 0.5, 0.3);
+/// Thickness of the border flashed while the sound timer runs.
+const VISUAL_BELL_WIDTH: f32 = 8.0;
+/// How often the config file is checked for changes to the controls.
+const CONFIG_CHECK_SECONDS: f32 = 1.0;
+/// How much time each frame advanced by hand covers.
+const FRAME_ADVANCE_SECONDS: f32 = 1.0 / 60.0;
+
+/// The windowed frontend: renders the emulator with macroquad and handles input, hotkeys and
+/// the settings menu.
+pub struct Frontend {
+    config: Config,
+    config_path: Option<PathBuf>,
+    rom_path: Option<PathBuf>,
+    /// Whether ROMs opened while running are looked up in the ROM database.
+    rom_db: bool,
+    /// Colors used instead of the config's palette without being saved, such as a ROM's own.
+    palette_override: Option<[Rgb; 4]>,
+    /// Audio settings used instead of the config's without being saved, such as a ROM's own.
+    audio_override: Option<AudioConfig>,
+    /// Keys used instead of the config's keymap without being saved, such as a ROM's own.
+    keymap_override: Option<Keymap>,
+    /// Keys the gamepad's stick presses instead of the configured ones, such as a ROM's own.
+    stick_override: Option<[u8; 4]>,
+    /// The theme file last applied, which the next theme hotkey moves on from.
+    theme_path: Option<PathBuf>,
+
+    paused: bool,
+    /// While stepping frame by frame, the keys held on the next frame advanced.
+    frame_advance: Option<[bool; NUM_INPUT_KEYS]>,
+    frame_advance_requested: bool,
+    speed: f32,
+    show_info_panel: bool,
+    hud: Option<Hud>,
+    muted: bool,
+    menu: Option<Menu>,
+    notification: Option<(String, f32)>,
+    rules: RuleEngine,
+    screenshot_requested: bool,
+    /// The RPL flags as last loaded from or saved to disk.
+    saved_rpl_flags: [u8; RPL_FLAGS],
+    /// When the config file was last changed, as of the last check.
+    config_modified: Option<SystemTime>,
+    config_check_time: f32,
+    gamepad: GamepadPort,
+    /// The input profiles' gamepads, by profile name.
+    profile_gamepads: BTreeMap<String, GamepadPort>,
+    turbo: Turbo,
+    audio: Audio,
+    phosphor: Phosphor,
+    blender: FrameBlender,
+    gif_recorder: Option<GifRecorder>,
+    gif_requested: bool,
+    video_recorder: Option<VideoRecorder>,
+    video_requested: bool,
+    /// The sound of the GIF or video being recorded.
+    wav_recorder: Option<WavRecorder>,
+    /// The movie being recorded, and where it's saved.
+    movie_recording: Option<(PathBuf, Movie)>,
+    /// The movie being played back, which holds the keys until it ends.
+    movie_playback: Option<MoviePlayback>,
+    bezel: Option<Bezel>,
+}
+
+impl Frontend {
+    pub fn new(config: Config) -> Frontend {
+        for conflict in config.hotkeys.conflicts(&config.keymap) {
+            eprintln!("Hotkey conflict: {conflict}");
+        }
+
+        Frontend {
+            rules: RuleEngine::new(config.rules.clone()),
+            audio: Audio::new(config.audio.output.backend()),
+            config,
+            config_path: None,
+            rom_path: None,
+            rom_db: true,
+            palette_override: None,
+            audio_override: None,
+            keymap_override: None,
+            stick_override: None,
+            theme_path: None,
+
+            paused: false,
+            frame_advance: None,
+            frame_advance_requested: false,
+            speed: 1.0,
+            show_info_panel: false,
+            hud: None,
+            muted: false,
+            menu: None,
+            notification: None,
+            screenshot_requested: false,
+            saved_rpl_flags: [0; RPL_FLAGS],
+            config_modified: None,
+            config_check_time: 0.0,
+            gamepad: GamepadPort::default(),
+            profile_gamepads: BTreeMap::new(),
+            turbo: Turbo::default(),
+            phosphor: Phosphor::new(),
+            blender: FrameBlender::new(),
+            gif_recorder: None,
+            gif_requested: false,
+            video_recorder: None,
+            video_requested: false,
+            wav_recorder: None,
+            movie_recording: None,
+            movie_playback: None,
+            bezel: None,
+        }
+    }
+
+    /// Sets the file that settings changed from the menu are saved to.
+    pub fn with_config_path(mut self, path: PathBuf) -> Frontend {
+        self.config_path = Some(path);
+        self
+    }
+
+    /// Sets the path of the running ROM, which save states and RPL flags are stored alongside.
+    pub fn with_rom_path(mut self, path: PathBuf) -> Frontend {
+        self.rom_path = Some(path);
+        self
+    }
+
+    /// Shows the screen in `palette` for this session, leaving the config's palette as it is.
+    pub fn with_palette(mut self, palette: [Rgb; 4]) -> Frontend {
+        self.palette_override = Some(palette);
+        self
+    }
+
+    /// Plays sound with `audio`'s settings for this session, leaving the config's as they are.
+    pub fn with_audio(mut self, audio: AudioConfig) -> Frontend {
+        self.audio_override = Some(audio);
+        self
+    }
+
+    /// Plays with `keymap`'s keys for this session, leaving the config's keymap as it is.
+    pub fn with_keymap(mut self, keymap: Keymap) -> Frontend {
+        for conflict in self.config.hotkeys.conflicts(&keymap) {
+            eprintln!("Hotkey conflict: {conflict}");
+        }
+        self.keymap_override = Some(keymap);
+        self
+    }
+
+    /// Has the gamepad's stick press `keys`, for up, down, left and right, for this session.
+    pub fn with_stick(mut self, keys: [u8; 4]) -> Frontend {
+        self.stick_override = Some(keys);
+        self
+    }
+
+    /// Records the theme file that was applied to the config, so the next theme hotkey carries
+    /// on from it.
+    pub fn with_theme_path(mut self, path: PathBuf) -> Frontend {
+        self.theme_path = Some(path);
+        self
+    }
+
+    /// Plays sound through `backend` instead of the one chosen in the config, such as a
+    /// [`Mixer`] feeding an audio device the application already owns.
+    pub fn with_audio_backend(mut self, backend: Box<dyn AudioBackend>) -> Frontend {
+        self.audio = Audio::new(backend);
+        self
+    }
+
+    /// Plays `movie` back, taking over the keypad until it ends. The emulator should have been
+    /// restarted on the movie's ROM with [`Movie::restart`], whose RPL flags are kept rather
+    /// than the ones saved on disk.
+    pub fn with_movie(mut self, movie: Movie) -> Frontend {
+        self.movie_playback = Some(movie.play());
+        self
+    }
+
+    /// Whether ROMs opened while running, such as dropped ones, get the ROM database's settings
+    /// for them. Without it they keep the current ones.
+    pub fn with_rom_db(mut self, enabled: bool) -> Frontend {
+        self.rom_db = enabled;
+        self
+    }
+
+    /// Starts with all sound muted.
+    pub fn with_muted(mut self, muted: bool) -> Frontend {
+        self.muted = muted;
+        self
+    }
+
+    /// Runs the emulator in a window until it halts, returning the reason it stopped.
+    pub async fn run(&mut self, emulator: &mut Emulator) -> HaltReason {
+        if let Some((x, y)) = self.config.window.position {
+            miniquad::window::set_window_position(x, y);
+        }
+        match self.movie_playback {
+            Some(_) => self.saved_rpl_flags = emulator.rpl_flags(),
+            None => self.load_rpl_flags(emulator),
+        }
+
+        let reason = self.run_loop(emulator).await;
+        self.stop_gif();
+        self.stop_video();
+        self.stop_movie();
+        self.remember_window();
+        reason
+    }
+
+    async fn run_loop(&mut self, emulator: &mut Emulator) -> HaltReason {
+        prevent_quit();
+
+        let mut image = Image::empty();
+        let mut texture = Texture2D::empty();
+        let mut screen_rect = Rect::new(0.0, 0.0, screen_width(), screen_height());
+        let mut keypad_rect = None;
+        let crt = Crt::new()
+            .inspect_err(|error| eprintln!("Failed to compile the CRT shader: {error}"))
+            .ok();
+        self.load_bezel();
+
+        loop {
+            if is_quit_requested() {
+                return HaltReason::UserQuit;
+            }
+
+            if let Some(path) = get_dropped_files().into_iter().find_map(|file| file.path) {
+                self.open_rom(path, emulator);
+            }
+            self.reload_controls();
+
+            if let Some(menu) = &mut self.menu {
+                match menu.update(&mut self.config) {
+                    MenuResult::None => {}
+                    MenuResult::Close => self.menu = None,
+                    MenuResult::Reset => {
+                        emulator.reset();
+                        self.menu = None;
+                    }
+                    MenuResult::Quit => return HaltReason::UserQuit,
+                    MenuResult::SettingsChanged => {
+                        // Settings chosen in the menu take over from the ROM's own
+                        self.audio_override = None;
+                        self.save_config();
+                    }
+                    MenuResult::KeymapChanged => {
+                        self.keymap_override = None;
+                        self.save_config();
+                    }
+                    MenuResult::Variant(variant) => {
+                        self.switch_variant(variant, emulator);
+                        self.menu = None;
+                    }
+                }
+            } else {
+                for action in self.config.hotkeys.pressed() {
+                    self.perform(action, emulator);
+                }
+            }
+
+            let gamepad_keys = self.poll_gamepads();
+            let window = Rect::new(0.0, 0.0, screen_width(), screen_height());
+            let touch_keys = touch::held(&self.config.touch, window, keypad_rect);
+            let extra_keys = std::array::from_fn(|key| gamepad_keys[key] || touch_keys[key]);
+            let panel_keys = keypad_rect.map_or([false; NUM_INPUT_KEYS], keypad_panel::held);
+            if self.menu.is_none() {
+                self.edit_frame_advance_keys();
+            }
+            let advancing = std::mem::take(&mut self.frame_advance_requested);
+            if (!self.paused || advancing) && self.menu.is_none() {
+                // Spread the frame's instruction budget over slices, interleaved with timer ticks
+                let slices = self.config.emulation.frame_slices.max(1);
+                let frame_time = if advancing {
+                    FRAME_ADVANCE_SECONDS
+                } else {
+                    get_frame_time() * self.speed
+                };
+                let slice_time = frame_time / slices as f32;
+                // The keyboard only changes between frames, so every slice sees the same keys
+                if self.movie_playback.is_none() {
+                    match self.frame_advance {
+                        Some(keys) if advancing => emulator.key_states = keys,
+                        _ => {
+                            Self::poll_input(
+                                emulator,
+                                screen_rect,
+                                self.config.display.rotation,
+                                self.keymap(),
+                                extra_keys,
+                                panel_keys,
+                            );
+                            self.turbo.apply(
+                                &mut emulator.key_states,
+                                &self.config.turbo,
+                                frame_time,
+                            );
+                        }
+                    }
+                }
+                for _ in 0..slices {
+                    let frame_time = match self.movie_playback.as_mut().map(Iterator::next) {
+                        Some(Some((frame_time, keys))) => {
+                            emulator.key_states = keys;
+                            frame_time
+                        }
+                        Some(None) => {
+                            self.movie_playback = None;
+                            self.notify("Movie finished");
+                            break;
+                        }
+                        None => {
+                            if let Some((_, movie)) = &mut self.movie_recording {
+                                movie.record(slice_time, &emulator.key_states);
+                            }
+                            slice_time
+                        }
+                    };
+                    match emulator.update(frame_time) {
+                        Ok(()) => {}
+                        Err(HaltReason::UnknownOpcode {
+                            address,
+                            instruction,
+                        }) => {
+                            self.paused = true;
+                            self.notify(&format!(
+                                "Paused on unknown opcode {instruction:04X} at {address:#05X}"
+                            ));
+                        }
+                        Err(reason) => return reason,
+                    }
+
+                    self.check_rules(emulator);
+                    if self.paused && !advancing {
+                        break;
+                    }
+                }
+                self.save_rpl_flags(emulator);
+            }
+            let silent = self.paused || self.menu.is_some() || self.muted;
+            let sounding = emulator.take_sound();
+            let audio_config = self.audio_override.as_ref().unwrap_or(&self.config.audio);
+            self.audio.update(emulator, sounding, silent, audio_config);
+
+            // Redraw the window graphics
+            self.redraw_screen(emulator, &mut image);
+            if self.screenshot_requested {
+                self.screenshot_requested = false;
+                self.save_screenshot(&image);
+            }
+            self.record_gif(&image);
+            self.record_video(&image);
+            self.record_wav();
+            clear_background(BLACK);
+
+            let scaler = self.config.display.scaler;
+            let scaled = scaler.apply(&image);
+            if texture.size() != vec2(scaled.width as f32, scaled.height as f32) {
+                texture = Texture2D::from_image(&scaled);
+            } else {
+                texture.update(&scaled);
+            }
+            texture.set_filter(scaler.filter());
+            let rotation = self.config.display.rotation;
+            let (width, height) = (image.width() as f32, image.height() as f32);
+            let (width, height) = if rotation.swaps_axes() {
+                (height, width)
+            } else {
+                (width, height)
+            };
+            let mut area = Rect::new(0.0, 0.0, screen_width(), screen_height());
+            keypad_rect = None;
+            if self.config.display.keypad_panel {
+                let (display_area, panel) = keypad_panel::layout(area, width, height);
+                area = display_area;
+                keypad_rect = Some(panel);
+                keypad_panel::draw(panel, &emulator.key_states);
+            }
+            if let Some(bezel) = &self.bezel {
+                area = bezel.draw(&self.config.display.bezel, area);
+            }
+            screen_rect =
+                Self::screen_rect(area, width, height, self.config.display.integer_scaling);
+            // The texture is turned around its center, so it's drawn where it would cover the
+            // screen area before being turned
+            let unrotated_size = if rotation.swaps_axes() {
+                vec2(screen_rect.h, screen_rect.w)
+            } else {
+                screen_rect.size()
+            };
+            let position = screen_rect.center() - unrotated_size / 2.0;
+            let crt = crt.as_ref().filter(|_| self.config.display.crt.enabled);
+            if let Some(crt) = crt {
+                crt.use_material(&self.config.display.crt, image.height());
+            }
+            draw_texture_ex(
+                &texture,
+                position.x,
+                position.y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(unrotated_size),
+                    source: None,
+                    rotation: rotation.radians(),
+                    flip_x: false,
+                    flip_y: false,
+                    pivot: None,
+                },
+            );
+            if crt.is_some() {
+                gl_use_default_material();
+            }
+            if self.config.display.pixel_grid {
+                Self::draw_pixel_grid(emulator, screen_rect, rotation);
+            }
+            if self.config.display.visual_bell && emulator.sound_timer() > 0 {
+                // macroquad draws half the given thickness, inside the rectangle
+                draw_rectangle_lines(
+                    0.0,
+                    0.0,
+                    screen_width(),
+                    screen_height(),
+                    VISUAL_BELL_WIDTH * 2.0,
+                    self.palette()[1].into(),
+                );
+            }
+
+            if self.show_info_panel {
+                Self::draw_info_panel(emulator);
+            }
+            let recording = self.gif_recorder.is_some()
+                || self.video_recorder.is_some()
+                || self.movie_recording.is_some();
+            let markers = status_markers(self.paused, self.speed, recording);
+            if let Some(hud) = &mut self.hud {
+                let title = match self.rom_path.as_ref().and_then(|path| path.file_name()) {
+                    Some(rom_name) => format!("{} {markers}", rom_name.to_string_lossy()),
+                    None => markers.clone(),
+                };
+                hud.update(emulator, get_frame_time());
+                hud.draw(emulator, title.trim_end(), self.speed, self.muted);
+            }
+
+            if let Some(menu) = &self.menu {
+                menu.draw(&self.config);
+            } else if !markers.is_empty() {
+                draw_text(&markers, MARGIN, MARGIN + FONT_SIZE, FONT_SIZE, YELLOW);
+            }
+
+            self.draw_notification();
+
+            next_frame().await;
+        }
+    }
+
+    /// The largest part of `area` a `width` by `height` screen fits in without changing its
+    /// shape, centered so that the rest is left as black bars. With `integer_scaling`, the screen
+    /// is only scaled by whole multiples of the display's physical pixels, so that every pixel is
+    /// the same size even on high-DPI displays.
+    fn screen_rect(area: Rect, width: f32, height: f32, integer_scaling: bool) -> Rect {
+        let dpi_scale = screen_dpi_scale();
+        let mut scale = (area.w / width).min(area.h / height) * dpi_scale;
+        if integer_scaling {
+            scale = scale.floor().max(1.0);
+        }
+        let (width, height) = (width * scale / dpi_scale, height * scale / dpi_scale);
+        // Aligned to physical pixels so that edges stay sharp
+        let align = |position: f32| (position * dpi_scale).floor() / dpi_scale;
+        Rect::new(
+            align(area.x + (area.w - width) / 2.0),
+            align(area.y + (area.h - height) / 2.0),
+            width,
+            height,
+        )
+    }
+
+    /// Copies the keypad's state to the emulator, as mapped by `keymap` and combined with the
+    /// keys held on the gamepads, by input profiles or by touching the window, along with the
+    /// pointer's position
+    /// within the emulated screen at `screen_rect`. Directions are turned to match the screen's `rotation`, except on the keypad
+    /// panel, whose keys are labeled.
+    fn poll_input(
+        emulator: &mut Emulator,
+        screen_rect: Rect,
+        rotation: Rotation,
+        keymap: &Keymap,
+        extra_keys: [bool; NUM_INPUT_KEYS],
+        panel_keys: [bool; NUM_INPUT_KEYS],
+    ) {
+        for (key_index, held) in keymap.held().into_iter().enumerate() {
+            emulator.key_states[rotation.remap_key(key_index as u8) as usize] =
+                held || extra_keys[key_index];
+        }
+        for (state, pressed) in emulator.key_states.iter_mut().zip(panel_keys) {
+            *state |= pressed;
+        }
+
+        if emulator.mmio_base().is_some() {
+            let (x, y) = mouse_position();
+            let (width, height) = emulator.display_size();
+            let buttons = [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+                .into_iter()
+                .enumerate()
+                .fold(0, |buttons, (bit, button)| {
+                    buttons | ((is_mouse_button_down(button) as u8) << bit)
+                });
+            let (x, y) = rotation.unrotate((
+                (x - screen_rect.x) / screen_rect.w,
+                (y - screen_rect.y) / screen_rect.h,
+            ));
+            emulator.set_pointer(Pointer {
+                x: (x * width as f32).clamp(0.0, width as f32 - 1.0) as u8,
+                y: (y * height as f32).clamp(0.0, height as f32 - 1.0) as u8,
+                buttons,
+            });
+        }
+    }
+
+    fn perform(&mut self, action: HotkeyAction, emulator: &mut Emulator) {
+        match action {
+            HotkeyAction::Menu => self.menu = Some(Menu::new()),
+            HotkeyAction::Pause => {
+                self.paused = !self.paused;
+                self.frame_advance = None;
+            }
+            HotkeyAction::FrameAdvance => {
+                if self.frame_advance.is_some() {
+                    self.frame_advance_requested = true;
+                } else {
+                    self.paused = true;
+                    self.frame_advance = Some([false; NUM_INPUT_KEYS]);
+                    self.notify("Frame advance: toggle keys with the keypad");
+                }
+            }
+            HotkeyAction::Reset => {
+                emulator.reset();
+                self.notify("Reset");
+            }
+            HotkeyAction::SaveState => self.save_state(emulator),
+            HotkeyAction::Screenshot => self.screenshot_requested = true,
+            HotkeyAction::DumpScreen => self.dump_screen(emulator),
+            HotkeyAction::RecordGif => match self.gif_recorder {
+                Some(_) => self.stop_gif(),
+                // Started once the next frame has been drawn, which sets the GIF's size
+                None => self.gif_requested = true,
+            },
+            HotkeyAction::RecordVideo => match self.video_recorder {
+                Some(_) => self.stop_video(),
+                None => self.video_requested = true,
+            },
+            HotkeyAction::RecordMovie => match self.movie_recording {
+                Some(_) => self.stop_movie(),
+                None => self.start_movie(emulator),
+            },
+            HotkeyAction::LoadState => self.load_state(emulator),
+            HotkeyAction::SpeedUp => self.set_speed(self.speed * 2.0),
+            HotkeyAction::SpeedDown => self.set_speed(self.speed / 2.0),
+            HotkeyAction::NormalSpeed => self.set_speed(1.0),
+            HotkeyAction::InfoPanel => self.show_info_panel = !self.show_info_panel,
+            HotkeyAction::Mute => {
+                self.muted = !self.muted;
+                self.notify(if self.muted { "Muted" } else { "Unmuted" });
+            }
+            HotkeyAction::WindowLarger => self.step_window_scale(1),
+            HotkeyAction::WindowSmaller => self.step_window_scale(-1),
+            HotkeyAction::Hud => {
+                self.hud = match self.hud {
+                    Some(_) => None,
+                    None => Some(Hud::new(emulator)),
+                }
+            }
+            HotkeyAction::NextScaler => {
+                self.config.display.scaler = self.config.display.scaler.next();
+                self.notify(&format!("Scaler: {}", self.config.display.scaler));
+                self.save_config();
+            }
+            HotkeyAction::FrameBlending => {
+                let display = &mut self.config.display;
+                display.blend_frames = match display.blend_frames {
+                    ..=1 => 2,
+                    2 => 3,
+                    _ => 1,
+                };
+                match display.blend_frames {
+                    1 => self.notify("Frame blending: off"),
+                    frames => self.notify(&format!("Frame blending: {frames} frames")),
+                }
+                self.save_config();
+            }
+            HotkeyAction::PixelGrid => {
+                let display = &mut self.config.display;
+                display.pixel_grid = !display.pixel_grid;
+                let state = if display.pixel_grid { "on" } else { "off" };
+                self.notify(&format!("Pixel grid: {state}"));
+                self.save_config();
+            }
+            HotkeyAction::KeypadPanel => {
+                let display = &mut self.config.display;
+                display.keypad_panel = !display.keypad_panel;
+                let state = if display.keypad_panel { "on" } else { "off" };
+                self.notify(&format!("Keypad panel: {state}"));
+                self.save_config();
+            }
+            HotkeyAction::Fullscreen => {
+                let window = &mut self.config.window;
+                window.fullscreen = !window.fullscreen;
+                set_fullscreen(window.fullscreen);
+                self.save_config();
+            }
+            HotkeyAction::NextTheme => self.next_theme(),
+            HotkeyAction::NextPalette => {
+                let preset = PalettePreset::after(&self.palette());
+                self.config.display.palette = preset.colors();
+                self.palette_override = None;
+                self.notify(&format!("Palette: {preset}"));
+                self.save_config();
+            }
+        }
+    }
+
+    /// Switches to the variant's quirks and platform, then restarts the program from a fresh
+    /// copy of the ROM, since it may load elsewhere or have modified itself.
+    fn switch_variant(&mut self, variant: Chip8Variant, emulator: &mut Emulator) {
+        let mut profile = variant.profile();
+        profile.timing = emulator.profile().timing;
+        emulator.set_profile(profile);
+        emulator.reset();
+
+        if let Some(rom_path) = &self.rom_path {
+            let result = std::fs::read(rom_path)
+                .map_err(|error| error.to_string())
+                .and_then(|data| {
+                    emulator
+                        .load_program(&data)
+                        .map_err(|error| error.to_string())
+                });
+            if let Err(error) = result {
+                self.notify(&format!("Failed to reload the ROM: {error}"));
+                return;
+            }
+        }
+        self.notify(&format!("Variant: {variant}"));
+    }
+
+    /// Switches to the ROM at `path`, such as one dropped on the window, with the settings the
+    /// ROM database has for it or else the current ones.
+    fn open_rom(&mut self, path: PathBuf, emulator: &mut Emulator) {
+        let program = match std::fs::read(&path) {
+            Ok(program) => program,
+            Err(error) => {
+                self.notify(&format!("Failed to read {}: {error}", path.display()));
+                return;
+            }
+        };
+
+        let rom_db = if self.rom_db {
+            RomDatabase::with_user_entries().unwrap_or_else(|_| RomDatabase::bundled())
+        } else {
+            RomDatabase::default()
+        };
+        let known = rom_db.lookup(&program);
+        if let Some(profile) = known.and_then(|known| known.profile().ok()) {
+            emulator.set_profile(profile);
+        }
+        emulator.reset();
+        if let Err(error) = emulator.load_program(&program) {
+            self.notify(&format!("Failed to load {}: {error}", path.display()));
+            return;
+        }
+
+        self.notify(&format!("Running {}", path.display()));
+        self.rom_path = Some(path);
+        self.palette_override = known.and_then(|known| known.palette);
+        self.audio_override = known.and_then(|known| known.audio(&self.config.audio));
+        self.keymap_override = known.and_then(|known| known.keymap.clone());
+        self.report_conflicts();
+        self.stick_override = known.and_then(|known| known.stick);
+        if known.is_some_and(|known| known.mute) {
+            self.muted = true;
+        }
+        self.paused = false;
+        emulator.set_rpl_flags([0; RPL_FLAGS]);
+        self.saved_rpl_flags = [0; RPL_FLAGS];
+        self.load_rpl_flags(emulator);
+    }
+
+    /// Applies the theme after the current one in the themes folder, saving its settings.
+    fn next_theme(&mut self) {
+        let themes = Theme::find_all();
+        let next = self
+            .theme_path
+            .as_ref()
+            .and_then(|current| themes.iter().position(|path| path == current))
+            .map_or(0, |index| (index + 1) % themes.len());
+        let Some(path) = themes.get(next).cloned() else {
+            let dir = Theme::dir().unwrap_or_default();
+            self.notify(&format!("No themes found in {}", dir.display()));
+            return;
+        };
+
+        match Theme::load(&path) {
+            Ok(theme) => {
+                theme.apply(&mut self.config.display);
+                self.palette_override = None;
+                self.load_bezel();
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                self.notify(&format!("Theme: {name}"));
+                self.theme_path = Some(path);
+                self.save_config();
+            }
+            Err(error) => self.notify(&format!("Failed to load {}: {error}", path.display())),
+        }
+    }
+
+    fn load_bezel(&mut self) {
+        self.bezel = match Bezel::load(&self.config.display.bezel) {
+            Ok(bezel) => bezel,
+            Err(error) => {
+                self.notify(&format!("Failed to load the bezel image: {error}"));
+                None
+            }
+        };
+    }
+
+    /// Resizes the window to the next whole multiple of 64x32 in the given direction, so that
+    /// the screen's pixels are all the same size.
+    fn step_window_scale(&mut self, step: i32) {
+        if self.config.window.fullscreen {
+            return;
+        }
+
+        let (width, height) = (SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+        let current = (screen_width() / width)
+            .min(screen_height() / height)
+            .round() as i32;
+        let scale = (current + step).clamp(1, MAX_WINDOW_SCALE) as f32;
+        request_new_screen_size(width * scale, height * scale);
+        self.notify(&format!("Window: {scale}x"));
+    }
+
+    fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.notify(&format!("Speed: {}x", self.speed));
+    }
+
+    fn check_rules(&mut self, emulator: &Emulator) {
+        for rule in self.rules.check(emulator) {
+            for action in &rule.then {
+                match action {
+                    RuleAction::Screenshot => self.screenshot_requested = true,
+                    RuleAction::SaveState => self.save_state(emulator),
+                    RuleAction::Pause => self.paused = true,
+                    RuleAction::Log => self.notify(&format!("Rule triggered: {}", rule.name)),
+                    // Ring the terminal bell
+                    RuleAction::Sound => eprint!("\x07"),
+                }
+            }
+        }
+    }
+
+    fn save_screenshot(&mut self, image: &Image) {
+        let path = screenshot::timestamped_path(&self.state_path(), "png");
+        match screenshot::save_png(image, self.config.display.screenshot_scale, &path) {
+            Ok(()) => self.notify(&format!("Saved screenshot to {}", path.display())),
+            Err(error) => self.notify(&format!("Failed to save screenshot: {error}")),
+        }
+    }
+
+    /// Prints the screen to stdout as text and saves it next to the ROM as a PBM image.
+    fn dump_screen(&mut self, emulator: &Emulator) {
+        print!("{}", screen_text(emulator));
+
+        let path = screenshot::timestamped_path(&self.state_path(), "pbm");
+        match File::create(&path).and_then(|file| write_pbm(emulator, BufWriter::new(file))) {
+            Ok(()) => self.notify(&format!("Saved screen to {}", path.display())),
+            Err(error) => self.notify(&format!("Failed to save screen: {error}")),
+        }
+    }
+
+    fn record_gif(&mut self, image: &Image) {
+        if std::mem::take(&mut self.gif_requested) {
+            let path = screenshot::timestamped_path(&self.state_path(), "gif");
+            match GifRecorder::start(&path, image, self.config.recording.scale) {
+                Ok(recorder) => {
+                    self.gif_recorder = Some(recorder);
+                    self.start_wav(&path);
+                    self.notify(&format!("Recording to {}", path.display()));
+                }
+                Err(error) => self.notify(&format!("Failed to start recording: {error}")),
+            }
+        }
+
+        let Some(recorder) = &mut self.gif_recorder else {
+            return;
+        };
+        if let Err(error) = recorder.capture(image, get_frame_time()) {
+            self.gif_recorder = None;
+            self.stop_wav();
+            self.notify(&format!("Recording failed: {error}"));
+        }
+    }
+
+    fn stop_gif(&mut self) {
+        let Some(recorder) = self.gif_recorder.take() else {
+            return;
+        };
+        self.stop_wav();
+        match recorder.finish() {
+            Ok(()) => self.notify("Recording saved"),
+            Err(error) => self.notify(&format!("Failed to save recording: {error}")),
+        }
+    }
+
+    fn record_video(&mut self, image: &Image) {
+        if std::mem::take(&mut self.video_requested) {
+            let recording = &self.config.recording;
+            let path = screenshot::timestamped_path(&self.state_path(), &recording.video_extension);
+            match VideoRecorder::start(&recording.ffmpeg, &path, image, recording.scale) {
+                Ok(recorder) => {
+                    self.video_recorder = Some(recorder);
+                    self.start_wav(&path);
+                    self.notify(&format!("Recording to {}", path.display()));
+                }
+                Err(error) => self.notify(&format!("Failed to start ffmpeg: {error}")),
+            }
+        }
+
+        let Some(recorder) = &mut self.video_recorder else {
+            return;
+        };
+        if let Err(error) = recorder.capture(image, get_frame_time()) {
+            self.video_recorder = None;
+            self.stop_wav();
+            self.notify(&format!("Recording failed: {error}"));
+        }
+    }
+
+    fn stop_video(&mut self) {
+        let Some(recorder) = self.video_recorder.take() else {
+            return;
+        };
+        let path = recorder.path().to_path_buf();
+        let sound = self.stop_wav();
+        if let Err(error) = recorder.finish() {
+            self.notify(&format!("Failed to save recording: {error}"));
+            return;
+        }
+
+        // The sound is left in its WAV file while a GIF that started first is still recording it
+        let Some(sound) = sound.filter(|sound| *sound == path.with_extension("wav")) else {
+            self.notify("Recording saved");
+            return;
+        };
+        match add_sound(&self.config.recording.ffmpeg, &path, &sound) {
+            Ok(()) => self.notify("Recording saved"),
+            Err(error) => self.notify(&format!("Recording saved without sound: {error}")),
+        }
+    }
+
+    /// Starts saving the sound next to the recording at `path`, unless the other recording
+    /// already is.
+    fn start_wav(&mut self, path: &Path) {
+        if self.wav_recorder.is_some() {
+            return;
+        }
+        let mixer = self.audio.start_recording();
+        match WavRecorder::start(&path.with_extension("wav"), mixer) {
+            Ok(recorder) => self.wav_recorder = Some(recorder),
+            Err(error) => {
+                self.audio.stop_recording();
+                self.notify(&format!("Failed to start recording sound: {error}"));
+            }
+        }
+    }
+
+    fn record_wav(&mut self) {
+        let Some(recorder) = &mut self.wav_recorder else {
+            return;
+        };
+        if let Err(error) = recorder.capture(get_frame_time()) {
+            self.wav_recorder = None;
+            self.audio.stop_recording();
+            self.notify(&format!("Recording sound failed: {error}"));
+        }
+    }
+
+    /// Finishes the sound once neither recording is running, returning the WAV file it was
+    /// saved to.
+    fn stop_wav(&mut self) -> Option<PathBuf> {
+        if self.gif_recorder.is_some() || self.video_recorder.is_some() {
+            return None;
+        }
+        let recorder = self.wav_recorder.take()?;
+        self.audio.stop_recording();
+        match recorder.finish() {
+            Ok(path) => Some(path),
+            Err(error) => {
+                self.notify(&format!("Failed to save recorded sound: {error}"));
+                None
+            }
+        }
+    }
+
+    /// Toggles the keys held on the next frame advanced as they're pressed on the keyboard, while
+    /// stepping frame by frame.
+    fn edit_frame_advance_keys(&mut self) {
+        // Stepping ends once emulation is resumed some other way, such as from the menu
+        if !self.paused {
+            self.frame_advance = None;
+        }
+        let pressed = self.keymap().pressed();
+        let Some(keys) = &mut self.frame_advance else {
+            return;
+        };
+        let rotation = self.config.display.rotation;
+        let mut changed = false;
+        for (key_index, pressed) in pressed.into_iter().enumerate() {
+            if pressed {
+                let key = &mut keys[rotation.remap_key(key_index as u8) as usize];
+                *key = !*key;
+                changed = true;
+            }
+        }
+
+        if changed {
+            let held: Vec<String> = (0..NUM_INPUT_KEYS)
+                .filter(|key| keys[*key])
+                .map(|key| format!("{key:X}"))
+                .collect();
+            let held = if held.is_empty() {
+                String::from("none")
+            } else {
+                held.join(" ")
+            };
+            self.notify(&format!("Next frame's keys: {held}"));
+        }
+    }
+
+    /// Restarts the ROM with a new seed and starts recording the keypad from there.
+    fn start_movie(&mut self, emulator: &mut Emulator) {
+        let program = match &self.rom_path {
+            Some(rom_path) => match std::fs::read(rom_path) {
+                Ok(program) => program,
+                Err(error) => {
+                    self.notify(&format!("Failed to reload the ROM: {error}"));
+                    return;
+                }
+            },
+            None => SPLASH_ROM.to_vec(),
+        };
+        let seed = ::rand::random();
+        if let Err(error) = emulator.restart(&program, seed) {
+            self.notify(&format!("Failed to restart the ROM: {error}"));
+            return;
+        }
+
+        let path = screenshot::timestamped_path(&self.state_path(), MOVIE_EXTENSION);
+        self.notify(&format!("Recording movie to {}", path.display()));
+        self.movie_playback = None;
+        self.movie_recording = Some((path, Movie::new(&program, emulator, seed)));
+    }
+
+    fn stop_movie(&mut self) {
+        let Some((path, movie)) = self.movie_recording.take() else {
+            return;
+        };
+        match movie.save(&path) {
+            Ok(()) => self.notify("Movie saved"),
+            Err(error) => self.notify(&format!("Failed to save movie: {error}")),
+        }
+    }
+
+    fn state_path(&self) -> PathBuf {
+        match &self.rom_path {
+            Some(rom_path) => rom_path.with_extension("state"),
+            None => PathBuf::from("hachi.state"),
+        }
+    }
+
+    fn save_state(&mut self, emulator: &Emulator) {
+        let path = self.state_path();
+        match std::fs::write(&path, emulator.snapshot().to_bytes()) {
+            Ok(()) => self.notify("State saved"),
+            Err(error) => self.notify(&format!("Failed to save state: {error}")),
+        }
+    }
+
+    fn load_state(&mut self, emulator: &mut Emulator) {
+        let path = self.state_path();
+        let result = std::fs::read(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|data| Snapshot::from_bytes(&data).map_err(|error| error.to_string()))
+            .and_then(|snapshot| {
+                emulator
+                    .restore(&snapshot)
+                    .map_err(|error| error.to_string())
+            });
+
+        match result {
+            Ok(()) => self.notify("State loaded"),
+            Err(error) => self.notify(&format!("Failed to load state: {error}")),
+        }
+    }
+
+    fn rpl_flags_path(&self) -> PathBuf {
+        self.state_path().with_extension("flags")
+    }
+
+    /// Restores the RPL flags the ROM saved in an earlier session, if any.
+    fn load_rpl_flags(&mut self, emulator: &mut Emulator) {
+        let Ok(data) = std::fs::read(self.rpl_flags_path()) else {
+            return;
+        };
+
+        let mut flags = [0; RPL_FLAGS];
+        let len = data.len().min(RPL_FLAGS);
+        flags[..len].copy_from_slice(&data[..len]);
+        emulator.set_rpl_flags(flags);
+        self.saved_rpl_flags = flags;
+    }
+
+    /// Writes the RPL flags to disk whenever the ROM changes them, so they survive a crash.
+    fn save_rpl_flags(&mut self, emulator: &Emulator) {
+        let flags = emulator.rpl_flags();
+        if flags == self.saved_rpl_flags {
+            return;
+        }
+
+        self.saved_rpl_flags = flags;
+        if let Err(error) = std::fs::write(self.rpl_flags_path(), flags) {
+            self.notify(&format!("Failed to save RPL flags: {error}"));
+        }
+    }
+
+    /// The colors the screen is currently shown in.
+    fn palette(&self) -> [Rgb; 4] {
+        self.palette_override.unwrap_or(self.config.display.palette)
+    }
+
+    /// The keypad keys held on the gamepads and with the input profiles' keymaps, opening
+    /// gamepads once they're plugged in.
+    fn poll_gamepads(&mut self) -> [bool; NUM_INPUT_KEYS] {
+        let elapsed = get_frame_time();
+        let (mut held, message) = match self.stick_override {
+            Some(keys) => self
+                .gamepad
+                .poll(&self.config.gamepad.with_stick(keys), elapsed),
+            None => self.gamepad.poll(&self.config.gamepad, elapsed),
+        };
+        let mut messages: Vec<String> = message.map(String::from).into_iter().collect();
+
+        for (name, profile) in &self.config.input_profiles {
+            if !profile.enabled {
+                continue;
+            }
+            let mut profile_held = profile.keymap.held();
+            if let Some(config) = &profile.gamepad {
+                let port = self.profile_gamepads.entry(name.clone()).or_default();
+                let (gamepad_held, message) = port.poll(config, elapsed);
+                for (key, gamepad_key) in profile_held.iter_mut().zip(gamepad_held) {
+                    *key |= gamepad_key;
+                }
+                messages.extend(message.map(|message| format!("{message} for {name}")));
+            }
+            for (key, profile_key) in held.iter_mut().zip(profile_held) {
+                *key |= profile_key;
+            }
+        }
+
+        for message in messages {
+            self.notify(&message);
+        }
+        held
+    }
+
+    /// Picks up changes made to the config file's keymap, hotkeys, gamepad, touch and input
+    /// profile settings while running, so controls can be remapped without restarting.
+    fn reload_controls(&mut self) {
+        self.config_check_time -= get_frame_time();
+        if self.config_check_time > 0.0 {
+            return;
+        }
+        self.config_check_time = CONFIG_CHECK_SECONDS;
+
+        let Some(path) = self.config_path.clone() else {
+            return;
+        };
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified == self.config_modified {
+            return;
+        }
+        self.config_modified = modified;
+
+        match Config::load(&path) {
+            Ok(config)
+                if config.keymap != self.config.keymap
+                    || config.hotkeys != self.config.hotkeys
+                    || config.gamepad != self.config.gamepad
+                    || config.input_profiles != self.config.input_profiles
+                    || config.touch != self.config.touch =>
+            {
+                self.config.keymap = config.keymap;
+                self.config.hotkeys = config.hotkeys;
+                self.config.gamepad = config.gamepad;
+                self.config.input_profiles = config.input_profiles;
+                self.config.touch = config.touch;
+                // Reopened in case the device changed
+                self.gamepad = GamepadPort::default();
+                self.profile_gamepads.clear();
+                self.notify("Controls reloaded");
+                self.report_conflicts();
+            }
+            Ok(_) => {}
+            Err(error) => self.notify(&format!("Failed to reload {}: {error}", path.display())),
+        }
+    }
+
+    /// Warns about hotkeys sharing a key with the keypad being played with, or with each other,
+    /// since both would be triggered.
+    fn report_conflicts(&mut self) {
+        let conflicts = self.config.hotkeys.conflicts(self.keymap());
+        for conflict in &conflicts {
+            eprintln!("Hotkey conflict: {conflict}");
+        }
+        if let Some(conflict) = conflicts.first() {
+            self.notify(&format!("Hotkey conflict: {conflict}"));
+        }
+    }
+
+    /// The keys the keypad is played with, which may be the ROM's own.
+    fn keymap(&self) -> &Keymap {
+        self.keymap_override.as_ref().unwrap_or(&self.config.keymap)
+    }
+
+    fn save_config(&mut self) {
+        let Some(path) = &self.config_path else {
+            return;
+        };
+
+        if let Err(error) = self.config.save(path) {
+            self.notify(&format!("Failed to save settings: {error}"));
+        }
+    }
+
+    /// Saves the window's current size and position to the config, if enabled.
+    fn remember_window(&mut self) {
+        let window = &mut self.config.window;
+        // The size and position would be the whole monitor's
+        if !window.remember || window.fullscreen {
+            return;
+        }
+
+        window.width = screen_width() as u32;
+        window.height = screen_height() as u32;
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        {
+            window.position = Some(miniquad::window::get_window_position());
+        }
+        self.save_config();
+    }
+
+    fn notify(&mut self, text: &str) {
+        println!("{text}");
+        self.notification = Some((text.to_string(), NOTIFICATION_SECONDS));
+    }
+
+    fn draw_notification(&mut self) {
+        let Some((text, remaining)) = &mut self.notification else {
+            return;
+        };
+
+        *remaining -= get_frame_time();
+        if *remaining <= 0.0 {
+            self.notification = None;
+            return;
+        }
+
+        let y = screen_height() - MARGIN;
+        let dimensions = measure_text(text, None, FONT_SIZE as u16, 1.0);
+        draw_rectangle(
+            0.0,
+            y - dimensions.height - MARGIN,
+            dimensions.width + MARGIN * 2.0,
+            dimensions.height + MARGIN * 2.0,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+        draw_text(text, MARGIN, y, FONT_SIZE, WHITE);
+    }
+
+    /// Draws lines between the screen's logical pixels, to help count sprite coordinates.
+    fn draw_pixel_grid(emulator: &Emulator, screen_rect: Rect, rotation: Rotation) {
+        let (width, height) = match emulator.megachip_frame() {
+            Some(_) => (MEGACHIP_WIDTH, MEGACHIP_HEIGHT),
+            None => emulator.display_size(),
+        };
+        let (width, height) = if rotation.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let (cell_width, cell_height) =
+            (screen_rect.w / width as f32, screen_rect.h / height as f32);
+        if cell_width.min(cell_height) < MIN_GRID_CELL {
+            return;
+        }
+
+        for column in 1..width {
+            let x = screen_rect.x + column as f32 * cell_width;
+            draw_line(x, screen_rect.y, x, screen_rect.bottom(), 1.0, GRID_COLOR);
+        }
+        for row in 1..height {
+            let y = screen_rect.y + row as f32 * cell_height;
+            draw_line(screen_rect.x, y, screen_rect.right(), y, 1.0, GRID_COLOR);
+        }
+    }
+
+    fn draw_info_panel(emulator: &Emulator) {
+        let mut text = match emulator.rom_report() {
+            Some(report) => report.to_string(),
+            None => String::from("No ROM loaded\n"),
+        };
+        text.push_str(&emulator.usage().to_string());
+
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.8),
+        );
+        for (line_index, line) in text.lines().enumerate() {
+            let y = MARGIN + FONT_SIZE * (line_index + 1) as f32;
+            draw_text(line, MARGIN, y, FONT_SIZE, WHITE);
+        }
+    }
+
+    fn redraw_screen(&mut self, emulator: &Emulator, image: &mut Image) {
+        let (width, height) = match emulator.megachip_frame() {
+            Some(_) => (MEGACHIP_WIDTH, MEGACHIP_HEIGHT),
+            None => emulator.screen_resolution(),
+        };
+        if (image.width(), image.height()) != (width, height) {
+            *image = Image::gen_image_color(width as u16, height as u16, BLACK);
+            self.phosphor.reset(width * height);
+            self.blender.reset();
+        }
+
+        let display = &self.config.display;
+        let levels = ColorLevels::new(display.brightness, display.gamma);
+        if let Some(frame) = emulator.megachip_frame() {
+            for (pixel, [r, g, b, a]) in frame.iter().enumerate() {
+                let Rgb { r, g, b } = levels.apply(Rgb::new(*r, *g, *b));
+                let color = Color::from_rgba(r, g, b, *a);
+                image.set_pixel((pixel % width) as u32, (pixel / width) as u32, color);
+            }
+            return;
+        }
+
+        let palette = self.palette().map(|color| levels.apply(color));
+        let decay_frames = self.config.display.decay_frames;
+        let mut frame = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut color = palette[emulator.screen_color(x, y) as usize];
+                if decay_frames > 0 {
+                    color = self
+                        .phosphor
+                        .apply(y * width + x, color, palette[0], decay_frames);
+                }
+                frame.push(color);
+            }
+        }
+
+        let blend_frames = self.config.display.blend_frames;
+        if blend_frames > 1 {
+            frame = self.blender.blend(frame, blend_frames as usize);
+        } else {
+            self.blender.reset();
+        }
+        for (pixel, color) in frame.into_iter().enumerate() {
+            image.set_pixel((pixel % width) as u32, (pixel / width) as u32, color.into());
+        }
+    }
+}
+
+impl Default for Frontend {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
@@ -0,0 +1,273 @@
+use std::{f32::consts::TAU, path::PathBuf};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{AUDIO_PATTERN_BYTES, DigitizedSound, Emulator, config::AudioConfig};
+
+use super::audio_backend::{AudioBackend, AudioChannel, Clip, Mixer};
+
+const SAMPLE_RATE: u32 = 44100;
+const AMPLITUDE: i16 = i16::MAX / 4;
+const PATTERN_BITS: usize = AUDIO_PATTERN_BYTES * 8;
+/// How long the looped beep is, so that noise doesn't audibly repeat.
+const BEEP_LOOP_SECONDS: f32 = 0.5;
+/// Lower frequencies are inaudible, and would make the loop impractically long.
+const MIN_BEEP_FREQUENCY: f32 = 20.0;
+/// Makes up for the volume lost low-pass filtering noise.
+const NOISE_GAIN: f32 = 4.0;
+
+/// The shape of the beep played when the program hasn't loaded an XO-CHIP audio pattern.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    /// The classic buzzer.
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+    /// White noise low-pass filtered at the beep's frequency, a soft hiss.
+    Noise,
+}
+
+impl Waveform {
+    /// Renders a loop of whole cycles at `frequency`, about [`BEEP_LOOP_SECONDS`] long.
+    fn synthesize(self, frequency: f32) -> Vec<i16> {
+        let frequency = frequency.max(MIN_BEEP_FREQUENCY);
+        let cycles = (frequency * BEEP_LOOP_SECONDS).round().max(1.0);
+        let samples = (cycles / frequency * SAMPLE_RATE as f32).round().max(1.0) as usize;
+        let smoothing = 1.0 - (-TAU * frequency / SAMPLE_RATE as f32).exp();
+        let mut rng = rand::rng();
+        let mut noise = 0.0;
+
+        (0..samples)
+            .map(|sample| {
+                let phase = (sample as f32 * cycles / samples as f32).fract();
+                let value = match self {
+                    Waveform::Square => {
+                        if phase < 0.5 {
+                            1.0
+                        } else {
+                            -1.0
+                        }
+                    }
+                    Waveform::Sine => (phase * TAU).sin(),
+                    Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                    Waveform::Noise => {
+                        noise += (rng.random_range(-1.0..1.0) - noise) * smoothing;
+                        noise * NOISE_GAIN
+                    }
+                };
+                (value.clamp(-1.0, 1.0) * AMPLITUDE as f32) as i16
+            })
+            .collect()
+    }
+}
+
+/// What the sound timer's sound was synthesized from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tone {
+    /// An XO-CHIP audio pattern and the pitch it's played at.
+    Pattern([u8; AUDIO_PATTERN_BYTES], u8),
+    /// The beep for programs without a pattern, and its frequency.
+    Beep(Waveform, f32),
+    /// The config's beep sample, played instead of the waveform.
+    Sample,
+}
+
+/// Beeps, or plays the XO-CHIP audio pattern, while the sound timer runs, and plays Mega-CHIP
+/// digitized sounds, through an [`AudioBackend`].
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    playing: Option<Tone>,
+    /// The Mega-CHIP sound playing on the digitized channel.
+    playing_digitized: Option<DigitizedSound>,
+    /// The volume last given to the backend.
+    volume: Option<f32>,
+    /// The buffer size last given to the backend.
+    buffer_size: Option<u32>,
+    /// What's playing on each channel, to start recordings with.
+    clips: [Option<Clip>; 2],
+    /// Also plays the sound here while it's being recorded.
+    recording: Option<Mixer>,
+    /// The config's beep sample, and the clip loaded from it if it could be.
+    beep_sample: Option<(PathBuf, Option<Clip>)>,
+}
+
+impl Audio {
+    pub fn new(backend: Box<dyn AudioBackend>) -> Audio {
+        Audio {
+            backend,
+            playing: None,
+            playing_digitized: None,
+            volume: None,
+            buffer_size: None,
+            clips: [None, None],
+            recording: None,
+            beep_sample: None,
+        }
+    }
+
+    /// Starts also playing the sound through a new mixer for recording, returning it. Clips that
+    /// are already playing start over in the recording.
+    pub(crate) fn start_recording(&mut self) -> Mixer {
+        let mut mixer = Mixer::new();
+        mixer.set_volume(self.volume.unwrap_or(1.0));
+        for (channel, clip) in [AudioChannel::Tone, AudioChannel::Digitized]
+            .into_iter()
+            .zip(&self.clips)
+        {
+            if let Some(clip) = clip {
+                mixer.play(channel, clip.clone());
+            }
+        }
+        self.recording = Some(mixer.clone());
+        mixer
+    }
+
+    pub(crate) fn stop_recording(&mut self) {
+        self.recording = None;
+    }
+
+    /// Starts, stops or changes the sound to match the emulator, given whether its sound timer
+    /// has been running this frame. Nothing plays while `silent`, such as when paused or muted.
+    pub fn update(
+        &mut self,
+        emulator: &Emulator,
+        sounding: bool,
+        silent: bool,
+        config: &AudioConfig,
+    ) {
+        if self.volume != Some(config.volume) {
+            self.volume = Some(config.volume);
+            self.backend.set_volume(config.volume);
+            if let Some(recording) = &mut self.recording {
+                recording.set_volume(config.volume);
+            }
+        }
+        if config.buffer_size.is_some() && self.buffer_size != config.buffer_size {
+            self.buffer_size = config.buffer_size;
+            let frames = config.buffer_size.unwrap_or_default();
+            if !self.backend.set_buffer_size(frames) {
+                eprintln!("The audio output's buffer size is fixed, so buffer_size is ignored");
+            }
+        }
+        self.update_digitized(emulator, silent);
+        self.update_beep_sample(config);
+
+        let wanted = (sounding && !silent).then(|| match emulator.audio_pattern() {
+            Some(pattern) => Tone::Pattern(pattern, emulator.pitch()),
+            None => match self.beep_sample {
+                Some((_, Some(_))) => Tone::Sample,
+                _ => Tone::Beep(config.waveform, config.frequency),
+            },
+        });
+        if wanted == self.playing {
+            return;
+        }
+        self.playing = wanted;
+
+        let samples = match wanted {
+            Some(Tone::Pattern(pattern, _)) => synthesize(&pattern, emulator.pattern_rate()),
+            Some(Tone::Beep(waveform, frequency)) => waveform.synthesize(frequency),
+            Some(Tone::Sample) => {
+                if let Some((_, Some(clip))) = &self.beep_sample {
+                    let clip = Clip {
+                        looped: true,
+                        ..clip.clone()
+                    };
+                    self.play(AudioChannel::Tone, clip);
+                }
+                return;
+            }
+            None => return self.stop(AudioChannel::Tone),
+        };
+        self.play(
+            AudioChannel::Tone,
+            Clip {
+                samples,
+                sample_rate: SAMPLE_RATE,
+                looped: true,
+            },
+        );
+    }
+
+    /// Loads the config's beep sample when it changes, stopping the old one.
+    fn update_beep_sample(&mut self, config: &AudioConfig) {
+        let loaded = self.beep_sample.as_ref().map(|(path, _)| path);
+        if loaded == config.sample.as_ref() {
+            return;
+        }
+
+        self.beep_sample = config.sample.as_ref().map(|path| {
+            let clip = Clip::load_wav(path)
+                .inspect_err(|error| {
+                    eprintln!("Failed to load beep sample {}: {error}", path.display())
+                })
+                .ok();
+            (path.clone(), clip)
+        });
+        if self.playing == Some(Tone::Sample) {
+            self.playing = None;
+            self.stop(AudioChannel::Tone);
+        }
+    }
+
+    fn update_digitized(&mut self, emulator: &Emulator, silent: bool) {
+        let wanted = emulator.megachip_sound().filter(|_| !silent);
+        if wanted == self.playing_digitized.as_ref() {
+            return;
+        }
+        self.playing_digitized = wanted.cloned();
+
+        let Some(digitized) = wanted else {
+            return self.stop(AudioChannel::Digitized);
+        };
+        let samples = digitized
+            .samples
+            .iter()
+            .map(|sample| (*sample as i16 - 128) << 8)
+            .collect();
+        self.play(
+            AudioChannel::Digitized,
+            Clip {
+                samples,
+                sample_rate: digitized.sample_rate.max(1) as u32,
+                looped: digitized.looped,
+            },
+        );
+    }
+
+    fn play(&mut self, channel: AudioChannel, clip: Clip) {
+        if let Some(recording) = &mut self.recording {
+            recording.play(channel, clip.clone());
+        }
+        self.clips[channel as usize] = Some(clip.clone());
+        self.backend.play(channel, clip);
+    }
+
+    fn stop(&mut self, channel: AudioChannel) {
+        if let Some(recording) = &mut self.recording {
+            recording.stop(channel);
+        }
+        self.clips[channel as usize] = None;
+        self.backend.stop(channel);
+    }
+}
+
+/// Renders one loop of `pattern`, played at `rate` bits per second, as a square wave.
+fn synthesize(pattern: &[u8; AUDIO_PATTERN_BYTES], rate: f32) -> Vec<i16> {
+    let samples = (PATTERN_BITS as f32 / rate * SAMPLE_RATE as f32)
+        .round()
+        .max(1.0) as usize;
+    (0..samples)
+        .map(|sample| {
+            let bit = (sample as f32 * rate / SAMPLE_RATE as f32) as usize % PATTERN_BITS;
+            if (pattern[bit / 8] >> (7 - bit % 8)) & 1 == 1 {
+                AMPLITUDE
+            } else {
+                -AMPLITUDE
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,516 @@
+use std::{
+    fs,
+    future::Future,
+    path::Path,
+    pin::pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+use macroquad::audio::{
+    PlaySoundParams, Sound, load_sound_from_bytes, play_sound, set_sound_volume, stop_sound,
+};
+#[cfg(feature = "sdl2")]
+use sdl2::{
+    AudioSubsystem,
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::WavError;
+
+const CHANNELS: usize = 2;
+/// How long sounds take to ramp in or out.
+const ENVELOPE_SECONDS: f32 = 0.005;
+/// How long looped sounds are repeated out to before macroquad plays them, so their baked ramps
+/// are only heard where they start over. This is the longest the sound timer runs unreloaded.
+const UNROLLED_LOOP_SECONDS: f32 = 256.0 / 60.0;
+/// About 23 milliseconds at 44.1kHz, which most machines keep up with.
+const DEFAULT_BUFFER_SIZE: u32 = 1024;
+/// The rate the SDL2 output asks its device for. SDL converts if the device can't play it.
+#[cfg(feature = "sdl2")]
+const SDL2_SAMPLE_RATE: i32 = 44100;
+
+/// The sounds the frontend plays at the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioChannel {
+    /// The sound timer's beep or XO-CHIP audio pattern.
+    Tone,
+    /// A Mega-CHIP digitized sound.
+    Digitized,
+}
+
+/// Mono 16-bit samples to play.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clip {
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+    /// Whether to start over from the beginning at the end rather than stop.
+    pub looped: bool,
+}
+
+impl Clip {
+    /// Loads a clip from a WAV file. See [`Clip::from_wav`].
+    pub fn load_wav(path: &Path) -> Result<Clip, WavError> {
+        Clip::from_wav(&fs::read(path)?)
+    }
+
+    /// Decodes a WAV file of 8, 16 or 24-bit integer or 32-bit float samples, mixing its
+    /// channels down to mono. The clip doesn't loop.
+    pub fn from_wav(bytes: &[u8]) -> Result<Clip, WavError> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(WavError::Invalid("not a WAV file"));
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut chunks = &bytes[12..];
+        while chunks.len() >= 8 {
+            let len = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+            let body = chunks
+                .get(8..8 + len)
+                .ok_or(WavError::Invalid("a chunk is truncated"))?;
+            match &chunks[0..4] {
+                b"fmt " => format = Some(body),
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            // Chunks are padded to an even length
+            chunks = chunks.get(8 + len + len % 2..).unwrap_or_default();
+        }
+        let format = format
+            .filter(|format| format.len() >= 16)
+            .ok_or(WavError::Invalid("the format chunk is missing"))?;
+        let data = data.ok_or(WavError::Invalid("the data chunk is missing"))?;
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([format[offset], format[offset + 1]]);
+        let mut encoding = read_u16(0);
+        let channels = read_u16(2).max(1) as usize;
+        let sample_rate = u32::from_le_bytes(format[4..8].try_into().unwrap());
+        let bits = read_u16(14);
+        // WAVE_FORMAT_EXTENSIBLE keeps the real encoding at the start of its sub-format GUID
+        if encoding == 0xFFFE && format.len() >= 26 {
+            encoding = read_u16(24);
+        }
+
+        let decode: fn(&[u8]) -> f32 = match (encoding, bits) {
+            (1, 8) => |bytes| (bytes[0] as f32 - 128.0) / 128.0,
+            (1, 16) => |bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            (1, 24) => {
+                |bytes| i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) as f32 / 2147483648.0
+            }
+            (3, 32) => |bytes| f32::from_le_bytes(bytes.try_into().unwrap()),
+            _ => return Err(WavError::Unsupported { encoding, bits }),
+        };
+        let frame_len = bits as usize / 8 * channels;
+        let samples = data
+            .chunks_exact(frame_len)
+            .map(|frame| {
+                let sum: f32 = frame.chunks_exact(bits as usize / 8).map(decode).sum();
+                ((sum / channels as f32).clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+            })
+            .collect();
+
+        Ok(Clip {
+            samples,
+            sample_rate,
+            looped: false,
+        })
+    }
+
+    /// The clip with ramps in and out baked into its start and end, for backends that can't ramp
+    /// while playing. Looped clips are first repeated to last [`UNROLLED_LOOP_SECONDS`].
+    fn enveloped(&self) -> Clip {
+        let mut samples = self.samples.clone();
+        if self.looped && !samples.is_empty() {
+            let unrolled = (UNROLLED_LOOP_SECONDS * self.sample_rate as f32) as usize;
+            samples = samples.repeat(unrolled.div_ceil(samples.len()).max(1));
+        }
+
+        let ramp = ((ENVELOPE_SECONDS * self.sample_rate as f32) as usize).min(samples.len() / 2);
+        let len = samples.len();
+        for sample in 0..ramp {
+            let gain = sample as f32 / ramp as f32;
+            samples[sample] = (samples[sample] as f32 * gain) as i16;
+            samples[len - 1 - sample] = (samples[len - 1 - sample] as f32 * gain) as i16;
+        }
+
+        Clip {
+            samples,
+            sample_rate: self.sample_rate,
+            looped: self.looped,
+        }
+    }
+}
+
+/// Outputs the sound the frontend synthesizes, so it can go somewhere other than macroquad's
+/// audio, such as an audio device the host application already owns. See
+/// [`Frontend::with_audio_backend`](crate::Frontend::with_audio_backend).
+pub trait AudioBackend {
+    /// Starts playing `clip` on `channel`, replacing whatever was playing there.
+    fn play(&mut self, channel: AudioChannel, clip: Clip);
+    /// Stops whatever is playing on `channel`.
+    fn stop(&mut self, channel: AudioChannel);
+    /// Sets the volume of every channel, from 0 to 1.
+    fn set_volume(&mut self, volume: f32);
+    /// Asks for the audio device to be given `frames` samples at a time, returning whether the
+    /// backend can. Those that can't choose their device's buffer ignore it and return false.
+    fn set_buffer_size(&mut self, _frames: u32) -> bool {
+        false
+    }
+}
+
+/// The built-in backends, chosen in the config file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioOutput {
+    /// macroquad's audio, which is only audible when built with the `audio` feature.
+    #[default]
+    Macroquad,
+    /// The audio device, opened through SDL2 and fed by a [`Mixer`], when built with the `sdl2`
+    /// feature. Unlike macroquad's, sounds ramp out when they're stopped early, and the config's
+    /// `buffer_size` sets the device's buffer.
+    Sdl2,
+    /// No sound at all.
+    None,
+}
+
+impl AudioOutput {
+    pub fn backend(self) -> Box<dyn AudioBackend> {
+        match self {
+            AudioOutput::Macroquad => Box::new(MacroquadBackend::default()),
+            #[cfg(feature = "sdl2")]
+            AudioOutput::Sdl2 => match Sdl2Backend::new() {
+                Ok(backend) => Box::new(backend),
+                Err(error) => {
+                    eprintln!("Failed to open the SDL2 audio device, using macroquad's: {error}");
+                    Box::new(MacroquadBackend::default())
+                }
+            },
+            #[cfg(not(feature = "sdl2"))]
+            AudioOutput::Sdl2 => {
+                eprintln!("Built without the sdl2 feature, so sound goes through macroquad");
+                Box::new(MacroquadBackend::default())
+            }
+            AudioOutput::None => Box::new(SilentBackend),
+        }
+    }
+}
+
+/// Plays sound through macroquad, whose buffer size is fixed by its platform code. macroquad only
+/// applies changes between buffers, so the ramps are baked into each clip instead: sounds ramp in,
+/// and ramp out where they end by themselves, but one stopped early is still cut off.
+pub struct MacroquadBackend {
+    sounds: [Option<Sound>; CHANNELS],
+    volume: f32,
+}
+
+impl Default for MacroquadBackend {
+    fn default() -> Self {
+        MacroquadBackend {
+            sounds: [None, None],
+            volume: 1.0,
+        }
+    }
+}
+
+impl AudioBackend for MacroquadBackend {
+    fn play(&mut self, channel: AudioChannel, clip: Clip) {
+        self.stop(channel);
+
+        // Loading only has to wait for the browser on the web, so it's done by the first poll
+        let clip = clip.enveloped();
+        let wav = wav_bytes(&clip.samples, clip.sample_rate);
+        let mut load = pin!(load_sound_from_bytes(&wav));
+        let Poll::Ready(Ok(sound)) = load.as_mut().poll(&mut Context::from_waker(Waker::noop()))
+        else {
+            return;
+        };
+
+        play_sound(
+            &sound,
+            PlaySoundParams {
+                looped: clip.looped,
+                volume: self.volume,
+            },
+        );
+        self.sounds[channel as usize] = Some(sound);
+    }
+
+    fn stop(&mut self, channel: AudioChannel) {
+        if let Some(sound) = self.sounds[channel as usize].take() {
+            stop_sound(&sound);
+        }
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        for sound in self.sounds.iter().flatten() {
+            set_sound_volume(sound, volume);
+        }
+    }
+}
+
+/// Discards all sound.
+pub struct SilentBackend;
+
+impl AudioBackend for SilentBackend {
+    fn play(&mut self, _channel: AudioChannel, _clip: Clip) {}
+
+    fn stop(&mut self, _channel: AudioChannel) {}
+
+    fn set_volume(&mut self, _volume: f32) {}
+}
+
+/// Mixes the channels into samples pulled with [`Mixer::fill`], for hosts that run their own
+/// audio device. Clones share the same sound, so one can be given to the frontend while another
+/// fills the device's buffers. Sounds are ramped in and out over a few milliseconds so that
+/// starting and stopping them doesn't pop.
+#[derive(Clone)]
+pub struct Mixer {
+    state: Arc<Mutex<MixerState>>,
+}
+
+struct MixerState {
+    voices: [Option<Voice>; CHANNELS],
+    /// Stopped or replaced sounds, playing until they've faded out.
+    releasing: Vec<Voice>,
+    volume: f32,
+    buffer_size: u32,
+}
+
+struct Voice {
+    clip: Clip,
+    /// Where playback is up to, in the clip's samples.
+    position: f64,
+    /// How far the sound has been ramped in, from 0 to 1.
+    gain: f32,
+}
+
+impl Voice {
+    fn new(clip: Clip) -> Voice {
+        Voice {
+            clip,
+            position: 0.0,
+            gain: 0.0,
+        }
+    }
+
+    /// Adds the voice's next samples to `output`, ramping towards full volume or silence,
+    /// returning whether it's still playing.
+    fn mix(&mut self, output: &mut [f32], sample_rate: u32, volume: f32, fading_out: bool) -> bool {
+        let step = self.clip.sample_rate as f64 / sample_rate.max(1) as f64;
+        let ramp = 1.0 / (ENVELOPE_SECONDS * sample_rate.max(1) as f32);
+        let length = self.clip.samples.len() as f64;
+
+        for sample in output.iter_mut() {
+            if self.position >= length {
+                if !self.clip.looped || length == 0.0 {
+                    return false;
+                }
+                self.position %= length;
+            }
+            self.gain = if fading_out {
+                self.gain - ramp
+            } else {
+                self.gain + ramp
+            }
+            .clamp(0.0, 1.0);
+            if fading_out && self.gain == 0.0 {
+                return false;
+            }
+
+            let value = self.clip.samples[self.position as usize] as f32 / i16::MAX as f32;
+            *sample += value * self.gain * volume;
+            self.position += step;
+        }
+        true
+    }
+}
+
+impl Mixer {
+    pub fn new() -> Mixer {
+        Mixer {
+            state: Arc::new(Mutex::new(MixerState {
+                voices: [None, None],
+                releasing: Vec::new(),
+                volume: 1.0,
+                buffer_size: DEFAULT_BUFFER_SIZE,
+            })),
+        }
+    }
+
+    /// Fills `output` with the next mono samples, from -1 to 1, played at `sample_rate`.
+    /// Clips at other rates are resampled without filtering.
+    pub fn fill(&self, output: &mut [f32], sample_rate: u32) {
+        output.fill(0.0);
+        let mut state = self.state();
+        let volume = state.volume;
+
+        for slot in &mut state.voices {
+            if let Some(voice) = slot
+                && !voice.mix(output, sample_rate, volume, false)
+            {
+                *slot = None;
+            }
+        }
+        state
+            .releasing
+            .retain_mut(|voice| voice.mix(output, sample_rate, volume, true));
+    }
+
+    /// How many samples the host should ask its audio device to buffer, as set by the config's
+    /// `buffer_size`. The host's device needs reopening when it changes.
+    pub fn buffer_size(&self) -> u32 {
+        self.state().buffer_size
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MixerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioBackend for Mixer {
+    fn play(&mut self, channel: AudioChannel, clip: Clip) {
+        let mut state = self.state();
+        if let Some(voice) = state.voices[channel as usize].replace(Voice::new(clip)) {
+            state.releasing.push(voice);
+        }
+    }
+
+    fn stop(&mut self, channel: AudioChannel) {
+        let mut state = self.state();
+        if let Some(voice) = state.voices[channel as usize].take() {
+            state.releasing.push(voice);
+        }
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.state().volume = volume;
+    }
+
+    fn set_buffer_size(&mut self, frames: u32) -> bool {
+        self.state().buffer_size = frames.max(1);
+        true
+    }
+}
+
+/// Plays sound on the default audio device through SDL2, mixed by a [`Mixer`] that the device
+/// pulls samples from as it needs them, so stopped sounds ramp out rather than clicking.
+#[cfg(feature = "sdl2")]
+pub struct Sdl2Backend {
+    mixer: Mixer,
+    audio: AudioSubsystem,
+    device: AudioDevice<MixerCallback>,
+}
+
+#[cfg(feature = "sdl2")]
+impl Sdl2Backend {
+    pub fn new() -> Result<Sdl2Backend, String> {
+        let audio = sdl2::init()?.audio()?;
+        let mixer = Mixer::new();
+        let device = Sdl2Backend::open(&audio, &mixer)?;
+        Ok(Sdl2Backend {
+            mixer,
+            audio,
+            device,
+        })
+    }
+
+    /// Opens the default device with the mixer's buffer size and starts it playing.
+    fn open(audio: &AudioSubsystem, mixer: &Mixer) -> Result<AudioDevice<MixerCallback>, String> {
+        let spec = AudioSpecDesired {
+            freq: Some(SDL2_SAMPLE_RATE),
+            channels: Some(1),
+            samples: Some(mixer.buffer_size().min(u16::MAX as u32) as u16),
+        };
+        let device = audio.open_playback(None, &spec, |spec| MixerCallback {
+            mixer: mixer.clone(),
+            sample_rate: spec.freq as u32,
+        })?;
+        device.resume();
+        Ok(device)
+    }
+}
+
+#[cfg(feature = "sdl2")]
+impl AudioBackend for Sdl2Backend {
+    fn play(&mut self, channel: AudioChannel, clip: Clip) {
+        self.mixer.play(channel, clip);
+    }
+
+    fn stop(&mut self, channel: AudioChannel) {
+        self.mixer.stop(channel);
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.mixer.set_volume(volume);
+    }
+
+    /// Reopens the device with the new buffer size, keeping what's playing.
+    fn set_buffer_size(&mut self, frames: u32) -> bool {
+        if frames.max(1) == self.mixer.buffer_size() {
+            return true;
+        }
+        self.mixer.set_buffer_size(frames);
+        match Sdl2Backend::open(&self.audio, &self.mixer) {
+            Ok(device) => self.device = device,
+            Err(error) => eprintln!("Failed to reopen the audio device: {error}"),
+        }
+        true
+    }
+}
+
+/// Fills SDL2's buffers from a [`Mixer`] on its audio thread.
+#[cfg(feature = "sdl2")]
+struct MixerCallback {
+    mixer: Mixer,
+    sample_rate: u32,
+}
+
+#[cfg(feature = "sdl2")]
+impl AudioCallback for MixerCallback {
+    type Channel = f32;
+
+    fn callback(&mut self, output: &mut [f32]) {
+        self.mixer.fill(output, self.sample_rate);
+    }
+}
+
+/// Wraps mono 16-bit samples played at `sample_rate` in a WAV file, which is the format
+/// macroquad loads sounds from.
+fn wav_bytes(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(WAV_HEADER_BYTES + data_len as usize);
+    bytes.extend_from_slice(&wav_header(data_len, sample_rate));
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+const WAV_HEADER_BYTES: usize = 44;
+
+/// The header of a WAV file holding `data_len` bytes of mono 16-bit samples.
+pub(crate) fn wav_header(data_len: u32, sample_rate: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(WAV_HEADER_BYTES);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes()); // Format chunk size
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // Mono
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // Bytes per second
+    bytes.extend_from_slice(&2u16.to_le_bytes()); // Bytes per sample
+    bytes.extend_from_slice(&16u16.to_le_bytes()); // Bits per sample
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    bytes
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Artwork drawn behind the emulated screen, such as a console or cabinet.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BezelConfig {
+    /// A PNG image, scaled to fit the window without changing its shape.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<PathBuf>,
+    /// The area of the image the screen is shown in, as the left, top, width and height in
+    /// fractions of the image's size.
+    pub screen: [f32; 4],
+}
+
+impl Default for BezelConfig {
+    fn default() -> Self {
+        BezelConfig {
+            image: None,
+            screen: [0.0, 0.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// A loaded bezel image.
+pub(crate) struct Bezel {
+    texture: Texture2D,
+}
+
+impl Bezel {
+    /// Loads the configured image, if there is one.
+    pub(crate) fn load(config: &BezelConfig) -> Result<Option<Bezel>, String> {
+        let Some(path) = &config.image else {
+            return Ok(None);
+        };
+
+        let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+        let image = Image::from_file_with_format(&bytes, Some(ImageFormat::Png))
+            .map_err(|error| error.to_string())?;
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Linear);
+        Ok(Some(Bezel { texture }))
+    }
+
+    /// Draws the image as large as fits in `area`, centered, returning the area the screen goes
+    /// in.
+    pub(crate) fn draw(&self, config: &BezelConfig, area: Rect) -> Rect {
+        let size = self.texture.size();
+        let scale = (area.w / size.x).min(area.h / size.y);
+        let size = size * scale;
+        let position = area.point() + (area.size() - size) / 2.0;
+        draw_texture_ex(
+            &self.texture,
+            position.x,
+            position.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            },
+        );
+
+        let [x, y, width, height] = config.screen;
+        Rect::new(
+            position.x + x * size.x,
+            position.y + y * size.y,
+            width * size.x,
+            height * size.y,
+        )
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::VecDeque;
+
+use super::palette::Rgb;
+
+/// Averages each frame with the ones before it, so that sprites which are erased and redrawn on
+/// alternate frames show steadily at partial brightness instead of flickering.
+pub(crate) struct FrameBlender {
+    /// The most recent frames, newest last.
+    history: VecDeque<Vec<Rgb>>,
+}
+
+impl FrameBlender {
+    pub(crate) fn new() -> FrameBlender {
+        FrameBlender {
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Forgets the previous frames, e.g. when the screen's resolution changes.
+    pub(crate) fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// Adds `frame` to the history and returns the average of the last `count` frames.
+    pub(crate) fn blend(&mut self, frame: Vec<Rgb>, count: usize) -> Vec<Rgb> {
+        self.history.push_back(frame);
+        while self.history.len() > count.max(1) {
+            self.history.pop_front();
+        }
+
+        let frames = self.history.len() as u32;
+        (0..self.history[0].len())
+            .map(|pixel| {
+                let sum = self.history.iter().fold([0u32; 3], |sum, frame| {
+                    let color = frame[pixel];
+                    [
+                        sum[0] + color.r as u32,
+                        sum[1] + color.g as u32,
+                        sum[2] + color.b as u32,
+                    ]
+                });
+                Rgb::new(
+                    (sum[0] / frames) as u8,
+                    (sum[1] / frames) as u8,
+                    (sum[2] / frames) as u8,
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,122 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Settings for the CRT post-processing pass. Each effect's strength ranges from 0 (off) to 1.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrtConfig {
+    pub enabled: bool,
+    /// How dark the gaps between the emulated screen's rows are.
+    pub scanlines: f32,
+    /// How far the screen bulges outwards, like the glass of a CRT.
+    pub curvature: f32,
+    /// How much the screen darkens towards its corners.
+    pub vignette: f32,
+}
+
+impl Default for CrtConfig {
+    fn default() -> Self {
+        CrtConfig {
+            enabled: false,
+            scanlines: 0.5,
+            curvature: 0.5,
+            vignette: 0.5,
+        }
+    }
+}
+
+/// A shader that makes the screen look like it's shown on a CRT, applied while the emulator's
+/// texture is drawn to the window.
+pub(crate) struct Crt {
+    material: Material,
+}
+
+impl Crt {
+    pub(crate) fn new() -> Result<Crt, macroquad::Error> {
+        let uniforms = ["rows", "scanlines", "curvature", "vignette"]
+            .map(|name| UniformDesc::new(name, UniformType::Float1))
+            .to_vec();
+        let material = load_material(
+            ShaderSource::Glsl {
+                vertex: VERTEX_SHADER,
+                fragment: FRAGMENT_SHADER,
+            },
+            MaterialParams {
+                uniforms,
+                ..Default::default()
+            },
+        )?;
+        Ok(Crt { material })
+    }
+
+    /// Makes the following draws use the shader, with a scanline for each of the screen's `rows`.
+    /// Switch back with `gl_use_default_material`.
+    pub(crate) fn use_material(&self, config: &CrtConfig, rows: usize) {
+        self.material.set_uniform("rows", rows as f32);
+        self.material
+            .set_uniform("scanlines", config.scanlines.clamp(0.0, 1.0));
+        self.material
+            .set_uniform("curvature", config.curvature.clamp(0.0, 1.0));
+        self.material
+            .set_uniform("vignette", config.vignette.clamp(0.0, 1.0));
+        gl_use_material(&self.material);
+    }
+}
+
+const VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying mediump vec2 uv;
+varying mediump vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+";
+
+const FRAGMENT_SHADER: &str = "#version 100
+precision mediump float;
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform sampler2D Texture;
+uniform float rows;
+uniform float scanlines;
+uniform float curvature;
+uniform float vignette;
+
+// Bends the coordinates outwards from the center, more so towards the edges
+vec2 curve(vec2 coord) {
+    coord = coord * 2.0 - 1.0;
+    vec2 offset = abs(coord.yx) * curvature / vec2(5.0, 4.0);
+    coord += coord * offset * offset;
+    return coord * 0.5 + 0.5;
+}
+
+void main() {
+    vec2 coord = curve(uv);
+    if (coord.x < 0.0 || coord.x > 1.0 || coord.y < 0.0 || coord.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 result = texture2D(Texture, coord).rgb * color.rgb;
+
+    // Darkest halfway between the centers of two rows
+    float line = 0.5 + 0.5 * cos(coord.y * rows * 6.2831853);
+    result *= 1.0 - scanlines * 0.6 * (1.0 - line);
+
+    float edges = coord.x * coord.y * (1.0 - coord.x) * (1.0 - coord.y);
+    result *= mix(1.0, clamp(pow(16.0 * edges, 0.3), 0.0, 1.0), vignette);
+
+    gl_FragColor = vec4(result, 1.0);
+}
+";
@@ -0,0 +1,302 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::Path,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+use crate::{NUM_INPUT_KEYS, config::GamepadConfig};
+
+/// How often to try opening the gamepad while it isn't plugged in.
+const RETRY_SECONDS: f32 = 2.0;
+/// How far an axis reads when pushed all the way.
+#[cfg(target_os = "linux")]
+const AXIS_MAX: f32 = 32767.0;
+/// How long the reader thread sleeps when there are no events, between checks for being stopped.
+#[cfg(target_os = "linux")]
+const READ_INTERVAL: std::time::Duration = std::time::Duration::from_millis(4);
+
+/// Controller buttons, named by where they are on an Xbox-style pad so that they mean the same
+/// on any brand. The left stick's directions count as buttons of their own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// A on Xbox pads, cross on PlayStation ones.
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+    StickUp,
+    StickDown,
+    StickLeft,
+    StickRight,
+}
+
+/// Maps the d-pad and left stick to the 5/7/8/9 block most games steer with (W, A, S and D on
+/// the keyboard) and the face buttons to the keys around it.
+pub(crate) fn default_gamepad_buttons() -> BTreeMap<GamepadButton, u8> {
+    BTreeMap::from([
+        (GamepadButton::Up, 0x5),
+        (GamepadButton::Down, 0x8),
+        (GamepadButton::Left, 0x7),
+        (GamepadButton::Right, 0x9),
+        (GamepadButton::South, 0x6),
+        (GamepadButton::East, 0x4),
+        (GamepadButton::West, 0xA),
+        (GamepadButton::North, 0xB),
+        (GamepadButton::LeftShoulder, 0x1),
+        (GamepadButton::RightShoulder, 0x3),
+        (GamepadButton::Select, 0xC),
+        (GamepadButton::Start, 0xF),
+        (GamepadButton::StickUp, 0x5),
+        (GamepadButton::StickDown, 0x8),
+        (GamepadButton::StickLeft, 0x7),
+        (GamepadButton::StickRight, 0x9),
+    ])
+}
+
+/// Reads the gamepad buttons from the config file, checking that they map to keypad keys.
+pub(crate) fn deserialize_gamepad_buttons<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<GamepadButton, u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let buttons = BTreeMap::<GamepadButton, u8>::deserialize(deserializer)?;
+    if let Some((button, key_value)) = buttons
+        .iter()
+        .find(|(_, key_value)| **key_value as usize >= NUM_INPUT_KEYS)
+    {
+        return Err(de::Error::custom(format!(
+            "{button:?} is mapped to {key_value}, which isn't a keypad key from 0 to 0xF"
+        )));
+    }
+    Ok(buttons)
+}
+
+/// A configured gamepad, opened once it's plugged in and reopened if it's unplugged.
+#[derive(Default)]
+pub(crate) struct GamepadPort {
+    gamepad: Option<Gamepad>,
+    retry_time: f32,
+}
+
+impl GamepadPort {
+    /// The keypad keys held on the gamepad `config` describes, along with a message to show if it
+    /// was just connected or disconnected. `elapsed` is the time since the last poll.
+    pub(crate) fn poll(
+        &mut self,
+        config: &GamepadConfig,
+        elapsed: f32,
+    ) -> ([bool; NUM_INPUT_KEYS], Option<&'static str>) {
+        if !config.enabled {
+            self.gamepad = None;
+            return ([false; NUM_INPUT_KEYS], None);
+        }
+
+        let mut message = None;
+        if let Some(gamepad) = &self.gamepad
+            && !gamepad.is_connected()
+        {
+            self.gamepad = None;
+            message = Some("Gamepad disconnected");
+        }
+        if self.gamepad.is_none() {
+            self.retry_time -= elapsed;
+            if self.retry_time <= 0.0 {
+                self.retry_time = RETRY_SECONDS;
+                // Failing to open it usually just means nothing's plugged in
+                if let Ok(gamepad) = Gamepad::open(&config.device) {
+                    self.gamepad = Some(gamepad);
+                    message = Some("Gamepad connected");
+                }
+            }
+        }
+
+        let held = match &self.gamepad {
+            Some(gamepad) => gamepad.held(&config.buttons, config.deadzone),
+            None => [false; NUM_INPUT_KEYS],
+        };
+        (held, message)
+    }
+}
+
+/// What the reader thread last saw of the controller.
+#[derive(Default)]
+struct GamepadState {
+    buttons: Vec<bool>,
+    axes: Vec<i16>,
+    connected: bool,
+}
+
+/// A game controller, read from the Linux joystick interface on a background thread, which is
+/// stopped and joined when it's dropped.
+struct Gamepad {
+    state: Arc<Mutex<GamepadState>>,
+    stop: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Gamepad {
+    /// Starts reading the joystick device at `path`, such as `/dev/input/js0`.
+    #[cfg(target_os = "linux")]
+    fn open(path: &Path) -> io::Result<Gamepad> {
+        use std::{fs::OpenOptions, io::Read, os::unix::fs::OpenOptionsExt};
+
+        // O_NONBLOCK, so that the reader thread can notice it's been stopped between events
+        let mut device = OpenOptions::new()
+            .read(true)
+            .custom_flags(0o4000)
+            .open(path)?;
+        let state = Arc::new(Mutex::new(GamepadState {
+            connected: true,
+            ..GamepadState::default()
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let shared = Arc::clone(&state);
+        let stopped = Arc::clone(&stop);
+        let reader = std::thread::spawn(move || {
+            // Each event is a timestamp, then the new value, the event type and the number of
+            // the button or axis that changed
+            let mut event = [0; 8];
+            while !stopped.load(Ordering::Relaxed) {
+                match device.read(&mut event) {
+                    Ok(8) => {}
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(READ_INTERVAL);
+                        continue;
+                    }
+                    // Reads fail once the controller is unplugged
+                    _ => break,
+                }
+                let value = i16::from_le_bytes([event[4], event[5]]);
+                let number = event[7] as usize;
+                let mut state = shared.lock().unwrap_or_else(PoisonError::into_inner);
+                // The initial state is sent as events with 0x80 set
+                match event[6] & !0x80 {
+                    0x01 => {
+                        if state.buttons.len() <= number {
+                            state.buttons.resize(number + 1, false);
+                        }
+                        state.buttons[number] = value != 0;
+                    }
+                    0x02 => {
+                        if state.axes.len() <= number {
+                            state.axes.resize(number + 1, 0);
+                        }
+                        state.axes[number] = value;
+                    }
+                    _ => {}
+                }
+            }
+            shared
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .connected = false;
+        });
+
+        Ok(Gamepad {
+            state,
+            stop,
+            reader: Some(reader),
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn open(_path: &Path) -> io::Result<Gamepad> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "gamepads are only supported on Linux",
+        ))
+    }
+
+    /// Whether the controller is still plugged in.
+    fn is_connected(&self) -> bool {
+        self.state().connected
+    }
+
+    /// Which keypad keys are held down, with buttons mapped by `buttons`. The stick has to be
+    /// pushed further than `deadzone`, from 0 to 1, to press its keys.
+    fn held(&self, buttons: &BTreeMap<GamepadButton, u8>, deadzone: f32) -> [bool; NUM_INPUT_KEYS] {
+        let state = self.state();
+        let mut held = [false; NUM_INPUT_KEYS];
+        for (button, key_value) in buttons {
+            if let Some(key) = held.get_mut(*key_value as usize) {
+                *key |= state.is_pressed(*button, deadzone);
+            }
+        }
+        held
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, GamepadState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for Gamepad {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+impl GamepadState {
+    /// Whether `button` is held, going by the layout the Linux xpad driver reports Xbox-style
+    /// pads with, which most other drivers follow.
+    #[cfg(target_os = "linux")]
+    fn is_pressed(&self, button: GamepadButton, deadzone: f32) -> bool {
+        let button_down = |number: usize| self.buttons.get(number).copied().unwrap_or(false);
+        // The left stick's axes are 0 and 1, the d-pad's 6 and 7, which only read 0 or fully
+        // pushed
+        let axis = |number: usize| self.axes.get(number).copied().unwrap_or(0) as f32 / AXIS_MAX;
+        let pushed = |number: usize, negative: bool| {
+            let value = if negative {
+                -axis(number)
+            } else {
+                axis(number)
+            };
+            value > deadzone.clamp(0.0, 0.99)
+        };
+
+        match button {
+            GamepadButton::Up => pushed(7, true),
+            GamepadButton::Down => pushed(7, false),
+            GamepadButton::Left => pushed(6, true),
+            GamepadButton::Right => pushed(6, false),
+            GamepadButton::StickUp => pushed(1, true),
+            GamepadButton::StickDown => pushed(1, false),
+            GamepadButton::StickLeft => pushed(0, true),
+            GamepadButton::StickRight => pushed(0, false),
+            GamepadButton::South => button_down(0),
+            GamepadButton::East => button_down(1),
+            GamepadButton::West => button_down(2),
+            GamepadButton::North => button_down(3),
+            GamepadButton::LeftShoulder => button_down(4),
+            GamepadButton::RightShoulder => button_down(5),
+            GamepadButton::Select => button_down(6),
+            GamepadButton::Start => button_down(7),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn is_pressed(&self, _button: GamepadButton, _deadzone: f32) -> bool {
+        false
+    }
+}
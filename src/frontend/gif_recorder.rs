@@ -0,0 +1,91 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use gif::{DisposalMethod, Encoder, EncodingError, Frame, Repeat};
+use macroquad::texture::Image;
+
+use super::screenshot;
+
+/// How often frames are captured, matching the CHIP-8's 60Hz display.
+const CAPTURE_RATE: f32 = 60.0;
+/// How hard NeuQuant works to pick colors when a frame has more than 256, from 1 (best) to 30.
+const QUANTIZE_SPEED: i32 = 10;
+
+/// Records the screen to an animated GIF.
+pub(crate) struct GifRecorder {
+    encoder: Encoder<BufWriter<File>>,
+    width: usize,
+    height: usize,
+    /// Seconds since recording started.
+    elapsed: f32,
+    next_capture: f32,
+    /// The last frame captured and when, in centiseconds, which is written once the next one
+    /// shows how long it was on screen for.
+    pending: Option<(Vec<u8>, u32)>,
+}
+
+impl GifRecorder {
+    /// Starts recording to `path`. The GIF is the size of `image` times `scale`, and later
+    /// frames at other resolutions are stretched to fit.
+    pub(crate) fn start(
+        path: &Path,
+        image: &Image,
+        scale: u32,
+    ) -> Result<GifRecorder, EncodingError> {
+        let scale = scale.max(1) as usize;
+        let (width, height) = (image.width() * scale, image.height() * scale);
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = Encoder::new(file, width as u16, height as u16, &[])?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        Ok(GifRecorder {
+            encoder,
+            width,
+            height,
+            elapsed: 0.0,
+            next_capture: 0.0,
+            pending: None,
+        })
+    }
+
+    /// Captures `image` if a frame is due, `frame_time` seconds after the last call.
+    pub(crate) fn capture(&mut self, image: &Image, frame_time: f32) -> Result<(), EncodingError> {
+        self.elapsed += frame_time;
+        if self.elapsed < self.next_capture {
+            return Ok(());
+        }
+        self.next_capture += 1.0 / CAPTURE_RATE;
+
+        let time = (self.elapsed * 100.0).round() as u32;
+        let pixels = screenshot::resize(image, self.width, self.height);
+        if let Some((previous, start)) = self.pending.replace((pixels, time)) {
+            self.write(previous, time.saturating_sub(start))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the last frame and the end of the GIF.
+    pub(crate) fn finish(mut self) -> Result<(), EncodingError> {
+        if let Some((last, _)) = self.pending.take() {
+            self.write(last, 2)?;
+        }
+        self.encoder.into_inner()?;
+        Ok(())
+    }
+
+    fn write(&mut self, mut pixels: Vec<u8>, delay: u32) -> Result<(), EncodingError> {
+        // A frame shorter than a centisecond is dropped, the next one covering its time
+        if delay == 0 {
+            return Ok(());
+        }
+
+        let mut frame = Frame::from_rgba_speed(
+            self.width as u16,
+            self.height as u16,
+            &mut pixels,
+            QUANTIZE_SPEED,
+        );
+        frame.delay = delay.min(u16::MAX as u32) as u16;
+        frame.dispose = DisposalMethod::Keep;
+        self.encoder.write_frame(&frame)
+    }
+}
@@ -0,0 +1,429 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use macroquad::input::{KeyCode, is_key_down, is_key_pressed};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser::SerializeMap};
+
+use super::{
+    keymap::Keymap,
+    keys::{key_name, parse_key},
+};
+
+/// Emulator controls which can be bound to keys, separate from the CHIP-8 keypad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HotkeyAction {
+    Menu,
+    Pause,
+    FrameAdvance,
+    Reset,
+    SaveState,
+    LoadState,
+    SpeedUp,
+    SpeedDown,
+    NormalSpeed,
+    InfoPanel,
+    NextScaler,
+    NextPalette,
+    NextTheme,
+    FrameBlending,
+    Fullscreen,
+    PixelGrid,
+    KeypadPanel,
+    Screenshot,
+    DumpScreen,
+    RecordGif,
+    RecordVideo,
+    RecordMovie,
+    Hud,
+    Mute,
+    WindowLarger,
+    WindowSmaller,
+}
+
+impl HotkeyAction {
+    pub const ALL: &[HotkeyAction] = &[
+        HotkeyAction::Menu,
+        HotkeyAction::Pause,
+        HotkeyAction::FrameAdvance,
+        HotkeyAction::Reset,
+        HotkeyAction::SaveState,
+        HotkeyAction::LoadState,
+        HotkeyAction::SpeedUp,
+        HotkeyAction::SpeedDown,
+        HotkeyAction::NormalSpeed,
+        HotkeyAction::InfoPanel,
+        HotkeyAction::NextScaler,
+        HotkeyAction::NextPalette,
+        HotkeyAction::NextTheme,
+        HotkeyAction::FrameBlending,
+        HotkeyAction::Fullscreen,
+        HotkeyAction::PixelGrid,
+        HotkeyAction::KeypadPanel,
+        HotkeyAction::Screenshot,
+        HotkeyAction::DumpScreen,
+        HotkeyAction::RecordGif,
+        HotkeyAction::RecordVideo,
+        HotkeyAction::RecordMovie,
+        HotkeyAction::Hud,
+        HotkeyAction::Mute,
+        HotkeyAction::WindowLarger,
+        HotkeyAction::WindowSmaller,
+    ];
+
+    /// The name used for this action in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            HotkeyAction::Menu => "menu",
+            HotkeyAction::Pause => "pause",
+            HotkeyAction::FrameAdvance => "frame_advance",
+            HotkeyAction::Reset => "reset",
+            HotkeyAction::SaveState => "save_state",
+            HotkeyAction::LoadState => "load_state",
+            HotkeyAction::SpeedUp => "speed_up",
+            HotkeyAction::SpeedDown => "speed_down",
+            HotkeyAction::NormalSpeed => "normal_speed",
+            HotkeyAction::InfoPanel => "info_panel",
+            HotkeyAction::NextScaler => "next_scaler",
+            HotkeyAction::NextPalette => "next_palette",
+            HotkeyAction::NextTheme => "next_theme",
+            HotkeyAction::FrameBlending => "frame_blending",
+            HotkeyAction::Fullscreen => "fullscreen",
+            HotkeyAction::PixelGrid => "pixel_grid",
+            HotkeyAction::KeypadPanel => "keypad_panel",
+            HotkeyAction::Screenshot => "screenshot",
+            HotkeyAction::DumpScreen => "dump_screen",
+            HotkeyAction::RecordGif => "record_gif",
+            HotkeyAction::RecordVideo => "record_video",
+            HotkeyAction::RecordMovie => "record_movie",
+            HotkeyAction::Hud => "hud",
+            HotkeyAction::Mute => "mute",
+            HotkeyAction::WindowLarger => "window_larger",
+            HotkeyAction::WindowSmaller => "window_smaller",
+        }
+    }
+
+    /// A human readable description for menus.
+    pub fn label(self) -> &'static str {
+        match self {
+            HotkeyAction::Menu => "Open menu",
+            HotkeyAction::Pause => "Pause",
+            HotkeyAction::FrameAdvance => "Frame advance",
+            HotkeyAction::Reset => "Reset",
+            HotkeyAction::SaveState => "Save state",
+            HotkeyAction::LoadState => "Load state",
+            HotkeyAction::SpeedUp => "Speed up",
+            HotkeyAction::SpeedDown => "Slow down",
+            HotkeyAction::NormalSpeed => "Normal speed",
+            HotkeyAction::InfoPanel => "ROM info panel",
+            HotkeyAction::NextScaler => "Next scaler",
+            HotkeyAction::NextPalette => "Next palette",
+            HotkeyAction::NextTheme => "Next theme",
+            HotkeyAction::FrameBlending => "Frame blending",
+            HotkeyAction::Fullscreen => "Fullscreen",
+            HotkeyAction::PixelGrid => "Pixel grid",
+            HotkeyAction::KeypadPanel => "Keypad panel",
+            HotkeyAction::Screenshot => "Screenshot",
+            HotkeyAction::DumpScreen => "Dump screen",
+            HotkeyAction::RecordGif => "Record GIF",
+            HotkeyAction::RecordVideo => "Record video",
+            HotkeyAction::RecordMovie => "Record movie",
+            HotkeyAction::Hud => "Speed HUD",
+            HotkeyAction::Mute => "Mute",
+            HotkeyAction::WindowLarger => "Larger window",
+            HotkeyAction::WindowSmaller => "Smaller window",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<HotkeyAction> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        let binding = match self {
+            HotkeyAction::Menu => Binding::new(KeyCode::Escape),
+            HotkeyAction::Pause => Binding::new(KeyCode::P),
+            HotkeyAction::FrameAdvance => Binding::new(KeyCode::Backslash),
+            HotkeyAction::Reset => Binding::new(KeyCode::F2),
+            HotkeyAction::SaveState => Binding::new(KeyCode::F5),
+            HotkeyAction::LoadState => Binding::new(KeyCode::F9),
+            HotkeyAction::SpeedUp => Binding::new(KeyCode::Equal),
+            HotkeyAction::SpeedDown => Binding::new(KeyCode::Minus),
+            HotkeyAction::NormalSpeed => Binding::new(KeyCode::Key0),
+            HotkeyAction::InfoPanel => Binding::new(KeyCode::F1),
+            HotkeyAction::NextScaler => Binding::new(KeyCode::F3),
+            HotkeyAction::NextPalette => Binding::new(KeyCode::F4),
+            HotkeyAction::NextTheme => Binding {
+                shift: true,
+                ..Binding::new(KeyCode::F4)
+            },
+            HotkeyAction::FrameBlending => Binding::new(KeyCode::F6),
+            HotkeyAction::Fullscreen => {
+                let alt_enter = Binding {
+                    alt: true,
+                    ..Binding::new(KeyCode::Enter)
+                };
+                return vec![Binding::new(KeyCode::F11), alt_enter];
+            }
+            HotkeyAction::PixelGrid => Binding::new(KeyCode::F7),
+            HotkeyAction::KeypadPanel => Binding::new(KeyCode::K),
+            HotkeyAction::Screenshot => Binding::new(KeyCode::F12),
+            HotkeyAction::DumpScreen => Binding::new(KeyCode::T),
+            HotkeyAction::RecordGif => Binding::new(KeyCode::F8),
+            HotkeyAction::RecordVideo => Binding::new(KeyCode::F10),
+            HotkeyAction::RecordMovie => Binding {
+                shift: true,
+                ..Binding::new(KeyCode::F8)
+            },
+            HotkeyAction::Hud => Binding::new(KeyCode::H),
+            HotkeyAction::Mute => Binding::new(KeyCode::M),
+            HotkeyAction::WindowLarger => Binding {
+                ctrl: true,
+                ..Binding::new(KeyCode::Equal)
+            },
+            HotkeyAction::WindowSmaller => Binding {
+                ctrl: true,
+                ..Binding::new(KeyCode::Minus)
+            },
+        };
+        vec![binding]
+    }
+}
+
+/// A key plus the modifiers which must be held with it, written like `Ctrl+Shift+S`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl Binding {
+    pub const fn new(key: KeyCode) -> Binding {
+        Binding {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    /// Creates a binding for `key` using whichever modifiers are currently held.
+    pub fn with_held_modifiers(key: KeyCode) -> Binding {
+        let (ctrl, alt, shift) = held_modifiers();
+        Binding {
+            key,
+            ctrl,
+            alt,
+            shift,
+        }
+    }
+
+    /// Whether the binding's key was pressed this frame with exactly its modifiers held.
+    pub fn is_pressed(&self) -> bool {
+        is_key_pressed(self.key) && held_modifiers() == (self.ctrl, self.alt, self.shift)
+    }
+}
+
+fn held_modifiers() -> (bool, bool, bool) {
+    (
+        is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl),
+        is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt),
+        is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift),
+    )
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{}", key_name(self.key).unwrap_or("Unknown"))
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key_part = parts.pop().unwrap_or_default();
+        let key = parse_key(key_part).ok_or_else(|| format!("unknown key \"{key_part}\""))?;
+
+        let mut binding = Binding::new(key);
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => binding.ctrl = true,
+                "alt" => binding.alt = true,
+                "shift" => binding.shift = true,
+                _ => return Err(format!("unknown modifier \"{modifier}\"")),
+            }
+        }
+
+        Ok(binding)
+    }
+}
+
+/// Reasons a hotkey binding can't be used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conflict {
+    /// The key is also mapped to a CHIP-8 keypad key, so games would see it being pressed.
+    Keypad { binding: Binding, key_value: u8 },
+    /// The binding is already used by another action.
+    Hotkey {
+        binding: Binding,
+        other: HotkeyAction,
+    },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Keypad { binding, key_value } => {
+                write!(f, "{binding} is mapped to keypad key {key_value:X}")
+            }
+            Conflict::Hotkey { binding, other } => {
+                write!(f, "{binding} is already bound to \"{}\"", other.label())
+            }
+        }
+    }
+}
+
+/// The mapping from emulator actions to the keys that trigger them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hotkeys {
+    bindings: BTreeMap<HotkeyAction, Vec<Binding>>,
+}
+
+impl Hotkeys {
+    pub fn bindings(&self, action: HotkeyAction) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set(&mut self, action: HotkeyAction, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+
+    /// The actions whose bindings were pressed this frame.
+    pub fn pressed(&self) -> Vec<HotkeyAction> {
+        self.bindings
+            .iter()
+            .filter(|(_, bindings)| bindings.iter().any(Binding::is_pressed))
+            .map(|(action, _)| *action)
+            .collect()
+    }
+
+    /// Checks whether `binding` could be assigned to `action` without clashing with the keypad,
+    /// as mapped by `keymap`, or another action.
+    pub fn conflict(
+        &self,
+        action: HotkeyAction,
+        binding: Binding,
+        keymap: &Keymap,
+    ) -> Option<Conflict> {
+        if let Some(key_value) = keymap.key_value(binding.key) {
+            return Some(Conflict::Keypad { binding, key_value });
+        }
+
+        self.bindings
+            .iter()
+            .find(|(other, bindings)| **other != action && bindings.contains(&binding))
+            .map(|(other, _)| Conflict::Hotkey {
+                binding,
+                other: *other,
+            })
+    }
+
+    /// The action bound to `key`, with or without modifiers, which would clash with mapping the
+    /// key to the keypad.
+    pub fn action_for_key(&self, key: KeyCode) -> Option<HotkeyAction> {
+        self.bindings
+            .iter()
+            .find(|(_, bindings)| bindings.iter().any(|binding| binding.key == key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Every conflict in the current bindings.
+    pub fn conflicts(&self, keymap: &Keymap) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                if let Some(conflict) = self.conflict(*action, *binding, keymap) {
+                    // Only report hotkey clashes once per pair
+                    if let Conflict::Hotkey { other, .. } = conflict
+                        && other < *action
+                    {
+                        continue;
+                    }
+                    conflicts.push(conflict);
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Hotkeys {
+            bindings: HotkeyAction::ALL
+                .iter()
+                .map(|action| (*action, action.default_bindings()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BindingList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Serialize for Hotkeys {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.bindings.len()))?;
+        for (action, bindings) in &self.bindings {
+            let names: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+            match names.as_slice() {
+                [name] => map.serialize_entry(action.name(), name)?,
+                _ => map.serialize_entry(action.name(), &names)?,
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Hotkeys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Start from the defaults so that a config only needs to list the bindings it changes
+        let mut hotkeys = Hotkeys::default();
+        let entries = BTreeMap::<String, BindingList>::deserialize(deserializer)?;
+
+        for (name, list) in entries {
+            let action = HotkeyAction::from_name(&name)
+                .ok_or_else(|| de::Error::custom(format!("unknown hotkey action \"{name}\"")))?;
+            let names = match list {
+                BindingList::One(name) => vec![name],
+                BindingList::Many(names) => names,
+            };
+            let bindings = names
+                .iter()
+                .map(|name| name.parse())
+                .collect::<Result<Vec<Binding>, String>>()
+                .map_err(de::Error::custom)?;
+            hotkeys.set(action, bindings);
+        }
+
+        Ok(hotkeys)
+    }
+}
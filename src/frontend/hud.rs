@@ -0,0 +1,108 @@
+use macroquad::prelude::*;
+
+use crate::{Emulator, TARGET_OPS_PER_SECOND, Timing};
+
+use super::{FONT_SIZE, MARGIN};
+
+/// How often the rates are recalculated, in seconds.
+const SAMPLE_SECONDS: f32 = 0.5;
+
+/// Markers for what the emulator is doing, such as `[PAUSED] [2x speed] [REC]`. They're drawn
+/// in the window since its title can't be changed once it's open.
+pub(crate) fn status_markers(paused: bool, speed: f32, recording: bool) -> String {
+    let mut markers = Vec::new();
+    if paused {
+        markers.push(String::from("[PAUSED]"));
+    }
+    if speed != 1.0 {
+        markers.push(format!("[{speed}x speed]"));
+    }
+    if recording {
+        markers.push(String::from("[REC]"));
+    }
+    markers.join(" ")
+}
+
+/// An overlay showing how fast the emulator is actually running, to help diagnose speed
+/// problems.
+pub(crate) struct Hud {
+    /// Frames drawn and time passed since the rates were last calculated.
+    frames: u32,
+    elapsed: f32,
+    cycles: u64,
+    fps: f32,
+    ips: f32,
+}
+
+impl Hud {
+    pub(crate) fn new(emulator: &Emulator) -> Hud {
+        Hud {
+            frames: 0,
+            elapsed: 0.0,
+            cycles: emulator.cycles(),
+            fps: 0.0,
+            ips: 0.0,
+        }
+    }
+
+    /// Counts a frame that took `frame_time` seconds.
+    pub(crate) fn update(&mut self, emulator: &Emulator, frame_time: f32) {
+        self.frames += 1;
+        self.elapsed += frame_time;
+        if self.elapsed < SAMPLE_SECONDS {
+            return;
+        }
+
+        // Resets rewind the cycle count
+        let cycles = emulator.cycles().saturating_sub(self.cycles);
+        self.fps = self.frames as f32 / self.elapsed;
+        self.ips = cycles as f32 / self.elapsed;
+        self.frames = 0;
+        self.elapsed = 0.0;
+        self.cycles = emulator.cycles();
+    }
+
+    /// Draws the overlay in the top right corner, headed by `title`. `speed` is the speed
+    /// multiplier the target instruction rate is scaled by.
+    pub(crate) fn draw(&self, emulator: &Emulator, title: &str, speed: f32, muted: bool) {
+        let ips = match emulator.profile().timing {
+            Timing::Fixed => format!(
+                "IPS: {:.0} / {:.0}",
+                self.ips,
+                TARGET_OPS_PER_SECOND as f32 * speed
+            ),
+            Timing::Vip => format!("IPS: {:.0} (VIP timing)", self.ips),
+        };
+        let lines: Vec<String> = [
+            title.to_string(),
+            format!("FPS: {:.1}", self.fps),
+            ips,
+            format!(
+                "DT: {:3}  ST: {:3}{}",
+                emulator.delay_timer(),
+                emulator.sound_timer(),
+                if muted { " (muted)" } else { "" }
+            ),
+        ]
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .collect();
+
+        let width = lines
+            .iter()
+            .map(|line| measure_text(line, None, FONT_SIZE as u16, 1.0).width)
+            .fold(0.0, f32::max);
+        let x = screen_width() - width - MARGIN * 2.0;
+        draw_rectangle(
+            x,
+            0.0,
+            width + MARGIN * 2.0,
+            FONT_SIZE * lines.len() as f32 + MARGIN * 2.0,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+        for (index, line) in lines.iter().enumerate() {
+            let y = MARGIN + FONT_SIZE * (index + 1) as f32;
+            draw_text(line, x + MARGIN, y, FONT_SIZE, WHITE);
+        }
+    }
+}
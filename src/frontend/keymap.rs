@@ -0,0 +1,229 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use macroquad::input::{KeyCode, is_key_down, is_key_pressed};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Unexpected},
+    ser::SerializeMap,
+};
+
+use crate::{NUM_INPUT_KEYS, error::UnknownLayoutError};
+
+use super::keys::{key_name, parse_key};
+
+/// The COSMAC VIP's hex keypad, row by row, as laid out on the left side of the keyboard.
+const KEYPAD_ORDER: [u8; NUM_INPUT_KEYS] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// The numeric keypad, with each digit on its own key and A to F on the keys around them.
+const NUMPAD_KEYS: [(KeyCode, u8); NUM_INPUT_KEYS] = [
+    (KeyCode::Kp0, 0x0),
+    (KeyCode::Kp1, 0x1),
+    (KeyCode::Kp2, 0x2),
+    (KeyCode::Kp3, 0x3),
+    (KeyCode::Kp4, 0x4),
+    (KeyCode::Kp5, 0x5),
+    (KeyCode::Kp6, 0x6),
+    (KeyCode::Kp7, 0x7),
+    (KeyCode::Kp8, 0x8),
+    (KeyCode::Kp9, 0x9),
+    (KeyCode::KpDivide, 0xA),
+    (KeyCode::KpMultiply, 0xB),
+    (KeyCode::KpSubtract, 0xC),
+    (KeyCode::KpAdd, 0xD),
+    (KeyCode::KpEnter, 0xE),
+    (KeyCode::KpDecimal, 0xF),
+];
+
+/// Keyboard layouts the keypad can be placed on, so that it stays in the same 4x4 block of keys
+/// whatever they're labeled. The windowing backend reports keys by the character they type
+/// rather than where they are, so each layout lists the keys found in that block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    /// 1 2 3 4, Q W E R, A S D F and Z X C V.
+    #[default]
+    Qwerty,
+    /// The French layout, with A and Z swapped with Q and W, and W in place of Z. Its number row
+    /// types symbols without Shift, so the block's top row is left out and every keypad key is
+    /// also on the numeric keypad instead.
+    Azerty,
+    /// The German layout, with Y in place of Z.
+    Qwertz,
+    Dvorak,
+    Colemak,
+}
+
+impl KeyboardLayout {
+    pub const ALL: [KeyboardLayout; 5] = [
+        KeyboardLayout::Qwerty,
+        KeyboardLayout::Azerty,
+        KeyboardLayout::Qwertz,
+        KeyboardLayout::Dvorak,
+        KeyboardLayout::Colemak,
+    ];
+
+    /// The name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyboardLayout::Qwerty => "qwerty",
+            KeyboardLayout::Azerty => "azerty",
+            KeyboardLayout::Qwertz => "qwertz",
+            KeyboardLayout::Dvorak => "dvorak",
+            KeyboardLayout::Colemak => "colemak",
+        }
+    }
+
+    /// The keys in the block, row by row.
+    fn keys(self) -> [KeyCode; NUM_INPUT_KEYS] {
+        use KeyCode::*;
+
+        match self {
+            KeyboardLayout::Qwerty => [Key1, Key2, Key3, Key4, Q, W, E, R, A, S, D, F, Z, X, C, V],
+            // The top row is replaced by the numeric keypad in the keymap
+            KeyboardLayout::Azerty => [Key1, Key2, Key3, Key4, A, Z, E, R, Q, S, D, F, W, X, C, V],
+            KeyboardLayout::Qwertz => [Key1, Key2, Key3, Key4, Q, W, E, R, A, S, D, F, Y, X, C, V],
+            KeyboardLayout::Dvorak => [
+                Key1, Key2, Key3, Key4, Apostrophe, Comma, Period, P, A, O, E, U, Semicolon, Q, J,
+                K,
+            ],
+            KeyboardLayout::Colemak => [Key1, Key2, Key3, Key4, Q, W, F, P, A, R, S, T, Z, X, C, D],
+        }
+    }
+
+    /// The keymap placing the keypad on this layout's block of keys.
+    pub fn keymap(self) -> Keymap {
+        let block = self.keys().into_iter().zip(KEYPAD_ORDER);
+        match self {
+            KeyboardLayout::Azerty => Keymap::new(block.skip(4).chain(NUMPAD_KEYS).collect()),
+            _ => Keymap::new(block.collect()),
+        }
+    }
+}
+
+impl FromStr for KeyboardLayout {
+    type Err = UnknownLayoutError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        KeyboardLayout::ALL
+            .into_iter()
+            .find(|layout| layout.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownLayoutError(name.to_string()))
+    }
+}
+
+/// Which keyboard keys press which CHIP-8 keypad keys. Several keyboard keys may press the same
+/// keypad key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    keys: Vec<(KeyCode, u8)>,
+}
+
+impl Keymap {
+    /// Sorted by keypad key, so that keymaps listing the same keys in another order are equal.
+    fn new(mut keys: Vec<(KeyCode, u8)>) -> Keymap {
+        keys.sort_by_key(|(keycode, key_value)| (*key_value, key_name(*keycode)));
+        Keymap { keys }
+    }
+
+    /// A keymap without any keys, for controls that only add to the main keymap.
+    pub fn empty() -> Keymap {
+        Keymap { keys: Vec::new() }
+    }
+
+    /// The keypad key that `keycode` presses, if any.
+    pub fn key_value(&self, keycode: KeyCode) -> Option<u8> {
+        self.keys
+            .iter()
+            .find(|(key, _)| *key == keycode)
+            .map(|(_, key_value)| *key_value)
+    }
+
+    /// The keyboard keys that press `key_value`.
+    pub fn keycodes(&self, key_value: u8) -> Vec<KeyCode> {
+        self.keys
+            .iter()
+            .filter(|(_, value)| *value == key_value)
+            .map(|(keycode, _)| *keycode)
+            .collect()
+    }
+
+    /// Makes `keycode` the only key pressing `key_value`, taking it off any other keypad key.
+    pub fn bind(&mut self, keycode: KeyCode, key_value: u8) {
+        let mut keys = std::mem::take(&mut self.keys);
+        keys.retain(|(key, value)| *key != keycode && *value != key_value);
+        keys.push((keycode, key_value));
+        *self = Keymap::new(keys);
+    }
+
+    /// Which keypad keys were pressed this frame.
+    pub fn pressed(&self) -> [bool; NUM_INPUT_KEYS] {
+        let mut pressed = [false; NUM_INPUT_KEYS];
+        for (keycode, key_value) in &self.keys {
+            pressed[*key_value as usize] |= is_key_pressed(*keycode);
+        }
+        pressed
+    }
+
+    /// Which keypad keys are held down.
+    pub fn held(&self) -> [bool; NUM_INPUT_KEYS] {
+        let mut held = [false; NUM_INPUT_KEYS];
+        for (keycode, key_value) in &self.keys {
+            held[*key_value as usize] |= is_key_down(*keycode);
+        }
+        held
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        KeyboardLayout::default().keymap()
+    }
+}
+
+impl Serialize for Keymap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.keys.len()))?;
+        for (keycode, key_value) in &self.keys {
+            map.serialize_entry(key_name(*keycode).unwrap_or("Unknown"), key_value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Entries {
+            Layout(String),
+            Keys(BTreeMap<String, u8>),
+        }
+
+        // A keymap replaces the default layout rather than adding to it, so keys can be freed
+        let entries = match Entries::deserialize(deserializer)? {
+            Entries::Layout(name) => {
+                return name
+                    .parse()
+                    .map(KeyboardLayout::keymap)
+                    .map_err(de::Error::custom);
+            }
+            Entries::Keys(entries) => entries,
+        };
+        let keys = entries
+            .into_iter()
+            .map(|(name, key_value)| {
+                let keycode = parse_key(&name)
+                    .ok_or_else(|| de::Error::custom(format!("unknown key \"{name}\"")))?;
+                if key_value as usize >= NUM_INPUT_KEYS {
+                    return Err(de::Error::invalid_value(
+                        Unexpected::Unsigned(key_value as u64),
+                        &"a keypad key from 0 to 0xF",
+                    ));
+                }
+                Ok((keycode, key_value))
+            })
+            .collect::<Result<_, D::Error>>()?;
+        Ok(Keymap::new(keys))
+    }
+}
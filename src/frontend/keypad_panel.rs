@@ -0,0 +1,106 @@
+use macroquad::prelude::*;
+
+use crate::NUM_INPUT_KEYS;
+
+/// The keypad's keys as laid out on the COSMAC VIP, row by row.
+const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+/// How much of the window the panel may take up, across the side it's placed along.
+const MAX_SHARE: f32 = 0.4;
+/// The gap around and between keys, as a fraction of a key's size.
+const GAP: f32 = 0.1;
+const KEY_COLOR: Color = Color::new(0.2, 0.2, 0.2, 1.0);
+const PRESSED_COLOR: Color = Color::new(0.9, 0.6, 0.1, 1.0);
+const LABEL_COLOR: Color = Color::new(0.85, 0.85, 0.85, 1.0);
+
+/// Splits `area` between the screen and the panel, putting the panel beside a screen that's
+/// wider than the area, or otherwise below it. Returns the screen's part, then the panel's.
+pub(crate) fn layout(area: Rect, screen_width: f32, screen_height: f32) -> (Rect, Rect) {
+    let screen_aspect = screen_width / screen_height;
+    let beside = area.w / area.h > screen_aspect;
+    if beside {
+        let size = area.h.min(area.w * MAX_SHARE);
+        let panel = Rect::new(
+            area.right() - size,
+            area.y + (area.h - size) / 2.0,
+            size,
+            size,
+        );
+        (Rect::new(area.x, area.y, area.w - size, area.h), panel)
+    } else {
+        let size = area.w.min(area.h * MAX_SHARE);
+        let panel = Rect::new(
+            area.x + (area.w - size) / 2.0,
+            area.bottom() - size,
+            size,
+            size,
+        );
+        (Rect::new(area.x, area.y, area.w, area.h - size), panel)
+    }
+}
+
+/// Which keys are being pressed on the panel at `panel`, by the left mouse button or a touch.
+pub(crate) fn held(panel: Rect) -> [bool; NUM_INPUT_KEYS] {
+    let mut held = [false; NUM_INPUT_KEYS];
+    let mouse = is_mouse_button_down(MouseButton::Left).then(|| Vec2::from(mouse_position()));
+    let touches = touches()
+        .into_iter()
+        .filter(|touch| !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled))
+        .map(|touch| touch.position);
+
+    for point in mouse.into_iter().chain(touches) {
+        for (key_rect, key_value) in keys(panel) {
+            if key_rect.contains(point) {
+                held[key_value as usize] = true;
+            }
+        }
+    }
+    held
+}
+
+/// Draws the panel at `panel`, lighting up the keys in `pressed`.
+pub(crate) fn draw(panel: Rect, pressed: &[bool; NUM_INPUT_KEYS]) {
+    for (key_rect, key_value) in keys(panel) {
+        let color = if pressed[key_value as usize] {
+            PRESSED_COLOR
+        } else {
+            KEY_COLOR
+        };
+        draw_rectangle(key_rect.x, key_rect.y, key_rect.w, key_rect.h, color);
+
+        let label = format!("{key_value:X}");
+        let font_size = (key_rect.h * 0.5) as u16;
+        let dimensions = measure_text(&label, None, font_size, 1.0);
+        draw_text(
+            &label,
+            key_rect.center().x - dimensions.width / 2.0,
+            key_rect.center().y + dimensions.offset_y / 2.0,
+            font_size as f32,
+            LABEL_COLOR,
+        );
+    }
+}
+
+/// Each key's area within the panel, and its value.
+fn keys(panel: Rect) -> impl Iterator<Item = (Rect, u8)> {
+    // Four keys and five gaps fit across the panel
+    let key_size = panel.w / (4.0 + 5.0 * GAP);
+    let step = key_size * (1.0 + GAP);
+    LAYOUT.into_iter().enumerate().flat_map(move |(row, keys)| {
+        keys.into_iter()
+            .enumerate()
+            .map(move |(column, key_value)| {
+                let rect = Rect::new(
+                    panel.x + key_size * GAP + column as f32 * step,
+                    panel.y + key_size * GAP + row as f32 * step,
+                    key_size,
+                    key_size,
+                );
+                (rect, key_value)
+            })
+    })
+}
@@ -0,0 +1,127 @@
+use macroquad::input::KeyCode;
+
+// Names used for keys in config files and menus.
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Space, "Space"),
+    (KeyCode::Apostrophe, "Apostrophe"),
+    (KeyCode::Comma, "Comma"),
+    (KeyCode::Minus, "Minus"),
+    (KeyCode::Period, "Period"),
+    (KeyCode::Slash, "Slash"),
+    (KeyCode::Key0, "0"),
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+    (KeyCode::Key5, "5"),
+    (KeyCode::Key6, "6"),
+    (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"),
+    (KeyCode::Key9, "9"),
+    (KeyCode::Semicolon, "Semicolon"),
+    (KeyCode::Equal, "Equal"),
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::LeftBracket, "LeftBracket"),
+    (KeyCode::Backslash, "Backslash"),
+    (KeyCode::RightBracket, "RightBracket"),
+    (KeyCode::GraveAccent, "GraveAccent"),
+    (KeyCode::Escape, "Escape"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PrintScreen, "PrintScreen"),
+    (KeyCode::Pause, "Pause"),
+    (KeyCode::F1, "F1"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::F11, "F11"),
+    (KeyCode::F12, "F12"),
+    (KeyCode::Kp0, "Kp0"),
+    (KeyCode::Kp1, "Kp1"),
+    (KeyCode::Kp2, "Kp2"),
+    (KeyCode::Kp3, "Kp3"),
+    (KeyCode::Kp4, "Kp4"),
+    (KeyCode::Kp5, "Kp5"),
+    (KeyCode::Kp6, "Kp6"),
+    (KeyCode::Kp7, "Kp7"),
+    (KeyCode::Kp8, "Kp8"),
+    (KeyCode::Kp9, "Kp9"),
+    (KeyCode::KpDecimal, "KpDecimal"),
+    (KeyCode::KpDivide, "KpDivide"),
+    (KeyCode::KpMultiply, "KpMultiply"),
+    (KeyCode::KpSubtract, "KpSubtract"),
+    (KeyCode::KpAdd, "KpAdd"),
+    (KeyCode::KpEnter, "KpEnter"),
+    (KeyCode::KpEqual, "KpEqual"),
+];
+
+pub fn key_name(keycode: KeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(code, _)| *code == keycode)
+        .map(|(_, name)| *name)
+}
+
+/// Looks up a key by its config name, ignoring case.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, key_name)| key_name.eq_ignore_ascii_case(name))
+        .map(|(code, _)| *code)
+}
+
+pub fn is_modifier(keycode: KeyCode) -> bool {
+    matches!(
+        keycode,
+        KeyCode::LeftShift
+            | KeyCode::RightShift
+            | KeyCode::LeftControl
+            | KeyCode::RightControl
+            | KeyCode::LeftAlt
+            | KeyCode::RightAlt
+            | KeyCode::LeftSuper
+            | KeyCode::RightSuper
+    )
+}
@@ -0,0 +1,385 @@
+use macroquad::prelude::*;
+
+use crate::{Chip8Variant, NUM_INPUT_KEYS, config::Config};
+
+use super::{
+    hotkeys::{Binding, HotkeyAction, Hotkeys},
+    keymap::Keymap,
+    keys::{is_modifier, key_name},
+};
+
+const FONT_SIZE: f32 = 24.0;
+const LINE_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 16.0;
+const SELECTED_COLOR: Color = YELLOW;
+const MESSAGE_COLOR: Color = Color::new(1.0, 0.4, 0.4, 1.0);
+
+const MAIN_ITEMS: &[&str] = &[
+    "Resume", "Reset", "Variant", "Display", "Audio", "Keypad", "Hotkeys", "Quit",
+];
+const MAIN_VARIANT_INDEX: usize = 2;
+const MAIN_DISPLAY_INDEX: usize = 3;
+const MAIN_AUDIO_INDEX: usize = 4;
+const MAIN_KEYPAD_INDEX: usize = 5;
+const MAIN_HOTKEYS_INDEX: usize = 6;
+
+/// How many settings the display and audio pages have, before their Back item.
+const DISPLAY_ITEMS: usize = 2;
+const AUDIO_ITEMS: usize = 2;
+const BRIGHTNESS_RANGE: (f32, f32) = (0.5, 1.5);
+const BRIGHTNESS_STEP: f32 = 0.05;
+const GAMMA_RANGE: (f32, f32) = (0.5, 2.5);
+const GAMMA_STEP: f32 = 0.1;
+const VOLUME_RANGE: (f32, f32) = (0.0, 1.0);
+const VOLUME_STEP: f32 = 0.1;
+const FREQUENCY_RANGE: (f32, f32) = (200.0, 2000.0);
+const FREQUENCY_STEP: f32 = 20.0;
+
+/// What the frontend should do in response to the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MenuResult {
+    None,
+    Close,
+    Reset,
+    Quit,
+    /// A setting was changed and the config should be saved.
+    SettingsChanged,
+    /// The keymap was changed and should be saved and played with.
+    KeymapChanged,
+    /// Switch to the variant's profile and restart the program.
+    Variant(Chip8Variant),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Page {
+    Main,
+    Variant,
+    Display,
+    Audio,
+    Keypad,
+    Hotkeys,
+}
+
+/// What the next key pressed is bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Capture {
+    Keypad(u8),
+    Hotkey(HotkeyAction),
+}
+
+/// The pause/settings menu, navigated with the arrow keys, Enter and Escape.
+pub(crate) struct Menu {
+    page: Page,
+    selected: usize,
+    capturing: Option<Capture>,
+    message: Option<String>,
+}
+
+impl Menu {
+    pub(crate) fn new() -> Menu {
+        Menu {
+            page: Page::Main,
+            selected: 0,
+            capturing: None,
+            message: None,
+        }
+    }
+
+    pub(crate) fn update(&mut self, config: &mut Config) -> MenuResult {
+        match self.capturing {
+            Some(Capture::Keypad(key_value)) => {
+                return self.capture_key(key_value, &mut config.keymap, &config.hotkeys);
+            }
+            Some(Capture::Hotkey(action)) => {
+                return self.capture_binding(action, &mut config.hotkeys, &config.keymap);
+            }
+            None => {}
+        }
+
+        let count = self.item_count();
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % count;
+        }
+
+        if is_key_pressed(KeyCode::Escape) {
+            match self.page {
+                Page::Main => return MenuResult::Close,
+                Page::Variant => self.open_page(Page::Main, MAIN_VARIANT_INDEX),
+                Page::Display => self.open_page(Page::Main, MAIN_DISPLAY_INDEX),
+                Page::Audio => self.open_page(Page::Main, MAIN_AUDIO_INDEX),
+                Page::Keypad => self.open_page(Page::Main, MAIN_KEYPAD_INDEX),
+                Page::Hotkeys => self.open_page(Page::Main, MAIN_HOTKEYS_INDEX),
+            }
+        }
+
+        if is_key_pressed(KeyCode::Enter) {
+            match self.page {
+                Page::Main => match MAIN_ITEMS[self.selected] {
+                    "Resume" => return MenuResult::Close,
+                    "Reset" => return MenuResult::Reset,
+                    "Variant" => self.open_page(Page::Variant, 0),
+                    "Display" => self.open_page(Page::Display, 0),
+                    "Audio" => self.open_page(Page::Audio, 0),
+                    "Keypad" => self.open_page(Page::Keypad, 0),
+                    "Hotkeys" => self.open_page(Page::Hotkeys, 0),
+                    "Quit" => return MenuResult::Quit,
+                    _ => {}
+                },
+                Page::Variant => match Chip8Variant::ALL.get(self.selected) {
+                    Some(variant) => return MenuResult::Variant(*variant),
+                    None => self.open_page(Page::Main, MAIN_VARIANT_INDEX),
+                },
+                Page::Display => {
+                    if self.selected == DISPLAY_ITEMS {
+                        self.open_page(Page::Main, MAIN_DISPLAY_INDEX);
+                    }
+                }
+                Page::Audio => {
+                    if self.selected == AUDIO_ITEMS {
+                        self.open_page(Page::Main, MAIN_AUDIO_INDEX);
+                    }
+                }
+                Page::Keypad => {
+                    if self.selected < NUM_INPUT_KEYS {
+                        self.capturing = Some(Capture::Keypad(self.selected as u8));
+                        self.message = None;
+                    } else {
+                        self.open_page(Page::Main, MAIN_KEYPAD_INDEX);
+                    }
+                }
+                Page::Hotkeys => match HotkeyAction::ALL.get(self.selected) {
+                    Some(action) => {
+                        self.capturing = Some(Capture::Hotkey(*action));
+                        self.message = None;
+                    }
+                    None => self.open_page(Page::Main, MAIN_HOTKEYS_INDEX),
+                },
+            }
+        }
+
+        if matches!(self.page, Page::Display | Page::Audio) {
+            let step = if is_key_pressed(KeyCode::Right) {
+                1.0
+            } else if is_key_pressed(KeyCode::Left) {
+                -1.0
+            } else {
+                0.0
+            };
+            if step != 0.0 && self.adjust_setting(config, step) {
+                return MenuResult::SettingsChanged;
+            }
+        }
+
+        MenuResult::None
+    }
+
+    /// Moves the selected setting a step up or down, returning whether it changed.
+    fn adjust_setting(&self, config: &mut Config, step: f32) -> bool {
+        let (value, (min, max), size) = match (self.page, self.selected) {
+            (Page::Display, 0) => (
+                &mut config.display.brightness,
+                BRIGHTNESS_RANGE,
+                BRIGHTNESS_STEP,
+            ),
+            (Page::Display, 1) => (&mut config.display.gamma, GAMMA_RANGE, GAMMA_STEP),
+            (Page::Audio, 0) => (&mut config.audio.volume, VOLUME_RANGE, VOLUME_STEP),
+            (Page::Audio, 1) => (&mut config.audio.frequency, FREQUENCY_RANGE, FREQUENCY_STEP),
+            _ => return false,
+        };
+
+        // Rounded so that repeated steps don't accumulate floating point error
+        let adjusted = ((*value + step * size) * 100.0).round() / 100.0;
+        let adjusted = adjusted.clamp(min, max);
+        if adjusted == *value {
+            return false;
+        }
+        *value = adjusted;
+        true
+    }
+
+    /// Binds the next key pressed to `key_value`, unless it's already a hotkey.
+    fn capture_key(&mut self, key_value: u8, keymap: &mut Keymap, hotkeys: &Hotkeys) -> MenuResult {
+        if is_key_pressed(KeyCode::Escape) {
+            self.capturing = None;
+            self.message = None;
+            return MenuResult::None;
+        }
+
+        let Some(key) = get_last_key_pressed() else {
+            return MenuResult::None;
+        };
+
+        match hotkeys.action_for_key(key) {
+            Some(action) => {
+                self.message = Some(format!(
+                    "{} is bound to \"{}\"",
+                    key_name(key).unwrap_or("Unknown"),
+                    action.label()
+                ));
+                MenuResult::None
+            }
+            None => {
+                keymap.bind(key, key_value);
+                self.capturing = None;
+                self.message = None;
+                MenuResult::KeymapChanged
+            }
+        }
+    }
+
+    fn capture_binding(
+        &mut self,
+        action: HotkeyAction,
+        hotkeys: &mut Hotkeys,
+        keymap: &Keymap,
+    ) -> MenuResult {
+        if is_key_pressed(KeyCode::Escape) {
+            self.capturing = None;
+            self.message = None;
+            return MenuResult::None;
+        }
+
+        let Some(key) = get_last_key_pressed().filter(|key| !is_modifier(*key)) else {
+            return MenuResult::None;
+        };
+
+        let binding = Binding::with_held_modifiers(key);
+        match hotkeys.conflict(action, binding, keymap) {
+            Some(conflict) => {
+                self.message = Some(conflict.to_string());
+                MenuResult::None
+            }
+            None => {
+                hotkeys.set(action, vec![binding]);
+                self.capturing = None;
+                self.message = None;
+                MenuResult::SettingsChanged
+            }
+        }
+    }
+
+    fn open_page(&mut self, page: Page, selected: usize) {
+        self.page = page;
+        self.selected = selected;
+        self.message = None;
+    }
+
+    fn item_count(&self) -> usize {
+        match self.page {
+            Page::Main => MAIN_ITEMS.len(),
+            Page::Variant => Chip8Variant::ALL.len() + 1,
+            Page::Display => DISPLAY_ITEMS + 1,
+            Page::Audio => AUDIO_ITEMS + 1,
+            Page::Keypad => NUM_INPUT_KEYS + 1,
+            Page::Hotkeys => HotkeyAction::ALL.len() + 1,
+        }
+    }
+
+    pub(crate) fn draw(&self, config: &Config) {
+        let hotkeys = &config.hotkeys;
+        let (title, items, hint) = match self.page {
+            Page::Main => (
+                "Menu",
+                MAIN_ITEMS.iter().map(|item| item.to_string()).collect(),
+                "Enter: select   Esc: resume",
+            ),
+            Page::Variant => {
+                let mut items: Vec<String> = Chip8Variant::ALL
+                    .iter()
+                    .map(|variant| format!("{variant} ({})", variant.name()))
+                    .collect();
+                items.push(String::from("Back"));
+                ("Variant", items, "Enter: switch and reset   Esc: back")
+            }
+            Page::Display => {
+                let display = &config.display;
+                let items = vec![
+                    format!("Brightness: {:.0}%", display.brightness * 100.0),
+                    format!("Gamma: {:.1}", display.gamma),
+                    String::from("Back"),
+                ];
+                ("Display", items, "Left/Right: adjust   Esc: back")
+            }
+            Page::Audio => {
+                let audio = &config.audio;
+                let items = vec![
+                    format!("Volume: {:.0}%", audio.volume * 100.0),
+                    format!("Beep frequency: {:.0}Hz", audio.frequency),
+                    String::from("Back"),
+                ];
+                ("Audio", items, "Left/Right: adjust   Esc: back")
+            }
+            Page::Keypad => {
+                let mut items: Vec<String> = (0..NUM_INPUT_KEYS as u8)
+                    .map(|key_value| {
+                        let keys: Vec<&str> = config
+                            .keymap
+                            .keycodes(key_value)
+                            .into_iter()
+                            .map(|keycode| key_name(keycode).unwrap_or("Unknown"))
+                            .collect();
+                        if self.capturing == Some(Capture::Keypad(key_value)) {
+                            format!("Key {key_value:X}: press a key...")
+                        } else if keys.is_empty() {
+                            format!("Key {key_value:X}: (none)")
+                        } else {
+                            format!("Key {key_value:X}: {}", keys.join(", "))
+                        }
+                    })
+                    .collect();
+                items.push(String::from("Back"));
+                ("Keypad", items, "Enter: rebind   Esc: back")
+            }
+            Page::Hotkeys => {
+                let mut items: Vec<String> = HotkeyAction::ALL
+                    .iter()
+                    .map(|action| {
+                        let bindings: Vec<String> = hotkeys
+                            .bindings(*action)
+                            .iter()
+                            .map(Binding::to_string)
+                            .collect();
+                        if self.capturing == Some(Capture::Hotkey(*action)) {
+                            format!("{}: press a key...", action.label())
+                        } else {
+                            format!("{}: {}", action.label(), bindings.join(", "))
+                        }
+                    })
+                    .collect();
+                items.push(String::from("Back"));
+                ("Hotkeys", items, "Enter: rebind   Esc: back")
+            }
+        };
+
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.85),
+        );
+
+        let mut y = MARGIN + FONT_SIZE;
+        draw_text(title, MARGIN, y, FONT_SIZE, WHITE);
+        y += LINE_HEIGHT;
+
+        for (index, item) in items.iter().enumerate() {
+            y += LINE_HEIGHT;
+            let (prefix, color) = if index == self.selected {
+                ("> ", SELECTED_COLOR)
+            } else {
+                ("  ", WHITE)
+            };
+            draw_text(&format!("{prefix}{item}"), MARGIN, y, FONT_SIZE, color);
+        }
+
+        y += LINE_HEIGHT * 2.0;
+        if let Some(message) = &self.message {
+            draw_text(message, MARGIN, y, FONT_SIZE, MESSAGE_COLOR);
+        }
+        draw_text(hint, MARGIN, screen_height() - MARGIN, FONT_SIZE, GRAY);
+    }
+}
@@ -0,0 +1,276 @@
+use std::{fmt, str::FromStr};
+
+use macroquad::color::Color;
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+use crate::error::{ParseColorError, UnknownPaletteError};
+
+/// An opaque color, written as `#RRGGBB` in the config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Rgb {
+        Rgb { r, g, b }
+    }
+}
+
+/// The colors for each combination of lit planes: nothing, the first plane, XO-CHIP's second
+/// plane, and both. Programs that only use the first plane just need the first two.
+pub const DEFAULT_PALETTE: [Rgb; 4] = [
+    Rgb::new(0x00, 0x00, 0x00),
+    Rgb::new(0xFF, 0xFF, 0xFF),
+    Rgb::new(0xAA, 0xAA, 0xAA),
+    Rgb::new(0x55, 0x55, 0x55),
+];
+
+/// Built-in palettes, cycled through with the next palette hotkey.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PalettePreset {
+    /// White on black, [`DEFAULT_PALETTE`].
+    #[default]
+    Classic,
+    /// The green of a P1 phosphor monochrome monitor.
+    PhosphorGreen,
+    /// The orange of a P3 phosphor monochrome monitor.
+    Amber,
+    /// Dark pixels on a grayish-green LCD, like a calculator's.
+    LcdGray,
+    /// Saturated colors that keep XO-CHIP's planes easy to tell apart.
+    HighContrast,
+    /// Orange and sky blue, which stay distinct with deuteranopia (red-green color blindness
+    /// from missing green cones).
+    Deuteranopia,
+    /// Yellow and blue, which stay distinct with protanopia (red-green color blindness from
+    /// missing red cones).
+    Protanopia,
+    /// Pure black on pure white.
+    BlackOnWhite,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 8] = [
+        PalettePreset::Classic,
+        PalettePreset::PhosphorGreen,
+        PalettePreset::Amber,
+        PalettePreset::LcdGray,
+        PalettePreset::HighContrast,
+        PalettePreset::Deuteranopia,
+        PalettePreset::Protanopia,
+        PalettePreset::BlackOnWhite,
+    ];
+
+    /// The name used in the config file and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            PalettePreset::Classic => "classic",
+            PalettePreset::PhosphorGreen => "phosphor-green",
+            PalettePreset::Amber => "amber",
+            PalettePreset::LcdGray => "lcd-gray",
+            PalettePreset::HighContrast => "high-contrast",
+            PalettePreset::Deuteranopia => "deuteranopia",
+            PalettePreset::Protanopia => "protanopia",
+            PalettePreset::BlackOnWhite => "black-on-white",
+        }
+    }
+
+    pub fn colors(self) -> [Rgb; 4] {
+        match self {
+            PalettePreset::Classic => DEFAULT_PALETTE,
+            PalettePreset::PhosphorGreen => [
+                Rgb::new(0x00, 0x14, 0x00),
+                Rgb::new(0x33, 0xFF, 0x33),
+                Rgb::new(0x1A, 0x99, 0x1A),
+                Rgb::new(0x0D, 0x59, 0x0D),
+            ],
+            PalettePreset::Amber => [
+                Rgb::new(0x1A, 0x0F, 0x00),
+                Rgb::new(0xFF, 0xB0, 0x00),
+                Rgb::new(0xA6, 0x72, 0x00),
+                Rgb::new(0x59, 0x3D, 0x00),
+            ],
+            PalettePreset::LcdGray => [
+                Rgb::new(0xC7, 0xCC, 0xB8),
+                Rgb::new(0x2E, 0x33, 0x2B),
+                Rgb::new(0x8A, 0x91, 0x83),
+                Rgb::new(0x5C, 0x61, 0x56),
+            ],
+            PalettePreset::HighContrast => [
+                Rgb::new(0x00, 0x00, 0x00),
+                Rgb::new(0xFF, 0xFF, 0x00),
+                Rgb::new(0x00, 0xFF, 0xFF),
+                Rgb::new(0xFF, 0xFF, 0xFF),
+            ],
+            // From the Okabe-Ito palette, designed to be told apart with any color vision
+            PalettePreset::Deuteranopia => [
+                Rgb::new(0x00, 0x00, 0x00),
+                Rgb::new(0xE6, 0x9F, 0x00),
+                Rgb::new(0x56, 0xB4, 0xE9),
+                Rgb::new(0xFF, 0xFF, 0xFF),
+            ],
+            PalettePreset::Protanopia => [
+                Rgb::new(0x00, 0x00, 0x00),
+                Rgb::new(0xF0, 0xE4, 0x42),
+                Rgb::new(0x00, 0x72, 0xB2),
+                Rgb::new(0xFF, 0xFF, 0xFF),
+            ],
+            PalettePreset::BlackOnWhite => [
+                Rgb::new(0xFF, 0xFF, 0xFF),
+                Rgb::new(0x00, 0x00, 0x00),
+                Rgb::new(0x80, 0x80, 0x80),
+                Rgb::new(0x40, 0x40, 0x40),
+            ],
+        }
+    }
+
+    /// The preset to switch to from `palette`: the one after it if it's a preset, else the
+    /// first.
+    pub fn after(palette: &[Rgb; 4]) -> PalettePreset {
+        match Self::ALL
+            .iter()
+            .position(|preset| preset.colors() == *palette)
+        {
+            Some(index) => Self::ALL[(index + 1) % Self::ALL.len()],
+            None => Self::ALL[0],
+        }
+    }
+}
+
+impl fmt::Display for PalettePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PalettePreset::Classic => write!(f, "Classic"),
+            PalettePreset::PhosphorGreen => write!(f, "Phosphor green"),
+            PalettePreset::Amber => write!(f, "Amber"),
+            PalettePreset::LcdGray => write!(f, "LCD gray"),
+            PalettePreset::HighContrast => write!(f, "High contrast"),
+            PalettePreset::Deuteranopia => write!(f, "Deuteranopia"),
+            PalettePreset::Protanopia => write!(f, "Protanopia"),
+            PalettePreset::BlackOnWhite => write!(f, "Black on white"),
+        }
+    }
+}
+
+impl FromStr for PalettePreset {
+    type Err = UnknownPaletteError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        PalettePreset::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownPaletteError(name.to_string()))
+    }
+}
+
+/// Reads a palette from the config file, given either as its colors or a preset's name.
+pub(crate) fn deserialize_palette<'de, D>(deserializer: D) -> Result<[Rgb; 4], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Palette {
+        Preset(String),
+        // Parsed afterwards, since a bad color would otherwise only report that neither form
+        // matched
+        Colors([String; 4]),
+    }
+
+    match Palette::deserialize(deserializer)? {
+        Palette::Preset(name) => name
+            .parse()
+            .map(PalettePreset::colors)
+            .map_err(de::Error::custom),
+        Palette::Colors(colors) => {
+            let mut palette = DEFAULT_PALETTE;
+            for (color, text) in palette.iter_mut().zip(colors) {
+                *color = text.parse().map_err(de::Error::custom)?;
+            }
+            Ok(palette)
+        }
+    }
+}
+
+/// Brightness and gamma adjustments for the colors shown, as a lookup table for each channel.
+pub(crate) struct ColorLevels([u8; 256]);
+
+impl ColorLevels {
+    /// Scales channels by `brightness` after raising them to the power of `1 / gamma`, so a gamma
+    /// above 1 brightens dark colors more than light ones.
+    pub(crate) fn new(brightness: f32, gamma: f32) -> ColorLevels {
+        let mut levels = [0; 256];
+        for (value, level) in levels.iter_mut().enumerate() {
+            let adjusted = (value as f32 / 255.0).powf(1.0 / gamma.max(0.01)) * brightness;
+            *level = (adjusted.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        ColorLevels(levels)
+    }
+
+    pub(crate) fn apply(&self, rgb: Rgb) -> Rgb {
+        Rgb::new(
+            self.0[rgb.r as usize],
+            self.0[rgb.g as usize],
+            self.0[rgb.b as usize],
+        )
+    }
+}
+
+/// Reads an optional palette, given like [`deserialize_palette`]'s.
+pub(crate) fn deserialize_optional_palette<'de, D>(
+    deserializer: D,
+) -> Result<Option<[Rgb; 4]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_palette(deserializer).map(Some)
+}
+
+impl From<Rgb> for Color {
+    fn from(rgb: Rgb) -> Self {
+        Color::from_rgba(rgb.r, rgb.g, rgb.b, 255)
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = ParseColorError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || ParseColorError(text.to_string());
+        let hex = text.strip_prefix('#').ok_or_else(error)?;
+        if hex.len() != 6 {
+            return Err(error());
+        }
+
+        let value = u32::from_str_radix(hex, 16).map_err(|_| error())?;
+        Ok(Rgb::new(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ))
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = ParseColorError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(rgb: Rgb) -> Self {
+        rgb.to_string()
+    }
+}
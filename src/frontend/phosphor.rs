@@ -0,0 +1,45 @@
+use super::palette::Rgb;
+
+/// Fades pixels out over a number of frames after they turn off, like a CRT's phosphor glow, so
+/// that the flicker of sprites being erased and redrawn with XOR is less noticeable.
+pub(crate) struct Phosphor {
+    /// For each pixel, the color it was last lit with and how many frames ago that was.
+    pixels: Vec<(Rgb, u32)>,
+}
+
+impl Phosphor {
+    pub(crate) fn new() -> Phosphor {
+        Phosphor { pixels: Vec::new() }
+    }
+
+    /// Forgets the glow, e.g. when the screen's resolution changes to `len` pixels.
+    pub(crate) fn reset(&mut self, len: usize) {
+        self.pixels.clear();
+        self.pixels.resize(len, (Rgb::new(0, 0, 0), u32::MAX));
+    }
+
+    /// The color to show for `pixel` this frame, given its actual `color`. Unlit pixels, drawn
+    /// in `background`, fade from the last color they were lit with over `frames` frames.
+    pub(crate) fn apply(&mut self, pixel: usize, color: Rgb, background: Rgb, frames: u32) -> Rgb {
+        let (lit_color, age) = &mut self.pixels[pixel];
+        if color != background {
+            *lit_color = color;
+            *age = 0;
+            return color;
+        }
+
+        *age = age.saturating_add(1);
+        if *age >= frames {
+            return background;
+        }
+
+        let brightness = 1.0 - *age as f32 / frames as f32;
+        let mix =
+            |lit: u8, unlit: u8| (unlit as f32 + (lit as f32 - unlit as f32) * brightness) as u8;
+        Rgb::new(
+            mix(lit_color.r, background.r),
+            mix(lit_color.g, background.g),
+            mix(lit_color.b, background.b),
+        )
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::InvalidRotationError;
+
+/// How far the screen is turned clockwise when it's shown, for rotated displays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Half,
+    Clockwise270,
+}
+
+impl Rotation {
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 90,
+            Rotation::Half => 180,
+            Rotation::Clockwise270 => 270,
+        }
+    }
+
+    pub fn radians(self) -> f32 {
+        (self.degrees() as f32).to_radians()
+    }
+
+    /// Whether the screen is on its side, so its width runs vertically.
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::Clockwise90 | Rotation::Clockwise270)
+    }
+
+    /// Maps a point on the rotated screen back to the emulated one, both as fractions of the
+    /// width and height.
+    pub fn unrotate(self, (x, y): (f32, f32)) -> (f32, f32) {
+        match self {
+            Rotation::None => (x, y),
+            Rotation::Clockwise90 => (y, 1.0 - x),
+            Rotation::Half => (1.0 - x, 1.0 - y),
+            Rotation::Clockwise270 => (1.0 - y, x),
+        }
+    }
+
+    /// The keypad key that should be pressed for `key`, treating 1-9 as a grid of directions
+    /// around 5 and turning it with the screen. On a screen turned 90 degrees, for instance, the
+    /// program's up points right, so 6 presses 2.
+    pub fn remap_key(self, key: u8) -> u8 {
+        if !(1..=9).contains(&key) {
+            return key;
+        }
+
+        let (x, y) = ((key as i8 - 1) % 3 - 1, (key as i8 - 1) / 3 - 1);
+        let (x, y) = match self {
+            Rotation::None => (x, y),
+            Rotation::Clockwise90 => (y, -x),
+            Rotation::Half => (-x, -y),
+            Rotation::Clockwise270 => (-y, x),
+        };
+        ((y + 1) * 3 + (x + 1) + 1) as u8
+    }
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = InvalidRotationError;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::None),
+            90 => Ok(Rotation::Clockwise90),
+            180 => Ok(Rotation::Half),
+            270 => Ok(Rotation::Clockwise270),
+            _ => Err(InvalidRotationError(degrees)),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        rotation.degrees()
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+
+use macroquad::{texture::FilterMode, texture::Image};
+use serde::{Deserialize, Serialize};
+
+type Pixel = [u8; 4];
+
+/// Upscaling algorithms applied to the emulator screen before it's stretched to the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scaler {
+    /// Plain square pixels.
+    #[default]
+    Nearest,
+    /// The Scale2x (EPX) pixel art algorithm, which rounds off diagonal edges.
+    Scale2x,
+    /// The Scale3x (AdvMAME3x) pixel art algorithm.
+    Scale3x,
+    /// Scale2x applied twice and then filtered, approximating the hqx family's smooth edges.
+    HqxLite,
+    /// Bilinear upscaling with a sharpening pass to keep pixel edges from turning to mush.
+    Smooth,
+}
+
+impl Scaler {
+    pub const ALL: [Scaler; 5] = [
+        Scaler::Nearest,
+        Scaler::Scale2x,
+        Scaler::Scale3x,
+        Scaler::HqxLite,
+        Scaler::Smooth,
+    ];
+
+    /// The scaler after this one, wrapping around.
+    pub fn next(self) -> Scaler {
+        let index = Self::ALL
+            .iter()
+            .position(|scaler| *scaler == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The texture filter the scaled image should be drawn with.
+    pub fn filter(self) -> FilterMode {
+        match self {
+            Scaler::Nearest | Scaler::Scale2x | Scaler::Scale3x => FilterMode::Nearest,
+            Scaler::HqxLite | Scaler::Smooth => FilterMode::Linear,
+        }
+    }
+
+    /// Upscales `image`, returning a copy when the scaler doesn't change the resolution.
+    pub fn apply(self, image: &Image) -> Image {
+        match self {
+            Scaler::Nearest => image.clone(),
+            Scaler::Scale2x => scale2x(image),
+            Scaler::Scale3x => scale3x(image),
+            Scaler::HqxLite => scale2x(&scale2x(image)),
+            Scaler::Smooth => sharpen(&bilinear(image, 4)),
+        }
+    }
+}
+
+impl fmt::Display for Scaler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scaler::Nearest => write!(f, "Nearest"),
+            Scaler::Scale2x => write!(f, "Scale2x"),
+            Scaler::Scale3x => write!(f, "Scale3x"),
+            Scaler::HqxLite => write!(f, "hqx-lite"),
+            Scaler::Smooth => write!(f, "Smooth"),
+        }
+    }
+}
+
+/// Read-only access to an image's pixels with coordinates clamped to the edges.
+struct Source<'a> {
+    pixels: &'a [Pixel],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Source<'a> {
+    fn new(image: &'a Image) -> Source<'a> {
+        Source {
+            pixels: image.get_image_data(),
+            width: image.width(),
+            height: image.height(),
+        }
+    }
+
+    fn at(&self, x: isize, y: isize) -> Pixel {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Builds an image `factor` times the size of `source`, filling each source pixel's block of
+/// output pixels with `block`.
+fn scale_blocks(
+    source: &Source,
+    factor: usize,
+    mut block: impl FnMut(isize, isize) -> Vec<Pixel>,
+) -> Image {
+    let width = source.width * factor;
+    let height = source.height * factor;
+    let mut bytes = vec![0; width * height * 4];
+
+    for y in 0..source.height {
+        for x in 0..source.width {
+            for (index, pixel) in block(x as isize, y as isize).into_iter().enumerate() {
+                let out_x = x * factor + index % factor;
+                let out_y = y * factor + index / factor;
+                let offset = (out_y * width + out_x) * 4;
+                bytes[offset..offset + 4].copy_from_slice(&pixel);
+            }
+        }
+    }
+
+    Image {
+        bytes,
+        width: width as u16,
+        height: height as u16,
+    }
+}
+
+fn scale2x(image: &Image) -> Image {
+    let source = Source::new(image);
+    scale_blocks(&source, 2, |x, y| {
+        let p = source.at(x, y);
+        let a = source.at(x, y - 1);
+        let b = source.at(x + 1, y);
+        let c = source.at(x - 1, y);
+        let d = source.at(x, y + 1);
+
+        vec![
+            if c == a && c != d && a != b { a } else { p },
+            if a == b && a != c && b != d { b } else { p },
+            if d == c && d != b && c != a { c } else { p },
+            if b == d && b != a && d != c { d } else { p },
+        ]
+    })
+}
+
+fn scale3x(image: &Image) -> Image {
+    let source = Source::new(image);
+    scale_blocks(&source, 3, |x, y| {
+        let a = source.at(x - 1, y - 1);
+        let b = source.at(x, y - 1);
+        let c = source.at(x + 1, y - 1);
+        let d = source.at(x - 1, y);
+        let e = source.at(x, y);
+        let f = source.at(x + 1, y);
+        let g = source.at(x - 1, y + 1);
+        let h = source.at(x, y + 1);
+        let i = source.at(x + 1, y + 1);
+
+        if b == h || d == f {
+            return vec![e; 9];
+        }
+
+        vec![
+            if d == b { d } else { e },
+            if (d == b && e != c) || (b == f && e != a) {
+                b
+            } else {
+                e
+            },
+            if b == f { f } else { e },
+            if (d == b && e != g) || (d == h && e != a) {
+                d
+            } else {
+                e
+            },
+            e,
+            if (b == f && e != i) || (h == f && e != c) {
+                f
+            } else {
+                e
+            },
+            if d == h { d } else { e },
+            if (d == h && e != i) || (h == f && e != g) {
+                h
+            } else {
+                e
+            },
+            if h == f { f } else { e },
+        ]
+    })
+}
+
+fn bilinear(image: &Image, factor: usize) -> Image {
+    let source = Source::new(image);
+    let width = source.width * factor;
+    let height = source.height * factor;
+    let mut bytes = Vec::with_capacity(width * height * 4);
+
+    for out_y in 0..height {
+        // Sample at the center of each output pixel
+        let y = (out_y as f32 + 0.5) / factor as f32 - 0.5;
+        let (y0, fy) = (y.floor(), y - y.floor());
+        for out_x in 0..width {
+            let x = (out_x as f32 + 0.5) / factor as f32 - 0.5;
+            let (x0, fx) = (x.floor(), x - x.floor());
+
+            let top_left = source.at(x0 as isize, y0 as isize);
+            let top_right = source.at(x0 as isize + 1, y0 as isize);
+            let bottom_left = source.at(x0 as isize, y0 as isize + 1);
+            let bottom_right = source.at(x0 as isize + 1, y0 as isize + 1);
+
+            for channel in 0..4 {
+                let top = lerp(top_left[channel], top_right[channel], fx);
+                let bottom = lerp(bottom_left[channel], bottom_right[channel], fx);
+                bytes.push((top + (bottom - top) * fy).round() as u8);
+            }
+        }
+    }
+
+    Image {
+        bytes,
+        width: width as u16,
+        height: height as u16,
+    }
+}
+
+fn lerp(from: u8, to: u8, amount: f32) -> f32 {
+    from as f32 + (to as f32 - from as f32) * amount
+}
+
+/// Applies an unsharp mask: each pixel is pushed away from the average of its neighbors.
+fn sharpen(image: &Image) -> Image {
+    const AMOUNT: f32 = 0.75;
+
+    let source = Source::new(image);
+    let mut bytes = Vec::with_capacity(image.bytes.len());
+    for y in 0..source.height as isize {
+        for x in 0..source.width as isize {
+            let center = source.at(x, y);
+            let neighbors = [
+                source.at(x - 1, y),
+                source.at(x + 1, y),
+                source.at(x, y - 1),
+                source.at(x, y + 1),
+            ];
+
+            for channel in 0..4 {
+                let blurred = neighbors
+                    .iter()
+                    .map(|pixel| pixel[channel] as f32)
+                    .sum::<f32>()
+                    / neighbors.len() as f32;
+                let value = center[channel] as f32;
+                bytes.push((value + (value - blurred) * AMOUNT).clamp(0.0, 255.0) as u8);
+            }
+        }
+    }
+
+    Image {
+        bytes,
+        width: image.width,
+        height: image.height,
+    }
+}
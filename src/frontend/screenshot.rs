@@ -0,0 +1,86 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use macroquad::texture::Image;
+
+/// Writes `image` to `path` as a PNG, with each pixel enlarged to a `scale` by `scale` block.
+pub fn save_png(image: &Image, scale: u32, path: &Path) -> Result<(), png::EncodingError> {
+    let scale = scale.max(1) as usize;
+    let (width, height) = (image.width() * scale, image.height() * scale);
+    let bytes = resize(image, width, height);
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&bytes)?;
+    writer.finish()
+}
+
+/// Resizes `image` to `width` by `height` without any smoothing, returning its RGBA bytes.
+pub fn resize(image: &Image, width: usize, height: usize) -> Vec<u8> {
+    let (source_width, source_height) = (image.width(), image.height());
+    let mut bytes = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let row = y * source_height / height * source_width;
+        for x in 0..width {
+            let pixel = (row + x * source_width / width) * 4;
+            bytes.extend_from_slice(&image.bytes[pixel..pixel + 4]);
+        }
+    }
+    bytes
+}
+
+/// A path next to `base` named after it and the current time, like
+/// `<stem>-20240131-235959.<extension>`, with a number added if that's already taken.
+pub fn timestamped_path(base: &Path, extension: &str) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("hachi"));
+    let name = format!("{stem}-{}", timestamp());
+
+    std::iter::once(base.with_file_name(format!("{name}.{extension}")))
+        .chain((2..).map(|number| base.with_file_name(format!("{name}-{number}.{extension}"))))
+        .find(|path| !path.exists())
+        .expect("ran out of file names")
+}
+
+/// The current UTC time as `YYYYMMDD-HHMMSS`.
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+
+    // Converts days since 1970-01-01 to a date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
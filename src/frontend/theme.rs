@@ -0,0 +1,85 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use super::{
+    bezel::BezelConfig,
+    crt::CrtConfig,
+    palette::{Rgb, deserialize_optional_palette},
+    scaler::Scaler,
+};
+use crate::{
+    config::{DisplayConfig, config_dir},
+    error::ConfigError,
+};
+
+const THEMES_DIR_NAME: &str = "themes";
+const THEME_EXTENSION: &str = "toml";
+
+/// A bundle of display settings loaded from a `.toml` file, such as a palette with matching CRT
+/// effects and bezel. Settings the theme leaves out are kept as they are.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    #[serde(deserialize_with = "deserialize_optional_palette")]
+    pub palette: Option<[Rgb; 4]>,
+    pub scaler: Option<Scaler>,
+    pub crt: Option<CrtConfig>,
+    /// The bezel, whose image is found relative to the theme file.
+    pub bezel: Option<BezelConfig>,
+    pub pixel_grid: Option<bool>,
+}
+
+impl Theme {
+    /// Loads a theme file, resolving its bezel image's path.
+    pub fn load(path: &Path) -> Result<Theme, ConfigError> {
+        let mut theme: Theme = toml::from_str(&fs::read_to_string(path)?)?;
+        if let Some(image) = theme.bezel.as_mut().and_then(|bezel| bezel.image.as_mut())
+            && let Some(dir) = path.parent()
+        {
+            *image = dir.join(&*image);
+        }
+        Ok(theme)
+    }
+
+    /// Replaces the display settings the theme has.
+    pub fn apply(&self, display: &mut DisplayConfig) {
+        if let Some(palette) = self.palette {
+            display.palette = palette;
+        }
+        if let Some(scaler) = self.scaler {
+            display.scaler = scaler;
+        }
+        if let Some(crt) = &self.crt {
+            display.crt = crt.clone();
+        }
+        if let Some(bezel) = &self.bezel {
+            display.bezel = bezel.clone();
+        }
+        if let Some(pixel_grid) = self.pixel_grid {
+            display.pixel_grid = pixel_grid;
+        }
+    }
+
+    /// The folder in the config directory themes are cycled through from.
+    pub fn dir() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(THEMES_DIR_NAME))
+    }
+
+    /// The theme files in [`Theme::dir`], in path order.
+    pub fn find_all() -> Vec<PathBuf> {
+        let Some(entries) = Theme::dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == THEME_EXTENSION))
+            .collect();
+        paths.sort();
+        paths
+    }
+}
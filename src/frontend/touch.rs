@@ -0,0 +1,114 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+use crate::NUM_INPUT_KEYS;
+
+/// Touching parts of the window to press keypad keys, for phones and tablets, or clicking them
+/// with the mouse.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TouchConfig {
+    /// Whether touches press keys. The keypad panel works either way.
+    pub enabled: bool,
+    /// Whether holding the left mouse button on a region presses it too, so that simple games
+    /// can be played with the mouse alone.
+    pub mouse: bool,
+    /// The parts of the window that press each key. Touches press every region they're in.
+    #[serde(deserialize_with = "deserialize_regions")]
+    pub regions: Vec<TouchRegion>,
+}
+
+impl Default for TouchConfig {
+    fn default() -> Self {
+        TouchConfig {
+            enabled: true,
+            mouse: false,
+            regions: default_regions(),
+        }
+    }
+}
+
+/// A part of the window that presses a keypad key while touched.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TouchRegion {
+    pub key: u8,
+    /// The left, top, width and height in fractions of the window's size.
+    pub area: [f32; 4],
+}
+
+impl TouchRegion {
+    fn contains(&self, window: Rect, point: Vec2) -> bool {
+        let [left, top, width, height] = self.area;
+        Rect::new(
+            window.x + left * window.w,
+            window.y + top * window.h,
+            width * window.w,
+            height * window.h,
+        )
+        .contains(point)
+    }
+}
+
+/// Splits the window into a 3x3 grid laid out like keys 1 to 9, so that the edges press 2, 4, 6
+/// and 8, which most games steer with, and the middle presses 5.
+fn default_regions() -> Vec<TouchRegion> {
+    let third = 1.0 / 3.0;
+    (0..9)
+        .map(|index| TouchRegion {
+            key: index + 1,
+            area: [
+                (index % 3) as f32 * third,
+                (index / 3) as f32 * third,
+                third,
+                third,
+            ],
+        })
+        .collect()
+}
+
+/// Reads the touch regions from the config file, checking that they press keypad keys.
+fn deserialize_regions<'de, D>(deserializer: D) -> Result<Vec<TouchRegion>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let regions = Vec::<TouchRegion>::deserialize(deserializer)?;
+    if let Some(region) = regions
+        .iter()
+        .find(|region| region.key as usize >= NUM_INPUT_KEYS)
+    {
+        return Err(de::Error::custom(format!(
+            "a touch region presses {}, which isn't a keypad key from 0 to 0xF",
+            region.key
+        )));
+    }
+    Ok(regions)
+}
+
+/// Which keys are held by touches, or the mouse if enabled, within `window`, ignoring those on
+/// the keypad panel at `panel`, which handles its own.
+pub(crate) fn held(
+    config: &TouchConfig,
+    window: Rect,
+    panel: Option<Rect>,
+) -> [bool; NUM_INPUT_KEYS] {
+    let mut held = [false; NUM_INPUT_KEYS];
+    let touches = touches()
+        .into_iter()
+        .filter(|_| config.enabled)
+        .filter(|touch| !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled))
+        .map(|touch| touch.position);
+    let mouse = (config.mouse && is_mouse_button_down(MouseButton::Left))
+        .then(|| Vec2::from(mouse_position()));
+
+    let points = touches
+        .chain(mouse)
+        .filter(|position| !panel.is_some_and(|panel| panel.contains(*position)));
+    for position in points {
+        for region in &config.regions {
+            if region.contains(window, position) {
+                held[region.key as usize] = true;
+            }
+        }
+    }
+    held
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+use crate::NUM_INPUT_KEYS;
+
+/// Keypad keys that repeatedly press and release themselves while held, for games that need a
+/// key hammered, such as to fire.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurboConfig {
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub keys: Vec<u8>,
+    /// How many times a second turbo keys are pressed.
+    pub rate: f32,
+}
+
+impl Default for TurboConfig {
+    fn default() -> Self {
+        TurboConfig {
+            keys: Vec::new(),
+            rate: 10.0,
+        }
+    }
+}
+
+/// Reads the turbo keys from the config file, checking that they're keypad keys.
+fn deserialize_keys<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let keys = Vec::<u8>::deserialize(deserializer)?;
+    if let Some(key) = keys.iter().find(|key| **key as usize >= NUM_INPUT_KEYS) {
+        return Err(de::Error::custom(format!(
+            "turbo key {key} isn't a keypad key from 0 to 0xF"
+        )));
+    }
+    Ok(keys)
+}
+
+/// How long each turbo key has been held, which decides whether it's pressed at the moment.
+#[derive(Default)]
+pub(crate) struct Turbo {
+    held_time: [f32; NUM_INPUT_KEYS],
+}
+
+impl Turbo {
+    /// Turns the turbo keys held in `keys` on and off at the configured rate, starting pressed.
+    /// `elapsed` is the emulated time since the last call.
+    pub(crate) fn apply(
+        &mut self,
+        keys: &mut [bool; NUM_INPUT_KEYS],
+        config: &TurboConfig,
+        elapsed: f32,
+    ) {
+        for (key_value, (held, held_time)) in keys.iter_mut().zip(&mut self.held_time).enumerate() {
+            if !*held || !config.keys.contains(&(key_value as u8)) {
+                *held_time = 0.0;
+                continue;
+            }
+
+            *held = (*held_time * config.rate).fract() < 0.5;
+            *held_time += elapsed;
+        }
+    }
+}
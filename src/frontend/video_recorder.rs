@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+use macroquad::texture::Image;
+
+use super::screenshot;
+
+/// The video's frame rate, matching the CHIP-8's 60Hz display.
+const FRAME_RATE: f32 = 60.0;
+
+/// Records the screen to a video by piping raw frames to an ffmpeg process, which picks the
+/// format from the file's extension. Sound is recorded separately, by a
+/// [`WavRecorder`](super::wav_recorder::WavRecorder), and added with [`add_sound`] afterwards.
+pub(crate) struct VideoRecorder {
+    path: PathBuf,
+    ffmpeg: Child,
+    stdin: ChildStdin,
+    width: usize,
+    height: usize,
+    /// Seconds since recording started.
+    elapsed: f32,
+    next_frame: f32,
+}
+
+impl VideoRecorder {
+    /// Starts `ffmpeg` recording to `path`. The video is the size of `image` times `scale`, and
+    /// later frames at other resolutions are stretched to fit.
+    pub(crate) fn start(
+        ffmpeg: &str,
+        path: &Path,
+        image: &Image,
+        scale: u32,
+    ) -> io::Result<VideoRecorder> {
+        let scale = scale.max(1) as usize;
+        // Most codecs need even dimensions
+        let width = (image.width() * scale).next_multiple_of(2);
+        let height = (image.height() * scale).next_multiple_of(2);
+
+        let mut ffmpeg = Command::new(ffmpeg)
+            .args(["-loglevel", "error", "-y"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .arg("-s")
+            .arg(format!("{width}x{height}"))
+            .arg("-r")
+            .arg(FRAME_RATE.to_string())
+            .args(["-i", "-", "-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let stdin = ffmpeg.stdin.take().expect("stdin is piped");
+
+        Ok(VideoRecorder {
+            path: path.to_path_buf(),
+            ffmpeg,
+            stdin,
+            width,
+            height,
+            elapsed: 0.0,
+            next_frame: 0.0,
+        })
+    }
+
+    /// Where the video is saved.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sends `image` for each video frame due in the `frame_time` seconds since the last call,
+    /// so the video keeps time even when the window refreshes slower or faster than it.
+    pub(crate) fn capture(&mut self, image: &Image, frame_time: f32) -> io::Result<()> {
+        self.elapsed += frame_time;
+        if self.elapsed < self.next_frame {
+            return Ok(());
+        }
+
+        let rgb: Vec<u8> = screenshot::resize(image, self.width, self.height)
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        while self.elapsed >= self.next_frame {
+            self.stdin.write_all(&rgb)?;
+            self.next_frame += 1.0 / FRAME_RATE;
+        }
+        Ok(())
+    }
+
+    /// Closes the pipe and waits for ffmpeg to finish writing the video.
+    pub(crate) fn finish(self) -> io::Result<()> {
+        let VideoRecorder {
+            mut ffmpeg, stdin, ..
+        } = self;
+        drop(stdin);
+
+        let status = ffmpeg.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("ffmpeg exited with {status}")));
+        }
+        Ok(())
+    }
+}
+
+/// Adds the sound in the WAV file `sound` to the finished video at `video` with `ffmpeg`,
+/// deleting the WAV file once it's in. The video is left as it was if that fails.
+pub(crate) fn add_sound(ffmpeg: &str, video: &Path, sound: &Path) -> io::Result<()> {
+    // Keeps the extension, which ffmpeg reads the format from
+    let silent = video.with_file_name(format!(
+        "silent-{}",
+        video.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    fs::rename(video, &silent)?;
+
+    let status = Command::new(ffmpeg)
+        .args(["-loglevel", "error", "-y", "-i"])
+        .arg(&silent)
+        .arg("-i")
+        .arg(sound)
+        .args(["-c:v", "copy"])
+        .arg(video)
+        .status();
+    match status {
+        Ok(status) if status.success() => {
+            fs::remove_file(&silent)?;
+            fs::remove_file(sound)
+        }
+        Ok(status) => {
+            fs::rename(&silent, video)?;
+            Err(io::Error::other(format!("ffmpeg exited with {status}")))
+        }
+        Err(error) => {
+            fs::rename(&silent, video)?;
+            Err(error)
+        }
+    }
+}
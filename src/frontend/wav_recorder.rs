@@ -0,0 +1,65 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use super::audio_backend::{Mixer, wav_header};
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Records the sound played during a GIF or video recording to a WAV file, mixed from a copy of
+/// what's sent to the audio backend.
+pub(crate) struct WavRecorder {
+    path: PathBuf,
+    mixer: Mixer,
+    writer: BufWriter<File>,
+    /// Seconds since recording started.
+    elapsed: f64,
+    samples_written: u64,
+    buffer: Vec<f32>,
+}
+
+impl WavRecorder {
+    /// Starts recording `mixer`'s sound to `path`.
+    pub(crate) fn start(path: &Path, mixer: Mixer) -> io::Result<WavRecorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        // Rewritten with the length once recording finishes
+        writer.write_all(&wav_header(0, SAMPLE_RATE))?;
+
+        Ok(WavRecorder {
+            path: path.to_path_buf(),
+            mixer,
+            writer,
+            elapsed: 0.0,
+            samples_written: 0,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Writes the sound of the `frame_time` seconds since the last call.
+    pub(crate) fn capture(&mut self, frame_time: f32) -> io::Result<()> {
+        self.elapsed += frame_time as f64;
+        let due = (self.elapsed * SAMPLE_RATE as f64) as u64;
+        let count = due.saturating_sub(self.samples_written) as usize;
+
+        self.buffer.resize(count, 0.0);
+        self.mixer.fill(&mut self.buffer, SAMPLE_RATE);
+        for sample in &self.buffer {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.samples_written = due;
+        Ok(())
+    }
+
+    /// Fills in the WAV header's length and closes the file, returning where it was saved.
+    pub(crate) fn finish(mut self) -> io::Result<PathBuf> {
+        let data_len = u32::try_from(self.samples_written * 2)
+            .map_err(|_| io::Error::other("recording is too long for a WAV file"))?;
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&wav_header(data_len, SAMPLE_RATE))?;
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha1_smol::Sha1;
+
+use crate::{BIG_FONT, Emulator, STANDARD_FONT, error::GoldenError, variant::Chip8Variant};
+
+/// The extension golden run files are recognized by when running a folder of them.
+pub const GOLDEN_EXTENSION: &str = "golden.toml";
+
+/// A recorded headless run of a ROM which should always end in the same machine state. Golden
+/// runs are stored as small TOML files, so regression cases are easy to share and accumulate.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldenRun {
+    /// SHA-1 of the ROM, used to find it and to make sure the right ROM is being run.
+    pub rom_sha1: String,
+    /// Where the ROM is, relative to the golden run file. If missing, the ROM is looked up by
+    /// hash among the files next to the golden run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rom: Option<PathBuf>,
+    #[serde(default)]
+    pub variant: Chip8Variant,
+    /// Seed for the CXNN random number generator.
+    #[serde(default)]
+    pub seed: u64,
+    /// How many headless steps to run for, each being 1/550th of a second of emulated time.
+    /// Steps spent waiting for a key or the display count too.
+    pub cycles: u64,
+    /// Changes to the held keys, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<InputEvent>,
+    /// SHA-1 of the machine state at the end of the run, see [`state_hash`].
+    pub state_sha1: String,
+}
+
+/// From step `cycle` onwards, exactly `keys` are held.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputEvent {
+    pub cycle: u64,
+    pub keys: Vec<u8>,
+}
+
+impl GoldenRun {
+    /// Runs `rom` and records the state it ends in.
+    pub fn record(
+        rom: &[u8],
+        variant: Chip8Variant,
+        seed: u64,
+        cycles: u64,
+        inputs: Vec<InputEvent>,
+    ) -> Result<GoldenRun, GoldenError> {
+        let mut run = GoldenRun {
+            rom_sha1: sha1(rom),
+            rom: None,
+            variant,
+            seed,
+            cycles,
+            inputs,
+            state_sha1: String::new(),
+        };
+        run.state_sha1 = run.execute(rom)?;
+        Ok(run)
+    }
+
+    pub fn load(path: &Path) -> Result<GoldenRun, GoldenError> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GoldenError> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Finds the ROM for a golden run stored at `path`, checking its hash.
+    pub fn find_rom(&self, path: &Path) -> Result<Vec<u8>, GoldenError> {
+        let folder = path.parent().unwrap_or(Path::new("."));
+        if let Some(rom_path) = &self.rom {
+            let rom = fs::read(folder.join(rom_path))?;
+            let actual = sha1(&rom);
+            if actual != self.rom_sha1 {
+                return Err(GoldenError::RomMismatch {
+                    expected: self.rom_sha1.clone(),
+                    actual,
+                });
+            }
+            return Ok(rom);
+        }
+
+        for entry in fs::read_dir(folder)? {
+            let entry_path = entry?.path();
+            if !entry_path.is_file() || is_golden_file(&entry_path) {
+                continue;
+            }
+
+            let rom = fs::read(&entry_path)?;
+            if sha1(&rom) == self.rom_sha1 {
+                return Ok(rom);
+            }
+        }
+
+        Err(GoldenError::RomNotFound {
+            sha1: self.rom_sha1.clone(),
+        })
+    }
+
+    /// Runs `rom` and checks it ends in the recorded state.
+    pub fn check(&self, rom: &[u8]) -> Result<(), GoldenError> {
+        let actual = sha1(rom);
+        if actual != self.rom_sha1 {
+            return Err(GoldenError::RomMismatch {
+                expected: self.rom_sha1.clone(),
+                actual,
+            });
+        }
+
+        let actual = self.execute(rom)?;
+        if actual != self.state_sha1 {
+            return Err(GoldenError::StateMismatch {
+                expected: self.state_sha1.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Runs `rom` for the recorded number of steps, returning the hash of the final state. The
+    /// run ends early if the emulator halts.
+    pub fn execute(&self, rom: &[u8]) -> Result<String, GoldenError> {
+        Ok(state_hash(&self.run(rom)?))
+    }
+
+    /// Runs `rom` for the recorded number of steps like [`GoldenRun::execute`], returning the
+    /// emulator in its final state.
+    pub fn run(&self, rom: &[u8]) -> Result<Emulator, GoldenError> {
+        let mut emulator = Emulator::new().with_variant(self.variant);
+        emulator.seed_rng(self.seed);
+        emulator.load_font(&STANDARD_FONT);
+        emulator.load_big_font(&BIG_FONT);
+        emulator.load_program(rom)?;
+
+        let mut inputs = self.inputs.iter().peekable();
+        for cycle in 0..self.cycles {
+            while let Some(input) = inputs.next_if(|input| input.cycle <= cycle) {
+                emulator.key_states = [false; crate::NUM_INPUT_KEYS];
+                for key in &input.keys {
+                    if let Some(state) = emulator.key_states.get_mut(*key as usize) {
+                        *state = true;
+                    }
+                }
+            }
+
+            if emulator.step_headless().is_err() {
+                break;
+            }
+        }
+
+        Ok(emulator)
+    }
+}
+
+/// SHA-1 of the guest-visible machine state: memory, registers, stack, timers and screen.
+pub fn state_hash(emulator: &Emulator) -> String {
+    let snapshot = emulator.snapshot();
+    let mut hasher = Sha1::new();
+    hasher.update(&snapshot.memory);
+    hasher.update(&snapshot.registers);
+    hasher.update(&(snapshot.index_register as u16).to_le_bytes());
+    hasher.update(&(snapshot.program_counter as u16).to_le_bytes());
+    for value in &snapshot.stack {
+        hasher.update(&value.to_le_bytes());
+    }
+    hasher.update(&[
+        snapshot.delay_timer,
+        snapshot.sound_timer,
+        snapshot.display_mode as u8,
+    ]);
+    for chunk in snapshot
+        .screen
+        .chunks(8)
+        .chain(snapshot.second_plane.chunks(8))
+    {
+        let byte = chunk
+            .iter()
+            .enumerate()
+            .fold(0u8, |byte, (bit, on)| byte | ((*on as u8) << bit));
+        hasher.update(&[byte]);
+    }
+    if let Some(megachip) = &snapshot.megachip {
+        hasher.update(megachip);
+    }
+    hasher.digest().to_string()
+}
+
+/// The outcome of one file in a corpus run.
+#[derive(Debug)]
+pub struct CorpusEntry {
+    pub path: PathBuf,
+    pub result: Result<(), GoldenError>,
+}
+
+/// Runs every golden run file in `folder`, returning each file's result in path order.
+pub fn run_corpus(folder: &Path) -> Result<Vec<CorpusEntry>, GoldenError> {
+    let mut paths = fs::read_dir(folder)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| is_golden_file(path));
+    paths.sort();
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let result = GoldenRun::load(&path)
+                .and_then(|run| run.find_rom(&path).and_then(|rom| run.check(&rom)));
+            CorpusEntry { path, result }
+        })
+        .collect())
+}
+
+fn is_golden_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(&format!(".{GOLDEN_EXTENSION}")))
+}
+
+fn sha1(data: &[u8]) -> String {
+    Sha1::from(data).digest().to_string()
+}
@@ -1,17 +1,103 @@
-use ::rand::random_range;
-use bit_set::BitSet;
-use macroquad::{prelude::*, texture::Image};
+use std::{collections::HashSet, ops::Range};
+
+use macroquad::prelude::*;
+
+mod config;
+mod disassembler;
+mod display;
+mod doctor;
+mod error;
+mod frontend;
+mod golden;
+mod megachip;
+mod mmio;
+mod movie;
+mod policy;
+mod profile;
+mod quirks;
+mod rng;
+mod rom_db;
+mod rules;
+mod screen_dump;
+mod snapshot;
+mod splash;
+#[cfg(test)]
+mod tests;
+mod timing;
+mod trace;
+mod usage;
+mod variant;
+mod watch;
+
+pub use config::{
+    AudioConfig, Config, DisplayConfig, EmulationConfig, GamepadConfig, InputProfile,
+    RecordingConfig, WindowConfig, config_dir,
+};
+pub use disassembler::disassemble;
+pub use display::{
+    DisplayMode, ETI_660_HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, PLANES, TWO_PAGE_HEIGHT,
+};
+pub use doctor::{Platform, RomReport, diagnose};
+pub use error::{
+    ConfigError, GoldenError, InvalidRotationError, LoadError, MovieError, ParseColorError,
+    ParseConditionError, RuntimeError, SnapshotError, UnknownLayoutError, UnknownPaletteError,
+    UnknownPolicyError, UnknownQuirkError, UnknownRngError, UnknownTimingError,
+    UnknownVariantError, WavError,
+};
+#[cfg(feature = "sdl2")]
+pub use frontend::Sdl2Backend;
+pub use frontend::{
+    AudioBackend, AudioChannel, AudioOutput, BezelConfig, Binding, Clip, Conflict, CrtConfig,
+    DEFAULT_PALETTE, Frontend, GamepadButton, HotkeyAction, Hotkeys, KeyboardLayout, Keymap,
+    MacroquadBackend, Mixer, PalettePreset, Rgb, Rotation, Scaler, SilentBackend, Theme,
+    TouchConfig, TouchRegion, TurboConfig, Waveform,
+};
+pub use golden::{CorpusEntry, GOLDEN_EXTENSION, GoldenRun, InputEvent, run_corpus, state_hash};
+pub use megachip::{DigitizedSound, MEGACHIP_HEIGHT, MEGACHIP_WIDTH};
+pub use mmio::{MMIO_BYTES, Pointer};
+pub use movie::{MOVIE_EXTENSION, Movie, MovieInput, MoviePlayback};
+pub use policy::{MachineCodePolicy, UnknownOpcodePolicy};
+pub use profile::Profile;
+pub use quirks::Quirks;
+pub use rng::{RngAlgorithm, RngState};
+pub use rom_db::{KnownRom, RomDatabase};
+pub use rules::{Comparison, Condition, Operand, Rule, RuleAction, RuleEngine};
+pub use screen_dump::{SCREEN_TEXT_CHARS, screen_text, write_pbm};
+pub use snapshot::{KeyWait, Snapshot};
+pub use splash::SPLASH_ROM;
+pub use timing::Timing;
+pub use trace::{ExecutedInstruction, Trace};
+pub use usage::UsageReport;
+pub use variant::Chip8Variant;
+pub use watch::{AccessKind, MemoryAccess, WatchId};
+
+use display::Display;
+use megachip::MegaChip;
+use mmio::Mmio;
+use rng::GuestRng;
+use watch::Watches;
 
 const SCREEN_WIDTH: usize = 64;
 const SCREEN_HEIGHT: usize = 32;
 const MEMORY_BYTES: usize = 4096;
+const XO_CHIP_MEMORY_BYTES: usize = 0x10000;
 const INITIAL_STACK_SIZE: usize = 64;
-const TARGET_OPS_PER_SECOND: u16 = 550;
+/// Instructions executed per second with [`Timing::Fixed`].
+pub const TARGET_OPS_PER_SECOND: u16 = 550;
 const NUM_INPUT_KEYS: usize = 16;
+/// Size of the XO-CHIP audio pattern loaded by F002, holding 128 one-bit samples.
+pub const AUDIO_PATTERN_BYTES: usize = 16;
+/// The XO-CHIP pitch register's initial value, which plays the pattern at 4000 samples a second.
+const DEFAULT_PITCH: u8 = 64;
+
+/// Number of RPL user flags saved by FX75. SUPER-CHIP only had 8; XO-CHIP allows all 16.
+pub const RPL_FLAGS: usize = 16;
 
 const TIMER_HZ: f32 = 60.0;
 
 const ROM_LOAD_INDEX: usize = 0x0200; // Memory location where roms are loaded from
+const TWO_PAGE_START: usize = 0x02C0; // Where two-page hires CHIP-8 programs really start
+const ETI_660_LOAD_INDEX: usize = 0x0600; // Where the ETI-660 loads roms from
 
 type FontData = [u8; 80];
 const FONT_LOAD_INDEX: usize = 0x0000;
@@ -34,49 +120,269 @@ pub const STANDARD_FONT: FontData = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+type BigFontData = [u8; 160];
+const BIG_FONT_LOAD_INDEX: usize = FONT_LOAD_INDEX + STANDARD_FONT.len();
+/// SUPER-CHIP's 8x10 digits, used with FX30 for large score displays. SUPER-CHIP itself only
+/// had 0-9; the A-F glyphs follow XO-CHIP.
+pub const BIG_FONT: BigFontData = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// Describes why emulation stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HaltReason {
+    /// The user closed the window.
+    UserQuit,
+    /// The program executed 00FD.
+    Exit,
+    /// The program hit an unrecoverable error.
+    Error(RuntimeError),
+    /// Execution reached a breakpoint at the given address. The instruction there has not been
+    /// executed yet.
+    Breakpoint(usize),
+    /// A headless run used up its instruction budget.
+    Timeout,
+    /// The program executed an opcode the interpreter doesn't recognize, under
+    /// [`UnknownOpcodePolicy::Pause`]. Resuming continues with the next instruction.
+    UnknownOpcode { address: usize, instruction: u16 },
+}
+
+impl HaltReason {
+    /// A process exit code suitable for reporting how emulation ended.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HaltReason::UserQuit | HaltReason::Exit => 0,
+            HaltReason::Error(_) => 1,
+            HaltReason::Breakpoint(_) | HaltReason::UnknownOpcode { .. } => 2,
+            HaltReason::Timeout => 3,
+        }
+    }
+}
+
+impl From<RuntimeError> for HaltReason {
+    fn from(error: RuntimeError) -> Self {
+        HaltReason::Error(error)
+    }
+}
+
+/// A host-provided handler for an opcode the interpreter doesn't implement itself. It receives
+/// the emulator and the opcode, and returns whether it handled the opcode; if not, the
+/// interpreter falls back to its default behavior.
+pub type OpcodeHandler = Box<dyn FnMut(&mut Emulator, u16) -> bool>;
+
+/// A host-provided callback for when the sound starts, stops or is set to a new length. It
+/// receives whether the sound timer is running and the timer ticks left.
+pub type SoundCallback = Box<dyn FnMut(bool, u8)>;
+
 pub struct Emulator {
-    memory: [u8; MEMORY_BYTES],
+    memory: Vec<u8>,
     registers: [u8; 16],
     index_register: usize,
     program_counter: usize,
     stack: Vec<u16>,
+    stack_limit: Option<usize>,
+    max_stack_depth: usize,
+    program_range: Range<usize>,
+    canaries: bool,
+    rpl_flags: [u8; RPL_FLAGS],
 
     delay_timer: u8,
     sound_timer: u8,
-
-    screen: BitSet,
+    /// Whether the sound timer was running at a timer tick since the host last checked.
+    sound_ticked: bool,
+    audio_pattern: Option<[u8; AUDIO_PATTERN_BYTES]>,
+    pitch: u8,
+    timer_time: f32,
+    update_time: f32,
+    /// How long the last step took, see [`Timing`].
+    step_time: f32,
+    cycles: u64,
+
+    breakpoints: HashSet<usize>,
+    resuming_from_breakpoint: bool,
+    instruction_address: usize,
+    instruction: u16,
+    watches: Watches,
+    mmio: Option<Mmio>,
+
+    display: Display,
+    /// Mega-CHIP's screen and drawing state while its mode is on.
+    megachip: Option<MegaChip>,
     key_states: [bool; NUM_INPUT_KEYS],
     awaiting_keypress: bool,
     awaiting_keypress_register: usize,
     awaiting_keyrelease: bool,
     awaiting_keyelease_key_value: u8,
+    awaiting_vblank: bool,
+
+    profile: Profile,
+    rng: GuestRng,
+
+    rom_report: Option<RomReport>,
+
+    machine_code_policy: MachineCodePolicy,
+    machine_code_handler: Option<OpcodeHandler>,
+    unknown_opcode_policy: UnknownOpcodePolicy,
+    unknown_opcode_handler: Option<OpcodeHandler>,
+    sound_callback: Option<SoundCallback>,
 }
 
 impl Emulator {
     pub fn new() -> Emulator {
         Emulator {
-            memory: [0; MEMORY_BYTES],
+            memory: vec![0; MEMORY_BYTES],
             registers: [0; 16],
             index_register: 0,
-            program_counter: 0,
+            program_counter: ROM_LOAD_INDEX,
             stack: Vec::with_capacity(INITIAL_STACK_SIZE),
+            stack_limit: None,
+            max_stack_depth: 0,
+            program_range: ROM_LOAD_INDEX..ROM_LOAD_INDEX,
+            canaries: false,
+            rpl_flags: [0; RPL_FLAGS],
 
             delay_timer: 0,
             sound_timer: 0,
-
-            screen: BitSet::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT),
+            sound_ticked: false,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            timer_time: 0.0,
+            update_time: 0.0,
+            step_time: 1.0 / TARGET_OPS_PER_SECOND as f32,
+            cycles: 0,
+
+            breakpoints: HashSet::new(),
+            resuming_from_breakpoint: false,
+            instruction_address: ROM_LOAD_INDEX,
+            instruction: 0,
+            watches: Watches::default(),
+            mmio: None,
+
+            display: Display::new(),
+            megachip: None,
             key_states: [false; NUM_INPUT_KEYS],
             awaiting_keypress: false,
             awaiting_keypress_register: 0,
             awaiting_keyrelease: false,
             awaiting_keyelease_key_value: 0,
+            awaiting_vblank: false,
+
+            profile: Profile::default(),
+            rng: GuestRng::new(RngAlgorithm::default()),
+
+            rom_report: None,
+
+            machine_code_policy: MachineCodePolicy::default(),
+            machine_code_handler: None,
+            unknown_opcode_policy: UnknownOpcodePolicy::default(),
+            unknown_opcode_handler: None,
+            sound_callback: None,
         }
     }
 
-    pub fn load_program(&mut self, data: &[u8]) {
-        for (index, value) in data.iter().enumerate() {
-            self.memory[ROM_LOAD_INDEX + index] = *value;
+    pub fn with_profile(mut self, profile: Profile) -> Emulator {
+        self.set_profile(profile);
+        self
+    }
+
+    pub fn set_profile(&mut self, profile: Profile) {
+        if profile.rng != self.profile.rng {
+            self.rng = GuestRng::new(profile.rng);
+        }
+        // Anything past the end of a smaller memory is lost
+        self.memory.resize(profile.memory_size(), 0);
+        // Platforms with a different layout start programs elsewhere, on a different screen
+        if profile.load_address() != self.profile.load_address() {
+            self.program_counter = profile.load_address();
+            self.instruction_address = profile.load_address();
         }
+        if profile.display_mode() != self.profile.display_mode() {
+            self.display.reset(profile.display_mode());
+        }
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Reseeds the CXNN random number generator so that runs can be reproduced exactly.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = GuestRng::seeded(self.profile.rng, seed);
+    }
+
+    /// Configures the emulator to behave like the given interpreter.
+    pub fn with_variant(self, variant: Chip8Variant) -> Emulator {
+        self.with_profile(variant.profile())
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Emulator {
+        self.set_quirks(quirks);
+        self
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.profile.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.profile.quirks
+    }
+
+    pub fn load_program(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        let load_address = self.profile.load_address();
+        let max = self.memory.len() - load_address;
+        if data.is_empty() {
+            return Err(LoadError::EmptyRom);
+        } else if data.len() > max {
+            return Err(LoadError::RomTooLarge {
+                size: data.len(),
+                max,
+            });
+        } else if !data.len().is_multiple_of(2) {
+            return Err(LoadError::OddLength { size: data.len() });
+        }
+
+        self.load_data_at(load_address, data)?;
+        self.program_range = load_address..load_address + data.len();
+        self.rom_report = Some(diagnose(data, load_address));
+        Ok(())
+    }
+
+    /// The diagnostics gathered when the current program was loaded.
+    pub fn rom_report(&self) -> Option<&RomReport> {
+        self.rom_report.as_ref()
+    }
+
+    /// Copies an arbitrary blob of data into memory starting at `address`. This can be done before
+    /// or during execution, e.g. to supply level data or patch bytes.
+    pub fn load_data_at(&mut self, address: usize, data: &[u8]) -> Result<(), LoadError> {
+        let end = address
+            .checked_add(data.len())
+            .filter(|end| *end <= self.memory.len())
+            .ok_or(LoadError::OutOfBounds {
+                address,
+                size: data.len(),
+                memory_size: self.memory.len(),
+            })?;
+
+        self.memory[address..end].copy_from_slice(data);
+        Ok(())
     }
 
     pub fn load_font(&mut self, font_data: &FontData) {
@@ -85,178 +391,754 @@ impl Emulator {
         }
     }
 
-    pub async fn run(&mut self) {
-        // Reset existing state
-        self.program_counter = ROM_LOAD_INDEX;
+    /// Loads the large font used by FX30, right after the small one.
+    pub fn load_big_font(&mut self, font_data: &BigFontData) {
+        for (index, value) in font_data.iter().enumerate() {
+            self.memory[BIG_FONT_LOAD_INDEX + index] = *value;
+        }
+    }
+
+    /// Starts `program` over on a clean machine, with memory past the interpreter's area cleared
+    /// and the random number generator seeded with `seed`, so that what happens from here on
+    /// only depends on the input.
+    pub fn restart(&mut self, program: &[u8], seed: u64) -> Result<(), LoadError> {
+        let load_address = self.profile.load_address();
+        self.memory[load_address..].fill(0);
+        self.program_range = load_address..load_address;
+        self.reset();
+        self.seed_rng(seed);
+        self.load_program(program)
+    }
+
+    /// Resets the CPU so that execution begins again from the start of the loaded program.
+    /// Memory contents are left untouched.
+    pub fn reset(&mut self) {
+        self.program_counter = self.profile.load_address();
         self.index_register = 0;
+        self.registers = [0; 16];
+        self.stack.clear();
+        self.delay_timer = 0;
+        self.set_sound_timer(0);
+        self.sound_ticked = false;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.display.reset(self.profile.display_mode());
+        self.megachip = None;
+        self.awaiting_keypress = false;
+        self.awaiting_keyrelease = false;
+        self.awaiting_vblank = false;
+        self.timer_time = 0.0;
+        self.update_time = 0.0;
+        self.max_stack_depth = 0;
+        if self.canaries {
+            self.fill_canaries();
+        }
+    }
 
-        let mut image = Image::gen_image_color(SCREEN_WIDTH as u16, SCREEN_HEIGHT as u16, BLACK);
-        let texture = Texture2D::from_image(&image);
-        texture.set_filter(FilterMode::Nearest);
+    /// Fills memory outside the font and the program with a canary pattern, now and on every
+    /// reset, so that [`Emulator::usage`] can report how much memory the program has touched.
+    /// Off by default, since programs may rely on unused memory reading as zero.
+    pub fn set_canaries(&mut self, enabled: bool) {
+        self.canaries = enabled;
+        if enabled {
+            self.fill_canaries();
+        }
+    }
 
-        let target_cycle_time = 1.0 / TARGET_OPS_PER_SECOND as f32;
-        let mut update_time = 0.0;
+    fn fill_canaries(&mut self) {
+        let font_range = FONT_LOAD_INDEX..BIG_FONT_LOAD_INDEX + BIG_FONT.len();
+        for address in 0..self.memory.len() {
+            if !font_range.contains(&address) && !self.program_range.contains(&address) {
+                self.memory[address] = usage::canary(address);
+            }
+        }
+    }
 
-        let target_timer_time = 1.0 / TIMER_HZ;
-        let mut timer_time = 0.0;
-
-        loop {
-            // Update input states
-            for key_index in 0..NUM_INPUT_KEYS {
-                let keycode =
-                    Self::key_value_to_keycode(key_index as u8).expect("Expected valid key index.");
-                self.key_states[key_index] = is_key_down(keycode);
+    /// Limits how many subroutine calls can be nested, so that runaway recursion stops with
+    /// [`RuntimeError::StackOverflow`] instead of growing the stack forever. The original
+    /// interpreters had room for 12 to 16 levels. None, the default, means no limit.
+    pub fn set_stack_limit(&mut self, limit: Option<usize>) {
+        self.stack_limit = limit;
+    }
+
+    pub fn stack_limit(&self) -> Option<usize> {
+        self.stack_limit
+    }
+
+    /// High-water marks of stack and memory use since the last reset.
+    pub fn usage(&self) -> UsageReport {
+        let used = self.canaries.then(|| {
+            (0..self.memory.len())
+                .filter(|address| self.memory[*address] != usage::canary(*address))
+        });
+        UsageReport {
+            max_stack_depth: self.max_stack_depth,
+            memory_used: used.clone().map(|used| used.count()),
+            highest_address_used: used.and_then(|mut used| used.next_back()),
+            memory_size: self.memory.len(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
+    }
+
+    /// Registers a callback that fires whenever the running program reads or writes (depending
+    /// on `kind`) a byte within `range`.
+    pub fn watch_memory(
+        &mut self,
+        kind: AccessKind,
+        range: Range<usize>,
+        callback: impl FnMut(&MemoryAccess) + 'static,
+    ) -> WatchId {
+        self.watches.add(kind, range, Box::new(callback))
+    }
+
+    /// Removes a previously registered memory observer, returning whether it existed.
+    pub fn unwatch_memory(&mut self, id: WatchId) -> bool {
+        self.watches.remove(id)
+    }
+
+    /// Captures the complete machine state.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            profile: self.profile,
+            memory: self.memory.clone(),
+            registers: self.registers,
+            index_register: self.index_register,
+            program_counter: self.program_counter,
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            screen: self.plane_bits(0),
+            second_plane: self.plane_bits(1),
+            selected_planes: self.display.selection(),
+            display_mode: self.display.mode(),
+            megachip: self.megachip.as_ref().map(MegaChip::to_bytes),
+            cycles: self.cycles,
+            rng: self.rng.state(),
+            key_wait: self.key_wait(),
+            timer_time: self.timer_time,
+            update_time: self.update_time,
+            step_time: self.step_time,
+            awaiting_vblank: self.awaiting_vblank,
+        }
+    }
+
+    fn plane_bits(&self, plane: usize) -> Vec<bool> {
+        (0..HIRES_WIDTH * HIRES_HEIGHT)
+            .map(|bit| {
+                self.display
+                    .get_raw(plane, bit % HIRES_WIDTH, bit / HIRES_WIDTH)
+            })
+            .collect()
+    }
+
+    /// Restores a machine state previously captured with [`Emulator::snapshot`], switching to the
+    /// profile it was captured with.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        let memory_size = snapshot.profile.memory_size();
+        if snapshot.memory.len() != memory_size {
+            return Err(SnapshotError::Invalid(
+                "memory size doesn't match the profile",
+            ));
+        }
+        if snapshot.rng.algorithm() != snapshot.profile.rng {
+            return Err(SnapshotError::Invalid(
+                "random number generator doesn't match the profile",
+            ));
+        }
+        if snapshot.screen.len() != HIRES_WIDTH * HIRES_HEIGHT
+            || snapshot.second_plane.len() != HIRES_WIDTH * HIRES_HEIGHT
+        {
+            return Err(SnapshotError::Invalid("screen size doesn't match"));
+        }
+        // FX1E doesn't wrap I, so it may point past the end of memory; only the program counter
+        // has to be in bounds
+        if snapshot.program_counter >= memory_size {
+            return Err(SnapshotError::Invalid("program counter out of bounds"));
+        }
+        match snapshot.key_wait {
+            KeyWait::Press { register } if register >= 16 => {
+                return Err(SnapshotError::Invalid("key wait register out of range"));
+            }
+            KeyWait::Release { key } if key as usize >= NUM_INPUT_KEYS => {
+                return Err(SnapshotError::Invalid("key wait key out of range"));
             }
+            _ => {}
+        }
+        if [
+            snapshot.timer_time,
+            snapshot.update_time,
+            snapshot.step_time,
+        ]
+        .iter()
+        .any(|time| !time.is_finite())
+        {
+            return Err(SnapshotError::Invalid("timer phase isn't a finite number"));
+        }
+        let megachip = match &snapshot.megachip {
+            None => None,
+            Some(state) => Some(
+                MegaChip::from_bytes(state)
+                    .ok_or(SnapshotError::Invalid("Mega-CHIP state is malformed"))?,
+            ),
+        };
 
-            // Update the timers
-            timer_time -= get_frame_time();
-            while timer_time <= 0.0 {
-                timer_time += target_timer_time;
+        self.set_profile(snapshot.profile);
+        self.rng = GuestRng::from_state(snapshot.rng);
+        self.memory.copy_from_slice(&snapshot.memory);
+        self.registers = snapshot.registers;
+        self.index_register = snapshot.index_register;
+        self.program_counter = snapshot.program_counter;
+        self.stack = snapshot.stack.clone();
+        self.delay_timer = snapshot.delay_timer;
+        self.set_sound_timer(snapshot.sound_timer);
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+        for (plane, bits) in [&snapshot.screen, &snapshot.second_plane]
+            .into_iter()
+            .enumerate()
+        {
+            for (bit, on) in bits.iter().enumerate() {
+                self.display
+                    .set_raw(plane, bit % HIRES_WIDTH, bit / HIRES_WIDTH, *on);
+            }
+        }
+        self.display.select(snapshot.selected_planes);
+        self.display.set_mode(snapshot.display_mode);
+        self.megachip = megachip;
+        self.cycles = snapshot.cycles;
+        self.awaiting_keypress = false;
+        self.awaiting_keypress_register = 0;
+        self.awaiting_keyrelease = false;
+        self.awaiting_keyelease_key_value = 0;
+        match snapshot.key_wait {
+            KeyWait::None => {}
+            KeyWait::Press { register } => {
+                self.awaiting_keypress = true;
+                self.awaiting_keypress_register = register;
+            }
+            KeyWait::Release { key } => {
+                self.awaiting_keyrelease = true;
+                self.awaiting_keyelease_key_value = key;
+            }
+        }
+        self.timer_time = snapshot.timer_time;
+        self.update_time = snapshot.update_time;
+        self.step_time = snapshot.step_time;
+        self.awaiting_vblank = snapshot.awaiting_vblank;
+        Ok(())
+    }
 
-                if let Some(new_delay_timer) = self.delay_timer.checked_sub(1) {
-                    self.delay_timer = new_delay_timer;
-                }
+    /// Maps a small read-only window of host-provided values into memory at `base`. This isn't
+    /// part of any CHIP-8 specification; it exists for homebrew experiments, so it's off by
+    /// default. Relative to `base`, the window holds:
+    ///
+    /// | Offset | Value                                          |
+    /// |--------|------------------------------------------------|
+    /// | 0      | Hours of the host's clock (UTC)                |
+    /// | 1      | Minutes                                        |
+    /// | 2      | Seconds                                        |
+    /// | 3      | Frame counter, incremented at 60Hz, wrapping   |
+    /// | 4      | Pointer X position in pixels                   |
+    /// | 5      | Pointer Y position in pixels                   |
+    /// | 6      | Pointer buttons, see [`Pointer::buttons`]      |
+    /// | 7      | Reserved, reads as 0                           |
+    ///
+    /// Writes to the window are ignored, and the memory underneath is left untouched.
+    pub fn enable_mmio(&mut self, base: usize) -> Result<(), LoadError> {
+        if base
+            .checked_add(MMIO_BYTES)
+            .is_none_or(|end| end > self.memory.len())
+        {
+            return Err(LoadError::OutOfBounds {
+                address: base,
+                size: MMIO_BYTES,
+                memory_size: self.memory.len(),
+            });
+        }
 
-                if let Some(new_sound_timer) = self.sound_timer.checked_sub(1) {
-                    self.sound_timer = new_sound_timer;
-                }
+        self.mmio = Some(Mmio::new(base));
+        Ok(())
+    }
+
+    pub fn disable_mmio(&mut self) {
+        self.mmio = None;
+    }
+
+    /// The address memory-mapped I/O is mapped at, if it's enabled.
+    pub fn mmio_base(&self) -> Option<usize> {
+        self.mmio.as_ref().map(|mmio| mmio.base)
+    }
+
+    /// Updates the pointer state reported through memory-mapped I/O.
+    pub fn set_pointer(&mut self, pointer: Pointer) {
+        if let Some(mmio) = &mut self.mmio {
+            mmio.pointer = pointer;
+        }
+    }
+
+    /// Registers a handler for 0NNN machine code calls, allowing custom peripherals or host
+    /// calls to be built on top of the interpreter. This also switches the machine code policy
+    /// to [`MachineCodePolicy::Handler`].
+    pub fn set_machine_code_handler(
+        &mut self,
+        handler: impl FnMut(&mut Emulator, u16) -> bool + 'static,
+    ) {
+        self.machine_code_handler = Some(Box::new(handler));
+        self.machine_code_policy = MachineCodePolicy::Handler;
+    }
+
+    /// Chooses what happens when the program calls a 0NNN machine code routine.
+    pub fn set_machine_code_policy(&mut self, policy: MachineCodePolicy) {
+        self.machine_code_policy = policy;
+    }
+
+    pub fn machine_code_policy(&self) -> MachineCodePolicy {
+        self.machine_code_policy
+    }
+
+    /// Registers a handler for opcodes the interpreter doesn't recognize. The unknown opcode
+    /// policy only applies to the opcodes it doesn't handle.
+    pub fn set_unknown_opcode_handler(
+        &mut self,
+        handler: impl FnMut(&mut Emulator, u16) -> bool + 'static,
+    ) {
+        self.unknown_opcode_handler = Some(Box::new(handler));
+    }
+
+    /// Registers a callback for when the program starts, stops or changes the sound, so a host
+    /// can drive its own audio or haptics without polling the sound timer. It's called with
+    /// whether the sound timer is running and the ticks left when the program sets the timer,
+    /// when the timer runs out, and when a reset or restored snapshot changes it.
+    pub fn on_sound_change(&mut self, callback: impl FnMut(bool, u8) + 'static) {
+        self.sound_callback = Some(Box::new(callback));
+    }
+
+    /// Chooses what happens when the program executes an opcode the interpreter doesn't
+    /// recognize. This can be changed at any time, e.g. to start pausing on them once a
+    /// program misbehaves.
+    pub fn set_unknown_opcode_policy(&mut self, policy: UnknownOpcodePolicy) {
+        self.unknown_opcode_policy = policy;
+    }
+
+    pub fn unknown_opcode_policy(&self) -> UnknownOpcodePolicy {
+        self.unknown_opcode_policy
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    pub fn set_register(&mut self, register: usize, value: u8) {
+        self.registers[register] = value;
+    }
+
+    pub fn index_register(&self) -> usize {
+        self.index_register
+    }
+
+    pub fn set_index_register(&mut self, value: usize) {
+        self.index_register = value;
+    }
+
+    pub fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+
+    pub fn set_program_counter(&mut self, value: usize) {
+        self.program_counter = value;
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// What the emulator is waiting on after an FX0A instruction, if anything.
+    pub fn key_wait(&self) -> KeyWait {
+        if self.awaiting_keypress {
+            KeyWait::Press {
+                register: self.awaiting_keypress_register,
+            }
+        } else if self.awaiting_keyrelease {
+            KeyWait::Release {
+                key: self.awaiting_keyelease_key_value,
             }
+        } else {
+            KeyWait::None
+        }
+    }
 
-            // Perform CPU Cycles
-            update_time -= get_frame_time();
-            while update_time <= 0.0 {
-                update_time += target_cycle_time;
-
-                if self.awaiting_keyrelease {
-                    println!("Awaiting keyrelease...");
-                    if is_key_down(
-                        Self::key_value_to_keycode(self.awaiting_keyelease_key_value)
-                            .expect("Expected valid keypress register."),
-                    ) {
-                        continue;
-                    }
+    /// Holds down keypad key `key_value` until [`Emulator::release_key`], for driving the
+    /// emulator without a window. Values above 0xF are ignored.
+    pub fn press_key(&mut self, key_value: u8) {
+        if let Some(state) = self.key_states.get_mut(key_value as usize) {
+            *state = true;
+        }
+    }
 
-                    self.awaiting_keyrelease = false;
-                    self.awaiting_keyelease_key_value = 0;
-                }
+    /// Lets go of keypad key `key_value`. Values above 0xF are ignored.
+    pub fn release_key(&mut self, key_value: u8) {
+        if let Some(state) = self.key_states.get_mut(key_value as usize) {
+            *state = false;
+        }
+    }
 
-                if self.awaiting_keypress {
-                    println!("Awaiting keypress...");
-                    match self.get_awaited_key() {
-                        Some(key_index) => {
-                            self.registers[self.awaiting_keypress_register] = key_index;
-                            // Done awaiting press...
-                            self.awaiting_keypress = false;
-                            self.awaiting_keypress_register = 0;
+    /// Whether keypad key `key_value` is held down.
+    pub fn is_key_held(&self, key_value: u8) -> bool {
+        self.key_states
+            .get(key_value as usize)
+            .copied()
+            .unwrap_or(false)
+    }
 
-                            // ...now await release.
-                            self.awaiting_keyrelease = true;
-                            self.awaiting_keyelease_key_value = key_index;
+    /// Time left until the delay and sound timers next tick, in seconds.
+    pub fn timer_time(&self) -> f32 {
+        self.timer_time
+    }
 
-                            continue; // need to continue to await the release
-                        }
-                        None => continue,
-                    }
-                }
+    /// Time left until [`Emulator::update`] executes the next instruction, in seconds.
+    pub fn update_time(&self) -> f32 {
+        self.update_time
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// Whether the sound should be heard since the last call: the sound timer is running, or was
+    /// at a timer tick in between. Hosts checking once a frame would otherwise miss beeps that
+    /// start and end within the frame.
+    pub fn take_sound(&mut self) -> bool {
+        std::mem::take(&mut self.sound_ticked) || self.sound_timer > 0
+    }
+
+    /// The XO-CHIP audio pattern played while the sound timer is running, as 128 one-bit samples
+    /// from the most significant bit of the first byte. None until the program loads one with
+    /// F002, in which case the host should play its usual beep instead.
+    pub fn audio_pattern(&self) -> Option<[u8; AUDIO_PATTERN_BYTES]> {
+        self.audio_pattern
+    }
+
+    /// The XO-CHIP pitch register set by FX3A.
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// How many samples of the audio pattern play per second, as set by the pitch register.
+    pub fn pattern_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - DEFAULT_PITCH as f32) / 48.0)
+    }
+
+    /// The RPL user flags written by FX75 and read by FX85. On the HP-48 these survived between
+    /// programs, so they aren't cleared by [`Emulator::reset`] or stored in snapshots; hosts can
+    /// persist them with [`Emulator::set_rpl_flags`].
+    pub fn rpl_flags(&self) -> [u8; RPL_FLAGS] {
+        self.rpl_flags
+    }
+
+    pub fn set_rpl_flags(&mut self, flags: [u8; RPL_FLAGS]) {
+        self.rpl_flags = flags;
+    }
+
+    /// Whether the pixel at (`x`, `y`) in the current mode's coordinates is lit. Coordinates
+    /// outside the screen are never lit.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let (width, height) = self.display.size();
+        x < width && y < height && self.display.get(x, y)
+    }
+
+    /// The size of the screen in the current mode's pixels: 64x32, 128x64 in SUPER-CHIP's hires
+    /// mode, 64x64 for two-page hires CHIP-8 or 64x48 on the ETI-660.
+    pub fn display_size(&self) -> (usize, usize) {
+        self.display.size()
+    }
+
+    /// The resolution the screen has to be shown at to be exact, see [`Emulator::screen_color`].
+    /// This is [`Emulator::display_size`], except when lores content has been scrolled by half a
+    /// pixel, which needs the full 128x64 framebuffer to show.
+    pub fn screen_resolution(&self) -> (usize, usize) {
+        self.display.resolution()
+    }
+
+    /// The color of the pixel at (`x`, `y`) at [`Emulator::screen_resolution`]: bit 0 is set if
+    /// it's lit on the first plane and bit 1 if it's lit on XO-CHIP's second plane.
+    pub fn screen_color(&self, x: usize, y: usize) -> u8 {
+        self.display.sample(x, y)
+    }
+
+    /// Whether SUPER-CHIP's 128x64 hires mode is active.
+    pub fn hires(&self) -> bool {
+        self.display.hires()
+    }
+
+    /// The screen geometry currently in use.
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display.mode()
+    }
+
+    /// The Mega-CHIP screen as [`MEGACHIP_WIDTH`] by [`MEGACHIP_HEIGHT`] RGBA colors, row by row,
+    /// while Mega-CHIP mode is on. It replaces the usual screen entirely.
+    pub fn megachip_frame(&self) -> Option<&[[u8; 4]]> {
+        self.megachip.as_ref().map(MegaChip::frame)
+    }
+
+    /// The digitized sound a Mega-CHIP program is playing, if any.
+    pub fn megachip_sound(&self) -> Option<&DigitizedSound> {
+        self.megachip
+            .as_ref()
+            .and_then(|megachip| megachip.sound.as_ref())
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Runs the emulator in a window with the default frontend settings until it halts,
+    /// returning the reason it stopped. Calling this again afterwards resumes from where
+    /// emulation left off.
+    pub async fn run(&mut self) -> HaltReason {
+        Frontend::default().run(self).await
+    }
+
+    /// Runs the emulator without a window for at most `max_cycles` instructions. Time is
+    /// emulated, so the timers tick at the same rate relative to instructions as when windowed.
+    pub fn run_headless(&mut self, max_cycles: u64) -> HaltReason {
+        for _ in 0..max_cycles {
+            if let Err(reason) = self.step_headless() {
+                return reason;
+            }
+        }
+
+        HaltReason::Timeout
+    }
+
+    /// Runs the emulator headlessly as an iterator over the instructions it executes.
+    pub fn trace(&mut self) -> Trace<'_> {
+        Trace::new(self)
+    }
+
+    pub(crate) fn step_headless(&mut self) -> Result<(), HaltReason> {
+        self.update_timers(self.step_time);
+        self.step()
+    }
 
-                // Grab the next instruction and increment the program counter
-                let high = self.memory[self.program_counter] as u16;
-                let low = self.memory[self.program_counter + 1] as u16;
-                let instruction = (high << 8) | low;
-                self.program_counter += 2;
-
-                // Extract some common pieces of the instruction
-                let x = ((instruction & 0x0F00) >> 8) as usize; // 4-bit register id
-                let y = ((instruction & 0x00F0) >> 4) as usize; // 4-bit register id
-                let n = (instruction & 0x000F) as u8; // 4-bit constant
-                let nn = (instruction & 0x00FF) as u8; // 8-bit constant
-                let nnn = (instruction & 0x0FFF) as usize; // address
-
-                let nibbles = (
-                    (instruction & 0xF000) >> 12,
-                    (instruction & 0x0F00) >> 8,
-                    (instruction & 0x00F0) >> 4,
-                    (instruction & 0x000F),
-                );
-
-                match nibbles {
-                    (0x0, 0x0, 0xE, 0x0) => self.op_00e0(), // 00E0 Display - Clears the screen
-                    (0x0, 0x0, 0xE, 0xE) => self.op_00ee(), // 00EE Flow - Return from subroutine
-                    (0x0, 0x1, _, _) => self.op_0nnn(), // 0NNN Call - Calls a machine code routine
-                    (0x1, _, _, _) => self.op_1nnn(nnn), // 1NNN Flow - Goto NNN
-                    (0x2, _, _, _) => self.op_2nnn(nnn), // 2NNN Flow - Calls subroutine at NNN
-                    (0x3, _, _, _) => self.op_3xnn(x, nn), // 3XNN Cond - Skips the next instruction if VX equals NN
-                    (0x4, _, _, _) => self.op_4xnn(x, nn), // 4XNN Cond - Skips the next instruction if VX does not equal NN
-                    (0x5, _, _, _) => self.op_5xy0(x, y), // 5XY0 Cond - Skips the next instruction if VX equals VY
-                    (0x6, _, _, _) => self.op_6xnn(x, nn), // 6XNN Const - Set VX to NN
-                    (0x7, _, _, _) => self.op_7xnn(x, nn), // 7XNN Const - Adds NN to VX
-                    (0x8, _, _, 0x0) => self.op_8xy0(x, y), // 8XY0 Assign - Sets VX to the value of VY
-                    (0x8, _, _, 0x1) => self.op_8xy1(x, y), // 8XY1 BitOp - Sets VX to VX | VY
-                    (0x8, _, _, 0x2) => self.op_8xy2(x, y), // 8XY2 BitOp - Sets VX to VX & VY
-                    (0x8, _, _, 0x3) => self.op_8xy3(x, y), // 8XY3 BitOp - Sets VX to VX ^ VY
-                    (0x8, _, _, 0x4) => self.op_8xy4(x, y), // 8XY4 Math - Adds VY to VX, setting VF if there's an overflow
-                    (0x8, _, _, 0x5) => self.op_8xy5(x, y), // 8XY5 Math - Subtracts VY from VX. Sets VF to 0 if underflow, 1 otherwise
-                    (0x8, _, _, 0x6) => self.op_8xy6(x), // 8XY6 BitOp - Shifts VX to the right by 1, setting VF to the shifted bit
-                    (0x8, _, _, 0x7) => self.op_8xy7(x, y), // 8XY7 Math - Sets VX to VY - VX. Sets VF to 0 if underflow, 1 otherwise
-                    (0x8, _, _, 0xE) => self.op_8xye(x), // 8XYE BitOp - Shifts VX to the left by 1, setting VF to the shifted bit
-                    (0x9, _, _, _) => self.op_9xy0(x, y), // 9XY0 Cond - Skips the next instruction if VX does not equal VY
-                    (0xA, _, _, _) => self.op_annn(nnn), // ANNN MEM - Sets the I to the address NNN
-                    (0xB, _, _, _) => self.op_bnnn(x, nnn), // BNNN Flow - Jumps to the address NNN + V0
-                    (0xC, _, _, _) => self.op_cxnn(x, nn), // CXNN Rand - Sets VX to the result of a bitwise AND operation on a random u8 number and NN
-                    (0xD, _, _, _) => self.op_dxyn(x, y, n), // DXYN Display - Draws a sprite at coordinate (VX, VY)
-                    (0xE, _, 0x9, 0xE) => self.op_ex9e(x),   // EX9E KeyOp - Skip if key pressed
-                    (0xE, _, 0xA, 0x1) => self.op_exa1(x),   // EXA1 KeyOp - Skip if not pressed
-                    (0xF, _, 0x0, 0x7) => self.op_fx07(x), // FX07 Timer - Sets VX to the value of the delay timer
-                    (0xF, _, 0x0, 0xA) => self.op_fx0a(x), // FX0A KeyOp - A key press is awaited and then stored in VX (blocking operation)
-                    (0xF, _, 0x1, 0x5) => self.op_fx15(x), // FX15 Timer - Sets the delay timer to VX
-                    (0xF, _, 0x1, 0x8) => self.op_fx18(x), // FX18 Timer - Sets the sound timer to VX
-                    (0xF, _, 0x1, 0xE) => self.op_fx1e(x), // FX1E MEM - Adds VX to I.
-                    (0xF, _, 0x2, 0x9) => self.op_fx29(x), // FX29 MEM - Sets I to the location of the sprite for the character in VX
-                    (0xF, _, 0x3, 0x3) => self.op_fx33(x), // FX33 BCD - Stores the binary-coded decimal representation of VX in memory using the index register
-                    (0xF, _, 0x5, 0x5) => self.op_fx55(x), // FX55 MEM - Stores V0 to VX in memory, starting at address I
-                    (0xF, _, 0x6, 0x5) => self.op_fx65(x), // FX65 MEM - Loads V0 to VX from memory, starting at address I
-                    _ => eprintln!("Unrecognized instruction: {instruction:#04X}"),
+    /// The address and opcode of the most recently executed instruction.
+    pub(crate) fn last_instruction(&self) -> (usize, u16) {
+        (self.instruction_address, self.instruction)
+    }
+
+    /// Advances emulation by `delta` seconds, ticking the timers and executing however many
+    /// instructions fit in that time.
+    pub fn update(&mut self, delta: f32) -> Result<(), HaltReason> {
+        self.update_timers(delta);
+
+        self.update_time -= delta;
+        while self.update_time <= 0.0 {
+            let result = self.step();
+            self.update_time += self.step_time;
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn update_timers(&mut self, delta: f32) {
+        let target_timer_time = 1.0 / TIMER_HZ;
+        self.timer_time -= delta;
+        while self.timer_time <= 0.0 {
+            self.timer_time += target_timer_time;
+            self.awaiting_vblank = false;
+
+            if let Some(mmio) = &mut self.mmio {
+                mmio.frames = mmio.frames.wrapping_add(1);
+            }
+
+            if let Some(new_delay_timer) = self.delay_timer.checked_sub(1) {
+                self.delay_timer = new_delay_timer;
+            }
+
+            if let Some(new_sound_timer) = self.sound_timer.checked_sub(1) {
+                self.sound_timer = new_sound_timer;
+                self.sound_ticked = true;
+                if new_sound_timer == 0 {
+                    self.notify_sound();
                 }
+            }
+        }
+    }
 
-                self.redraw_screen(&mut image);
+    /// Executes a single instruction, or a single cycle of waiting for a key.
+    pub fn step(&mut self) -> Result<(), HaltReason> {
+        // Waiting and anything else which doesn't execute an instruction takes a fixed time
+        self.step_time = 1.0 / TARGET_OPS_PER_SECOND as f32;
+        if self.awaiting_vblank {
+            return Ok(());
+        }
+
+        if self.awaiting_keyrelease {
+            if self.key_states[self.awaiting_keyelease_key_value as usize] {
+                return Ok(());
             }
 
-            // Redraw the window graphics
-            clear_background(BLACK);
-
-            texture.update(&image);
-            draw_texture_ex(
-                &texture,
-                0.0,
-                0.0,
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(Vec2 {
-                        x: screen_width(),
-                        y: screen_height(),
-                    }),
-                    source: None,
-                    rotation: 0.0,
-                    flip_x: false,
-                    flip_y: false,
-                    pivot: None,
-                },
-            );
-
-            next_frame().await;
-        }
-    }
-
-    fn redraw_screen(&mut self, image: &mut Image) {
-        for bit in 0..(SCREEN_WIDTH * SCREEN_HEIGHT) {
-            let (x, y) = Self::flat_to_screen(bit);
-
-            if self.screen.contains(bit) {
-                image.set_pixel(x as u32, y as u32, WHITE);
-            } else {
-                image.set_pixel(x as u32, y as u32, BLACK);
+            self.awaiting_keyrelease = false;
+            self.awaiting_keyelease_key_value = 0;
+        }
+
+        if self.awaiting_keypress {
+            if let Some(key_index) = self.get_awaited_key() {
+                self.registers[self.awaiting_keypress_register] = key_index;
+                // Done awaiting press...
+                self.awaiting_keypress = false;
+                self.awaiting_keypress_register = 0;
+
+                // ...now await release, if the key only counts once it's released.
+                if self.profile.quirks.key_release {
+                    self.awaiting_keyrelease = true;
+                    self.awaiting_keyelease_key_value = key_index;
+                }
             }
+
+            return Ok(()); // need to continue to await the release
+        }
+
+        if self.program_counter + 1 >= self.memory.len() {
+            return Err(RuntimeError::ProgramCounterOutOfBounds {
+                address: self.program_counter,
+            }
+            .into());
+        }
+
+        // Stop before executing a breakpoint, but don't trip over it again when resuming
+        if self.breakpoints.contains(&self.program_counter) && !self.resuming_from_breakpoint {
+            self.resuming_from_breakpoint = true;
+            return Err(HaltReason::Breakpoint(self.program_counter));
         }
+        self.resuming_from_breakpoint = false;
+
+        // Grab the next instruction and increment the program counter
+        let address = self.program_counter;
+        self.instruction_address = address;
+        let high = self.memory[self.program_counter] as u16;
+        let low = self.memory[self.program_counter + 1] as u16;
+        let instruction = (high << 8) | low;
+        self.instruction = instruction;
+        self.program_counter += 2;
+        self.cycles += 1;
+        let registers = self.registers;
+
+        // Extract some common pieces of the instruction
+        let x = ((instruction & 0x0F00) >> 8) as usize; // 4-bit register id
+        let y = ((instruction & 0x00F0) >> 4) as usize; // 4-bit register id
+        let n = (instruction & 0x000F) as u8; // 4-bit constant
+        let nn = (instruction & 0x00FF) as u8; // 8-bit constant
+        let nnn = (instruction & 0x0FFF) as usize; // address
+
+        let nibbles = (
+            (instruction & 0xF000) >> 12,
+            (instruction & 0x0F00) >> 8,
+            (instruction & 0x00F0) >> 4,
+            (instruction & 0x000F),
+        );
+
+        match nibbles {
+            (0x0, 0x0, 0x1, 0x0) if self.profile.megachip => self.op_0010(), // 0010 Display - Switch Mega-CHIP mode off (Mega-CHIP)
+            (0x0, 0x0, 0x1, 0x1) if self.profile.megachip => self.op_0011(), // 0011 Display - Switch to the 256x192 Mega-CHIP mode (Mega-CHIP)
+            (0x0, 0x0, 0xB, _) if self.megachip.is_some() => self.op_00bn(n), // 00BN Display - Scroll up N pixels (Mega-CHIP)
+            (0x0, 0x1, _, _) if self.profile.megachip => self.op_01nn(nn)?, // 01NN NNNN MEM - Sets I to the 24-bit address NN NNNN (Mega-CHIP)
+            (0x0, 0x2, _, _) if self.profile.megachip => self.op_02nn(nn), // 02NN Display - Loads NN ARGB palette colors from I (Mega-CHIP)
+            (0x0, 0x3, _, _) if self.profile.megachip => self.op_03nn(nn), // 03NN Display - Sets the sprite width to NN (Mega-CHIP)
+            (0x0, 0x4, _, _) if self.profile.megachip => self.op_04nn(nn), // 04NN Display - Sets the sprite height to NN (Mega-CHIP)
+            (0x0, 0x5, _, _) if self.profile.megachip => self.op_05nn(nn), // 05NN Display - Sets the screen alpha to NN (Mega-CHIP)
+            (0x0, 0x6, 0x0, _) if self.profile.megachip => self.op_060n(n), // 060N Sound - Plays the digitized sound at I, looping if N is 0 (Mega-CHIP)
+            (0x0, 0x7, 0x0, 0x0) if self.profile.megachip => self.op_0700(), // 0700 Sound - Stops the digitized sound (Mega-CHIP)
+            (0x0, 0x8, 0x0, _) if self.profile.megachip => self.op_080n(n), // 080N Display - Sets the sprite blend mode (Mega-CHIP)
+            (0x0, 0x9, _, _) if self.profile.megachip => self.op_09nn(nn), // 09NN Display - Sets the collision color index to NN (Mega-CHIP)
+            (0x0, 0x0, 0xC, _) if self.profile.super_chip => self.op_00cn(n), // 00CN Display - Scroll down N pixels (SUPER-CHIP)
+            (0x0, 0x0, 0xD, _) if self.profile.xo_chip => self.op_00dn(n), // 00DN Display - Scroll up N pixels (XO-CHIP)
+            (0x0, 0x0, 0xE, 0x0) => self.op_00e0(), // 00E0 Display - Clears the screen
+            (0x0, 0x0, 0xE, 0xE) => self.op_00ee(address)?, // 00EE Flow - Return from subroutine
+            (0x0, 0x0, 0xF, 0xB) if self.profile.super_chip => self.op_00fb(), // 00FB Display - Scroll right 4 pixels (SUPER-CHIP)
+            (0x0, 0x0, 0xF, 0xC) if self.profile.super_chip => self.op_00fc(), // 00FC Display - Scroll left 4 pixels (SUPER-CHIP)
+            (0x0, 0x0, 0xF, 0xD) if self.profile.super_chip => return Err(HaltReason::Exit), // 00FD Flow - Exit the interpreter (SUPER-CHIP)
+            (0x0, 0x0, 0xF, 0xE) if self.profile.super_chip => self.op_00fe(), // 00FE Display - Switch to 64x32 lores mode (SUPER-CHIP)
+            (0x0, 0x0, 0xF, 0xF) if self.profile.super_chip => self.op_00ff(), // 00FF Display - Switch to 128x64 hires mode (SUPER-CHIP)
+            _ if instruction == 0x1260
+                && address == ROM_LOAD_INDEX
+                && self.profile.two_page_hires =>
+            {
+                self.op_two_page_start()
+            } // 1260 at 0x200 - Starts a two-page hires CHIP-8 program
+            (0x0, 0x2, 0x3, 0x0) if self.display.mode() == DisplayMode::TwoPage => self.op_00e0(), // 0230 Display - Clears the 64x64 screen (two-page hires CHIP-8)
+            (0x0, _, _, _) => self.op_0nnn(address, instruction)?, // 0NNN Call - Calls a machine code routine
+            (0x1, _, _, _) => self.op_1nnn(nnn),                   // 1NNN Flow - Goto NNN
+            (0x2, _, _, _) => self.op_2nnn(address, nnn)?, // 2NNN Flow - Calls subroutine at NNN
+            (0x3, _, _, _) => self.op_3xnn(x, nn), // 3XNN Cond - Skips the next instruction if VX equals NN
+            (0x4, _, _, _) => self.op_4xnn(x, nn), // 4XNN Cond - Skips the next instruction if VX does not equal NN
+            (0x5, _, _, 0x2) if self.profile.xo_chip => self.op_5xy2(x, y), // 5XY2 MEM - Stores VX to VY in memory, starting at address I (XO-CHIP)
+            (0x5, _, _, 0x3) if self.profile.xo_chip => self.op_5xy3(x, y), // 5XY3 MEM - Loads VX to VY from memory, starting at address I (XO-CHIP)
+            (0x5, _, _, _) => self.op_5xy0(x, y), // 5XY0 Cond - Skips the next instruction if VX equals VY
+            (0x6, _, _, _) => self.op_6xnn(x, nn), // 6XNN Const - Set VX to NN
+            (0x7, _, _, _) => self.op_7xnn(x, nn), // 7XNN Const - Adds NN to VX
+            (0x8, _, _, 0x0) => self.op_8xy0(x, y), // 8XY0 Assign - Sets VX to the value of VY
+            (0x8, _, _, 0x1) => self.op_8xy1(x, y), // 8XY1 BitOp - Sets VX to VX | VY
+            (0x8, _, _, 0x2) => self.op_8xy2(x, y), // 8XY2 BitOp - Sets VX to VX & VY
+            (0x8, _, _, 0x3) => self.op_8xy3(x, y), // 8XY3 BitOp - Sets VX to VX ^ VY
+            (0x8, _, _, 0x4) => self.op_8xy4(x, y), // 8XY4 Math - Adds VY to VX, setting VF if there's an overflow
+            (0x8, _, _, 0x5) => self.op_8xy5(x, y), // 8XY5 Math - Subtracts VY from VX. Sets VF to 0 if underflow, 1 otherwise
+            (0x8, _, _, 0x6) => self.op_8xy6(x, y), // 8XY6 BitOp - Shifts VX to the right by 1, setting VF to the shifted bit
+            (0x8, _, _, 0x7) => self.op_8xy7(x, y), // 8XY7 Math - Sets VX to VY - VX. Sets VF to 0 if underflow, 1 otherwise
+            (0x8, _, _, 0xE) => self.op_8xye(x, y), // 8XYE BitOp - Shifts VX to the left by 1, setting VF to the shifted bit
+            (0x9, _, _, _) => self.op_9xy0(x, y), // 9XY0 Cond - Skips the next instruction if VX does not equal VY
+            (0xA, _, _, _) => self.op_annn(nnn),  // ANNN MEM - Sets the I to the address NNN
+            (0xB, _, _, _) => self.op_bnnn(x, nnn), // BNNN Flow - Jumps to the address NNN + V0
+            (0xC, _, _, _) => self.op_cxnn(x, nn), // CXNN Rand - Sets VX to the result of a bitwise AND operation on a random u8 number and NN
+            (0xD, _, _, _) => self.op_dxyn(x, y, n), // DXYN Display - Draws a sprite at coordinate (VX, VY)
+            (0xE, _, 0x9, 0xE) => self.op_ex9e(x),   // EX9E KeyOp - Skip if key pressed
+            (0xE, _, 0xA, 0x1) => self.op_exa1(x),   // EXA1 KeyOp - Skip if not pressed
+            (0xF, 0x0, 0x0, 0x0) if self.profile.xo_chip => self.op_f000()?, // F000 NNNN MEM - Sets I to the 16-bit address in the next word (XO-CHIP)
+            (0xF, 0x0, 0x0, 0x2) if self.profile.xo_chip => self.op_f002(), // F002 Sound - Loads the 16-byte audio pattern from I (XO-CHIP)
+            (0xF, _, 0x0, 0x1) if self.profile.xo_chip => self.op_fn01(x as u8), // FN01 Display - Selects the planes drawn on by bitmask N (XO-CHIP)
+            (0xF, _, 0x0, 0x7) => self.op_fx07(x), // FX07 Timer - Sets VX to the value of the delay timer
+            (0xF, _, 0x0, 0xA) => self.op_fx0a(x), // FX0A KeyOp - A key press is awaited and then stored in VX (blocking operation)
+            (0xF, _, 0x1, 0x5) => self.op_fx15(x), // FX15 Timer - Sets the delay timer to VX
+            (0xF, _, 0x1, 0x8) => self.op_fx18(x), // FX18 Timer - Sets the sound timer to VX
+            (0xF, _, 0x1, 0xE) => self.op_fx1e(x), // FX1E MEM - Adds VX to I.
+            (0xF, _, 0x2, 0x9) => self.op_fx29(x), // FX29 MEM - Sets I to the location of the sprite for the character in VX
+            (0xF, _, 0x3, 0x0) => self.op_fx30(x), // FX30 MEM - Sets I to the location of the large sprite for the character in VX (SUPER-CHIP)
+            (0xF, _, 0x3, 0xA) if self.profile.xo_chip => self.op_fx3a(x), // FX3A Sound - Sets the audio pitch to VX (XO-CHIP)
+            (0xF, _, 0x3, 0x3) => self.op_fx33(x), // FX33 BCD - Stores the binary-coded decimal representation of VX in memory using the index register
+            (0xF, _, 0x5, 0x5) => self.op_fx55(x), // FX55 MEM - Stores V0 to VX in memory, starting at address I
+            (0xF, _, 0x6, 0x5) => self.op_fx65(x), // FX65 MEM - Loads V0 to VX from memory, starting at address I
+            (0xF, _, 0x7, 0x5) => self.op_fx75(x), // FX75 MEM - Stores V0 to VX in the RPL user flags (SUPER-CHIP)
+            (0xF, _, 0x8, 0x5) => self.op_fx85(x), // FX85 MEM - Loads V0 to VX from the RPL user flags (SUPER-CHIP)
+            _ => self.op_unknown(address, instruction)?,
+        }
+
+        if self.profile.timing == Timing::Vip {
+            let skipped = matches!(
+                instruction & 0xF000,
+                0x3000 | 0x4000 | 0x5000 | 0x9000 | 0xE000
+            ) && self.program_counter != address + 2;
+            self.step_time = timing::vip_time(instruction, &registers, skipped);
+        }
+
+        Ok(())
     }
 
     fn op_fx0a(&mut self, x: usize) {
@@ -266,35 +1148,100 @@ impl Emulator {
 
     fn op_fx65(&mut self, x: usize) {
         for register in 0..=x {
-            self.registers[register] = self.memory[self.index_register + register];
+            self.registers[register] = self.read_memory(self.index_register + register);
+        }
+        self.increment_index_after_transfer(x);
+    }
+
+    /// The registers from VX to VY inclusive, counting down if X is greater than Y.
+    fn register_range(x: usize, y: usize) -> Vec<usize> {
+        if x <= y {
+            (x..=y).collect()
+        } else {
+            (y..=x).rev().collect()
+        }
+    }
+
+    fn op_5xy2(&mut self, x: usize, y: usize) {
+        for (offset, register) in Self::register_range(x, y).into_iter().enumerate() {
+            self.write_memory(self.index_register + offset, self.registers[register]);
+        }
+    }
+
+    fn op_5xy3(&mut self, x: usize, y: usize) {
+        for (offset, register) in Self::register_range(x, y).into_iter().enumerate() {
+            self.registers[register] = self.read_memory(self.index_register + offset);
+        }
+    }
+
+    /// Applies the `memory_increment` quirks after FX55 or FX65 transferred V0 to VX.
+    fn increment_index_after_transfer(&mut self, x: usize) {
+        let quirks = &self.profile.quirks;
+        if quirks.memory_increment {
+            self.index_register += if quirks.memory_increment_by_x {
+                x
+            } else {
+                x + 1
+            };
         }
     }
 
+    fn op_fx75(&mut self, x: usize) {
+        self.rpl_flags[..=x].copy_from_slice(&self.registers[..=x]);
+    }
+
+    fn op_fx85(&mut self, x: usize) {
+        self.registers[..=x].copy_from_slice(&self.rpl_flags[..=x]);
+    }
+
     fn op_fx55(&mut self, x: usize) {
         for register in 0..=x {
-            self.memory[self.index_register + register] = self.registers[register];
+            self.write_memory(self.index_register + register, self.registers[register]);
         }
+        self.increment_index_after_transfer(x);
     }
 
     fn op_fx33(&mut self, x: usize) {
         let hundreds = self.registers[x] / 100;
         let tens = self.registers[x] / 10 % 10;
         let ones = self.registers[x] % 10;
-        self.memory[self.index_register] = hundreds;
-        self.memory[self.index_register + 1] = tens;
-        self.memory[self.index_register + 2] = ones;
+        self.write_memory(self.index_register, hundreds);
+        self.write_memory(self.index_register + 1, tens);
+        self.write_memory(self.index_register + 2, ones);
     }
 
     fn op_fx29(&mut self, x: usize) {
-        self.index_register = FONT_LOAD_INDEX + (x * 5)
+        self.index_register = FONT_LOAD_INDEX + (self.registers[x] & 0xF) as usize * 5
+    }
+
+    fn op_fx30(&mut self, x: usize) {
+        self.index_register = BIG_FONT_LOAD_INDEX + (self.registers[x] & 0xF) as usize * 10
     }
 
     fn op_fx1e(&mut self, x: usize) {
-        self.index_register += self.registers[x] as usize
+        self.index_register += self.registers[x] as usize;
+        if self.profile.quirks.index_overflow {
+            self.registers[0xF] = (self.index_register > 0xFFF) as u8;
+        }
     }
 
     fn op_fx18(&mut self, x: usize) {
-        self.sound_timer = self.registers[x]
+        self.set_sound_timer(self.registers[x]);
+    }
+
+    /// Sets the sound timer, telling the sound callback unless the sound stays off.
+    fn set_sound_timer(&mut self, ticks: u8) {
+        let was_sounding = self.sound_timer > 0;
+        self.sound_timer = ticks;
+        if was_sounding || ticks > 0 {
+            self.notify_sound();
+        }
+    }
+
+    fn notify_sound(&mut self) {
+        if let Some(callback) = &mut self.sound_callback {
+            callback(self.sound_timer > 0, self.sound_timer);
+        }
     }
 
     fn op_fx15(&mut self, x: usize) {
@@ -307,35 +1254,49 @@ impl Emulator {
 
     fn op_exa1(&mut self, x: usize) {
         if !self.key_states[self.registers[x] as usize] {
-            self.program_counter += 2;
+            self.skip_next();
         }
     }
 
     fn op_ex9e(&mut self, x: usize) {
         if self.key_states[self.registers[x] as usize] {
-            self.program_counter += 2;
+            self.skip_next();
         }
     }
 
     fn op_dxyn(&mut self, x: usize, y: usize, n: u8) {
-        let x_coord = self.registers[x] % SCREEN_WIDTH as u8;
-        let y_coord = self.registers[y] % SCREEN_HEIGHT as u8;
-        let height = n;
-        self.draw(x_coord, y_coord, height);
+        if self.megachip.is_some() {
+            let (x_coord, y_coord) = (self.registers[x] as usize, self.registers[y] as usize);
+            self.draw_megachip(x_coord, y_coord, n);
+            return;
+        }
+
+        let (width, height) = self.display.size();
+        let x_coord = (self.registers[x] as usize % width) as u8;
+        let y_coord = (self.registers[y] as usize % height) as u8;
+
+        // In hires mode, DXY0 draws a 16x16 sprite stored as 32 bytes, two per row. XO-CHIP
+        // allows it in lores mode too.
+        if n == 0 && (self.display.hires() || self.profile.xo_chip) {
+            self.draw(x_coord, y_coord, 16, 16);
+        } else {
+            self.draw(x_coord, y_coord, 8, n);
+        }
+
+        if self.profile.quirks.display_wait {
+            self.awaiting_vblank = true;
+        }
     }
 
     fn op_cxnn(&mut self, x: usize, nn: u8) {
-        let num = random_range(0..=255) as u8;
+        let num = self.rng.next_byte();
         self.registers[x] = num & nn;
     }
 
     fn op_bnnn(&mut self, x: usize, nnn: usize) {
         // This op behaves differently in CHIP-8 vs. CHIP-48/SUPER-CHIP.
-        // Original CHIP-8 behavior:
-        // self.program_counter = nnn + self.registers[0] as usize;
-
-        // CHIP-48/SUPER-CHIP:
-        self.program_counter = nnn + self.registers[x] as usize;
+        let offset_register = if self.profile.quirks.jump_v0 { 0 } else { x };
+        self.program_counter = nnn + self.registers[offset_register] as usize;
     }
 
     fn op_annn(&mut self, nnn: usize) {
@@ -344,17 +1305,16 @@ impl Emulator {
 
     fn op_9xy0(&mut self, x: usize, y: usize) {
         if self.registers[x] != self.registers[y] {
-            self.program_counter += 2;
+            self.skip_next();
         }
     }
 
-    fn op_8xye(&mut self, x: usize) {
-        // The following line is a quirk on some systems, and could be made into a toggleable
-        // option to enable a running roms which require this behavior:
-        // self.registers[x] = self.registers[y];
+    fn op_8xye(&mut self, x: usize, y: usize) {
+        if self.profile.quirks.shift_vy {
+            self.registers[x] = self.registers[y];
+        }
         let vf_result = (self.registers[x] >> 7) & 1;
-        self.registers[x] <<= 1;
-        self.registers[0xF] = vf_result;
+        self.store_with_flag(x, self.registers[x] << 1, vf_result);
     }
 
     fn op_8xy7(&mut self, x: usize, y: usize) {
@@ -363,14 +1323,19 @@ impl Emulator {
         } else {
             0
         };
-        self.registers[x] = self.registers[y].wrapping_sub(self.registers[x]);
-        self.registers[0xF] = vf_result;
+        self.store_with_flag(
+            x,
+            self.registers[y].wrapping_sub(self.registers[x]),
+            vf_result,
+        );
     }
 
-    fn op_8xy6(&mut self, x: usize) {
+    fn op_8xy6(&mut self, x: usize, y: usize) {
+        if self.profile.quirks.shift_vy {
+            self.registers[x] = self.registers[y];
+        }
         let vf_result = self.registers[x] & 1;
-        self.registers[x] >>= 1;
-        self.registers[0xF] = vf_result;
+        self.store_with_flag(x, self.registers[x] >> 1, vf_result);
     }
 
     fn op_8xy5(&mut self, x: usize, y: usize) {
@@ -379,29 +1344,49 @@ impl Emulator {
         } else {
             0
         };
-        self.registers[x] = self.registers[x].wrapping_sub(self.registers[y]);
-        self.registers[0xF] = vf_result;
+        self.store_with_flag(
+            x,
+            self.registers[x].wrapping_sub(self.registers[y]),
+            vf_result,
+        );
     }
 
     fn op_8xy4(&mut self, x: usize, y: usize) {
-        let result = self.registers[x] as u16 + self.registers[y] as u16;
-        self.registers[x] = self.registers[x].wrapping_add(self.registers[y]);
-        self.registers[0xF] = if result > 0xFF { 1 } else { 0 };
+        let (result, overflowed) = self.registers[x].overflowing_add(self.registers[y]);
+        self.store_with_flag(x, result, overflowed as u8);
+    }
+
+    /// Stores an arithmetic result in VX and its flag in VF, in the order the vf-before-result
+    /// quirk calls for. The order only matters when X is F.
+    fn store_with_flag(&mut self, x: usize, result: u8, flag: u8) {
+        if self.profile.quirks.vf_before_result {
+            self.registers[0xF] = flag;
+            self.registers[x] = result;
+        } else {
+            self.registers[x] = result;
+            self.registers[0xF] = flag;
+        }
     }
 
     fn op_8xy3(&mut self, x: usize, y: usize) {
         self.registers[x] ^= self.registers[y];
-        // self.registers[0xF] = 0; // Quirk for CHIP-8, could become a toggleable option
+        if self.profile.quirks.vf_reset {
+            self.registers[0xF] = 0;
+        }
     }
 
     fn op_8xy2(&mut self, x: usize, y: usize) {
         self.registers[x] &= self.registers[y];
-        // self.registers[0xF] = 0; // Quirk for CHIP-8, could become a toggleable option
+        if self.profile.quirks.vf_reset {
+            self.registers[0xF] = 0;
+        }
     }
 
     fn op_8xy1(&mut self, x: usize, y: usize) {
         self.registers[x] |= self.registers[y];
-        // self.registers[0xF] = 0; // Quirk for CHIP-8, could become a toggleable option
+        if self.profile.quirks.vf_reset {
+            self.registers[0xF] = 0;
+        }
     }
 
     fn op_8xy0(&mut self, x: usize, y: usize) {
@@ -418,83 +1403,420 @@ impl Emulator {
 
     fn op_5xy0(&mut self, x: usize, y: usize) {
         if self.registers[x] == self.registers[y] {
-            self.program_counter += 2;
+            self.skip_next();
         }
     }
 
     fn op_4xnn(&mut self, x: usize, nn: u8) {
         if self.registers[x] != nn {
-            self.program_counter += 2;
+            self.skip_next();
         }
     }
 
     fn op_3xnn(&mut self, x: usize, nn: u8) {
         if self.registers[x] == nn {
-            self.program_counter += 2;
+            self.skip_next();
         }
     }
 
-    fn op_2nnn(&mut self, nnn: usize) {
+    /// Skips over the next instruction, which takes two words if it's XO-CHIP's F000 NNNN.
+    fn skip_next(&mut self) {
+        let next = self.program_counter;
+        let long = self.profile.xo_chip && self.memory.get(next..next + 2) == Some(&[0xF0, 0x00]);
+        self.program_counter += if long { 4 } else { 2 };
+    }
+
+    fn op_2nnn(&mut self, address: usize, nnn: usize) -> Result<(), RuntimeError> {
+        if let Some(limit) = self.stack_limit
+            && self.stack.len() >= limit
+        {
+            return Err(RuntimeError::StackOverflow { address, limit });
+        }
+
         self.stack.push(self.program_counter as u16);
+        self.max_stack_depth = self.max_stack_depth.max(self.stack.len());
         self.program_counter = nnn;
+        Ok(())
     }
 
     fn op_1nnn(&mut self, nnn: usize) {
         self.program_counter = nnn
     }
 
-    fn op_00ee(&mut self) {
-        self.program_counter = self
-            .stack
-            .pop()
-            .expect("stack should not be empty when returning from subroutine")
-            as usize
+    fn op_00ee(&mut self, address: usize) -> Result<(), RuntimeError> {
+        self.program_counter =
+            self.stack
+                .pop()
+                .ok_or(RuntimeError::StackUnderflow { address })? as usize;
+        Ok(())
     }
 
     fn op_00e0(&mut self) {
-        self.screen.clear()
+        match &mut self.megachip {
+            Some(megachip) => megachip.present(),
+            None => self.display.clear(),
+        }
+    }
+
+    fn op_00bn(&mut self, n: u8) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.scroll(0, -(n as isize));
+        }
     }
 
-    fn op_0nnn(&mut self) {
-        panic!("Attempted to call machine code routine; not implemented.");
+    fn op_00cn(&mut self, n: u8) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.scroll(0, n as isize);
+            return;
+        }
+        let rows = self.scroll_distance(n as usize);
+        self.display.scroll_down(rows);
     }
 
-    fn draw(&mut self, x: u8, y: u8, height: u8) {
-        self.registers[0xF] = 0;
+    fn op_00dn(&mut self, n: u8) {
+        let rows = self.scroll_distance(n as usize);
+        self.display.scroll_up(rows);
+    }
 
-        // Loop through all the "rows" of the sprite
-        for sprite_y in 0..height {
-            if sprite_y + y >= SCREEN_HEIGHT as u8 {
-                return;
+    fn op_00fb(&mut self) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.scroll(4, 0);
+            return;
+        }
+        let columns = self.scroll_distance(4);
+        self.display.scroll_right(columns);
+    }
+
+    fn op_00fc(&mut self) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.scroll(-4, 0);
+            return;
+        }
+        let columns = self.scroll_distance(4);
+        self.display.scroll_left(columns);
+    }
+
+    /// Converts a scroll distance in pixels of the current mode to framebuffer pixels.
+    fn scroll_distance(&self, pixels: usize) -> usize {
+        if self.display.hires() || self.profile.quirks.half_pixel_scroll {
+            pixels
+        } else {
+            pixels * 2
+        }
+    }
+
+    fn op_00fe(&mut self) {
+        self.display.set_mode(DisplayMode::Lores);
+        self.display.clear_all();
+    }
+
+    fn op_00ff(&mut self) {
+        self.display.set_mode(DisplayMode::Hires);
+        self.display.clear_all();
+    }
+
+    fn op_f000(&mut self) -> Result<(), RuntimeError> {
+        let address = self.program_counter;
+        if address + 1 >= self.memory.len() {
+            return Err(RuntimeError::ProgramCounterOutOfBounds { address });
+        }
+
+        self.index_register =
+            u16::from_be_bytes([self.memory[address], self.memory[address + 1]]) as usize;
+        self.program_counter += 2;
+        Ok(())
+    }
+
+    fn op_f002(&mut self) {
+        let mut pattern = [0; AUDIO_PATTERN_BYTES];
+        for (offset, byte) in pattern.iter_mut().enumerate() {
+            *byte = self.read_memory(self.index_register + offset);
+        }
+        self.audio_pattern = Some(pattern);
+    }
+
+    fn op_fx3a(&mut self, x: usize) {
+        self.pitch = self.registers[x];
+    }
+
+    /// Two-page hires CHIP-8 programs begin by jumping to the interpreter patch they carry at
+    /// 0x260, which sets up the 64x64 screen and runs the program proper from 0x2C0. The patch
+    /// is VIP machine code, so its effect is emulated directly instead.
+    fn op_two_page_start(&mut self) {
+        self.display.set_mode(DisplayMode::TwoPage);
+        self.display.clear_all();
+        self.program_counter = TWO_PAGE_START;
+    }
+
+    fn op_0010(&mut self) {
+        self.megachip = None;
+        self.display.reset(self.profile.display_mode());
+    }
+
+    fn op_0011(&mut self) {
+        self.megachip = Some(MegaChip::new());
+    }
+
+    fn op_01nn(&mut self, nn: u8) -> Result<(), RuntimeError> {
+        let address = self.program_counter;
+        if address + 1 >= self.memory.len() {
+            return Err(RuntimeError::ProgramCounterOutOfBounds { address });
+        }
+
+        self.index_register =
+            u32::from_be_bytes([0, nn, self.memory[address], self.memory[address + 1]]) as usize;
+        self.program_counter += 2;
+        Ok(())
+    }
+
+    fn op_02nn(&mut self, nn: u8) {
+        let colors: Vec<u8> = (0..nn as usize * 4)
+            .map(|offset| self.read_memory(self.index_register + offset))
+            .collect();
+        if let Some(megachip) = &mut self.megachip {
+            megachip.load_palette(&colors);
+        }
+    }
+
+    fn op_03nn(&mut self, nn: u8) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.sprite_width = if nn == 0 { 256 } else { nn as usize };
+        }
+    }
+
+    fn op_04nn(&mut self, nn: u8) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.sprite_height = if nn == 0 { 256 } else { nn as usize };
+        }
+    }
+
+    fn op_05nn(&mut self, nn: u8) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.screen_alpha = nn;
+        }
+    }
+
+    /// The sound at I starts with a 6-byte header: the sample rate in 2 bytes, the length in 3
+    /// bytes and a reserved byte, followed by unsigned 8-bit samples.
+    fn op_060n(&mut self, n: u8) {
+        let header: Vec<u8> = (0..6)
+            .map(|offset| self.read_memory(self.index_register + offset))
+            .collect();
+        let sample_rate = u16::from_be_bytes([header[0], header[1]]);
+        let length = u32::from_be_bytes([0, header[2], header[3], header[4]]) as usize;
+        let samples = (0..length)
+            .map(|offset| self.read_memory(self.index_register + 6 + offset))
+            .collect();
+        if let Some(megachip) = &mut self.megachip {
+            megachip.sound = Some(DigitizedSound {
+                sample_rate,
+                samples,
+                looped: n == 0,
+            });
+        }
+    }
+
+    fn op_0700(&mut self) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.sound = None;
+        }
+    }
+
+    fn op_080n(&mut self, n: u8) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.set_blend_mode(n);
+        }
+    }
+
+    fn op_09nn(&mut self, nn: u8) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.collision_index = nn;
+        }
+    }
+
+    /// Draws on the Mega-CHIP screen. Sprites are blocks of palette indices of the size set with
+    /// 03NN and 04NN, except for the font, which is drawn with the usual one-bit rows.
+    fn draw_megachip(&mut self, x: usize, y: usize, n: u8) {
+        let Some(megachip) = &self.megachip else {
+            return;
+        };
+        let font = FONT_LOAD_INDEX..BIG_FONT_LOAD_INDEX + BIG_FONT.len();
+        let glyph = font.contains(&self.index_register);
+        let size = if glyph {
+            n as usize
+        } else {
+            megachip.sprite_width * megachip.sprite_height
+        };
+        let data: Vec<u8> = (0..size)
+            .map(|offset| self.read_memory(self.index_register + offset))
+            .collect();
+
+        let Some(megachip) = &mut self.megachip else {
+            return;
+        };
+        let collided = if glyph {
+            megachip.draw_glyph(x, y, &data)
+        } else {
+            megachip.draw(x, y, &data)
+        };
+        self.registers[0xF] = collided as u8;
+    }
+
+    fn op_fn01(&mut self, n: u8) {
+        self.display.select(n);
+    }
+
+    fn op_unknown(&mut self, address: usize, instruction: u16) -> Result<(), HaltReason> {
+        if self.invoke_handler(|emulator| &mut emulator.unknown_opcode_handler, instruction) {
+            return Ok(());
+        }
+
+        match self.unknown_opcode_policy {
+            UnknownOpcodePolicy::Log => {
+                eprintln!("Unknown opcode {instruction:04X} at {address:#05X}");
+                Ok(())
+            }
+            UnknownOpcodePolicy::Pause => Err(HaltReason::UnknownOpcode {
+                address,
+                instruction,
+            }),
+            UnknownOpcodePolicy::Abort => Err(RuntimeError::UnknownOpcode {
+                address,
+                instruction,
+            }
+            .into()),
+        }
+    }
+
+    fn op_0nnn(&mut self, address: usize, instruction: u16) -> Result<(), RuntimeError> {
+        match self.machine_code_policy {
+            MachineCodePolicy::Ignore => return Ok(()),
+            MachineCodePolicy::Log => {
+                eprintln!("Ignoring machine code call {instruction:04X} at {address:#05X}");
+                return Ok(());
             }
+            MachineCodePolicy::Halt => {}
+            MachineCodePolicy::Handler => {
+                if self.invoke_handler(|emulator| &mut emulator.machine_code_handler, instruction) {
+                    return Ok(());
+                }
+            }
+        }
 
-            // Compute the address of the data and fetch it
-            let address = self.index_register + sprite_y as usize;
-            let sprite_data = self.memory[address];
+        Err(RuntimeError::MachineCodeCall {
+            address,
+            instruction,
+        })
+    }
 
-            // Go through all the bits in the byte of sprite data
-            for sprite_x in 0..8 {
-                let draw_x = x + sprite_x;
-                let draw_y = y + sprite_y;
-                let draw_v = (sprite_data >> (7 - sprite_x)) & 1;
+    /// Draws a `sprite_width` pixel wide sprite (8 or 16) from I, which holds its rows one after
+    /// another. When several planes are selected, each one gets its own copy of the sprite
+    /// data, one after the other.
+    fn draw(&mut self, x: u8, y: u8, sprite_width: usize, height: u8) {
+        let (width, screen_height) = self.display.size();
+        let wrap = self.profile.quirks.wrap;
+        let row_bytes = sprite_width / 8;
+        let sprite_bytes = row_bytes * height as usize;
+        let planes: Vec<usize> = self.display.selected_planes().collect();
+        let mut collided_rows = 0;
+        let mut clipped_rows = 0;
 
-                if draw_x >= SCREEN_WIDTH as u8 {
-                    continue;
+        // Loop through all the "rows" of the sprite
+        for sprite_y in 0..height as usize {
+            let mut draw_y = y as usize + sprite_y;
+            if draw_y >= screen_height {
+                if !wrap {
+                    clipped_rows = height as usize - sprite_y;
+                    break;
                 }
+                draw_y %= screen_height;
+            }
 
-                // Flip the bits based on the sprite data
-                if draw_v == 1 {
-                    let bit = Self::screen_to_flat(draw_x, draw_y);
-                    if self.screen.contains(bit) {
-                        self.screen.remove(bit);
-                        self.registers[0xF] = 1; // on -> off sets VF
-                    } else {
-                        self.screen.insert(bit);
+            let mut row_collided = false;
+            for (index, plane) in planes.iter().enumerate() {
+                // Compute the address of the row's data and fetch it
+                let address = self.index_register + index * sprite_bytes + sprite_y * row_bytes;
+                let sprite_data = (0..row_bytes).fold(0u16, |data, byte| {
+                    (data << 8) | self.read_memory(address + byte) as u16
+                });
+
+                // Go through all the bits in the row of sprite data, flipping the set ones
+                for sprite_x in 0..sprite_width {
+                    if (sprite_data >> (sprite_width - 1 - sprite_x)) & 1 == 0 {
+                        continue;
+                    }
+
+                    let mut draw_x = x as usize + sprite_x;
+                    if draw_x >= width {
+                        if !wrap {
+                            continue;
+                        }
+                        draw_x %= width;
                     }
-                } else if draw_v != 0 {
-                    panic!("Invalid draw value in draw.");
+
+                    // on -> off counts as a collision
+                    row_collided |= self.display.toggle(*plane, draw_x, draw_y);
                 }
             }
+            collided_rows += row_collided as usize;
+        }
+
+        self.registers[0xF] = if self.profile.quirks.row_collisions && self.display.hires() {
+            (collided_rows + clipped_rows) as u8
+        } else {
+            (collided_rows > 0) as u8
+        };
+    }
+
+    /// Calls the handler in the slot chosen by `select`, returning whether it handled the opcode.
+    fn invoke_handler(
+        &mut self,
+        select: fn(&mut Emulator) -> &mut Option<OpcodeHandler>,
+        instruction: u16,
+    ) -> bool {
+        let Some(mut handler) = select(self).take() else {
+            return false;
+        };
+
+        let handled = handler(self, instruction);
+        // Keep any replacement the handler registered while it ran
+        select(self).get_or_insert(handler);
+        handled
+    }
+
+    /// Reads a byte for an instruction. Addresses wrap around at the end of memory, so that
+    /// XO-CHIP programs can use all 16 bits of I.
+    fn read_memory(&mut self, address: usize) -> u8 {
+        let address = address % self.memory.len();
+        let value = match &self.mmio {
+            Some(mmio) if mmio.contains(address) => mmio.read(address),
+            _ => self.memory[address],
+        };
+        self.notify_watches(AccessKind::Read, address, value);
+        value
+    }
+
+    /// Writes a byte for an instruction, wrapping around like [`Emulator::read_memory`].
+    fn write_memory(&mut self, address: usize, value: u8) {
+        let address = address % self.memory.len();
+        if !self
+            .mmio
+            .as_ref()
+            .is_some_and(|mmio| mmio.contains(address))
+        {
+            self.memory[address] = value;
+        }
+        self.notify_watches(AccessKind::Write, address, value);
+    }
+
+    fn notify_watches(&mut self, kind: AccessKind, address: usize, value: u8) {
+        if !self.watches.is_empty() {
+            self.watches.notify(&MemoryAccess {
+                kind,
+                address,
+                value,
+                program_counter: self.instruction_address,
+            });
         }
     }
 
@@ -507,58 +1829,6 @@ impl Emulator {
 
         None
     }
-
-    fn screen_to_flat(x: u8, y: u8) -> usize {
-        (y as usize * SCREEN_WIDTH) + x as usize
-    }
-
-    fn flat_to_screen(bit: usize) -> (u8, u8) {
-        ((bit % SCREEN_WIDTH) as u8, (bit / SCREEN_WIDTH) as u8)
-    }
-
-    fn keycode_to_key_value(keycode: KeyCode) -> Option<u8> {
-        match keycode {
-            KeyCode::Key1 => Some(0x1),
-            KeyCode::Key2 => Some(0x2),
-            KeyCode::Key3 => Some(0x3),
-            KeyCode::Key4 => Some(0xC),
-            KeyCode::Q => Some(0x4),
-            KeyCode::W => Some(0x5),
-            KeyCode::E => Some(0x6),
-            KeyCode::R => Some(0xD),
-            KeyCode::A => Some(0x7),
-            KeyCode::S => Some(0x8),
-            KeyCode::D => Some(0x9),
-            KeyCode::F => Some(0xE),
-            KeyCode::Z => Some(0xA),
-            KeyCode::X => Some(0x0),
-            KeyCode::C => Some(0xB),
-            KeyCode::V => Some(0xF),
-            _ => None,
-        }
-    }
-
-    fn key_value_to_keycode(key_value: u8) -> Option<KeyCode> {
-        match key_value {
-            0x1 => Some(KeyCode::Key1),
-            0x2 => Some(KeyCode::Key2),
-            0x3 => Some(KeyCode::Key3),
-            0xC => Some(KeyCode::Key4),
-            0x4 => Some(KeyCode::Q),
-            0x5 => Some(KeyCode::W),
-            0x6 => Some(KeyCode::E),
-            0xD => Some(KeyCode::R),
-            0x7 => Some(KeyCode::A),
-            0x8 => Some(KeyCode::S),
-            0x9 => Some(KeyCode::D),
-            0xE => Some(KeyCode::F),
-            0xA => Some(KeyCode::Z),
-            0x0 => Some(KeyCode::X),
-            0xB => Some(KeyCode::C),
-            0xF => Some(KeyCode::V),
-            _ => None,
-        }
-    }
 }
 
 impl Default for Emulator {
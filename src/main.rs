@@ -1,34 +1,230 @@
-use std::env;
+mod cli;
 
-use hachi_emu::Emulator;
+use std::{env, path::PathBuf};
+
+use hachi_emu::{
+    Config, Emulator, Frontend, HaltReason, HotkeyAction, Movie, RomDatabase, SPLASH_ROM, Theme,
+};
 use macroquad::prelude::*;
 
 fn conf() -> Conf {
+    // Errors are reported once the window is open and main parses everything properly
+    let options = cli::parse(env::args().skip(1)).ok().flatten();
+    let window = options
+        .as_ref()
+        .and_then(|options| options.config_path.clone())
+        .or_else(Config::default_path)
+        .and_then(|path| Config::load(&path).ok())
+        .unwrap_or_default()
+        .window;
+
+    // The title can only be set when the window is created, so whether the emulator is paused
+    // or recording, and ROMs dropped later, are shown in the window instead
+    let rom_name = options
+        .and_then(|options| options.rom_path)
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        });
+    let window_title = match rom_name {
+        Some(rom_name) => format!("{rom_name} - HachiEmu"),
+        None => String::from("HachiEmu"),
+    };
+
     Conf {
-        window_title: String::from("HachiEmu"),
-        window_width: 64 * 12,
-        window_height: 32 * 12,
-        window_resizable: false,
+        window_title,
+        window_width: window.width as i32,
+        window_height: window.height as i32,
+        window_resizable: true,
+        // Sizes are in logical pixels, with the screen drawn at the display's full resolution
+        high_dpi: true,
+        fullscreen: window.fullscreen,
         ..Default::default()
     }
 }
 
 #[macroquad::main(conf)]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args = env::args();
+    let program = args.next().unwrap_or_else(|| String::from("hachi_emu"));
+
+    let options = match cli::parse(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::usage(&program));
+            return;
+        }
+        Err(error) => {
+            eprintln!("{error}\n\n{}", cli::usage(&program));
+            std::process::exit(2);
+        }
+    };
+
+    let rom_name = match &options.rom_path {
+        Some(rom_path) => rom_path.display().to_string(),
+        None => String::from("the splash screen"),
+    };
+
+    let (config_path, mut config) = load_config(options.config_path.clone());
+
+    if let Some(index) = options.monitor {
+        match config.window.monitors.get(index) {
+            Some(position) => config.window.position = Some(*position),
+            None => eprintln!(
+                "Monitor {index} isn't listed in the config's [window] monitors, \
+                 opening the window at its saved position"
+            ),
+        }
+    }
+
+    if let Some(path) = &options.theme {
+        match Theme::load(path) {
+            Ok(theme) => theme.apply(&mut config.display),
+            Err(error) => {
+                eprintln!("Failed to load {}: {error}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let program = match &options.rom_path {
+        Some(rom_path) => match std::fs::read(rom_path) {
+            Ok(program) => program,
+            Err(error) => {
+                eprintln!("Failed to read {rom_name}: {error}");
+                std::process::exit(1);
+            }
+        },
+        None => SPLASH_ROM.to_vec(),
+    };
 
-    if args.len() != 2 {
-        println!("Usage: {} <rom-file>", args[0]);
-        return;
+    let rom_db = if options.uses_rom_db() {
+        RomDatabase::with_user_entries().unwrap_or_else(|error| {
+            eprintln!("Failed to load the user ROM database: {error}");
+            RomDatabase::bundled()
+        })
+    } else {
+        RomDatabase::default()
+    };
+    let known = rom_db.lookup(&program);
+    if let Some(known) = known {
+        println!(
+            "Recognized {}; running it as {} (use --variant or --no-rom-db to override).",
+            known.title, known.variant
+        );
+    }
+    // A ROM's own colors and those from the command line are only used for this session
+    let mut palette = known
+        .and_then(|known| known.palette)
+        .unwrap_or(config.display.palette);
+    if let Some(preset) = options.palette {
+        palette = preset.colors();
     }
+    palette[0] = options.background.unwrap_or(palette[0]);
+    palette[1] = options.foreground.unwrap_or(palette[1]);
+    let palette_override = (palette != config.display.palette).then_some(palette);
 
-    let rom_name = &args[1];
+    let profile = match options.profile(known, &config.emulation) {
+        Ok(profile) => profile,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
 
-    let mut emulator = Emulator::new();
+    let mut emulator = Emulator::new().with_profile(profile);
+    emulator.set_machine_code_policy(options.machine_code);
+    emulator.set_unknown_opcode_policy(options.unknown_opcodes);
+    emulator.set_stack_limit(options.stack_limit);
     emulator.load_font(&hachi_emu::STANDARD_FONT);
+    emulator.load_big_font(&hachi_emu::BIG_FONT);
+
+    if let Some(base) = options.mmio_base
+        && let Err(error) = emulator.enable_mmio(base)
+    {
+        eprintln!("Failed to enable memory-mapped I/O: {error}");
+        std::process::exit(2);
+    }
+
+    if let Err(error) = emulator.load_program(&program) {
+        eprintln!("Failed to load {rom_name}: {error}");
+        std::process::exit(1);
+    }
+
+    if options.canaries {
+        emulator.set_canaries(true);
+    }
 
-    let program = std::fs::read(rom_name).unwrap();
-    emulator.load_program(&program);
+    // Movies bring the profile they were recorded with, replacing the one chosen above
+    let movie = options.movie.as_ref().map(|path| {
+        let result = Movie::load(path)
+            .and_then(|movie| movie.restart(&mut emulator, &program).map(|()| movie));
+        result.unwrap_or_else(|error| {
+            eprintln!("Failed to play {}: {error}", path.display());
+            std::process::exit(1);
+        })
+    });
+
+    if let Some(report) = emulator.rom_report() {
+        print!("{report}");
+        if let Some(binding) = config.hotkeys.bindings(HotkeyAction::InfoPanel).first() {
+            println!("Press {binding} to show this report in the emulator window.");
+        }
+    }
+
+    let audio_override = known.and_then(|known| known.audio(&config.audio));
+    let muted = options.mute || known.is_some_and(|known| known.mute);
+
+    let mut frontend = Frontend::new(config)
+        .with_muted(muted)
+        .with_rom_db(options.uses_rom_db());
+    if let Some(audio) = audio_override {
+        frontend = frontend.with_audio(audio);
+    }
+    if let Some(palette) = palette_override {
+        frontend = frontend.with_palette(palette);
+    }
+    if let Some(keymap) = known.and_then(|known| known.keymap.clone()) {
+        frontend = frontend.with_keymap(keymap);
+    }
+    if let Some(keys) = known.and_then(|known| known.stick) {
+        frontend = frontend.with_stick(keys);
+    }
+    if let Some(path) = options.theme {
+        frontend = frontend.with_theme_path(path);
+    }
+    if let Some(movie) = movie {
+        frontend = frontend.with_movie(movie);
+    }
+    if let Some(rom_path) = options.rom_path {
+        frontend = frontend.with_rom_path(rom_path);
+    }
+    if let Some(path) = config_path {
+        frontend = frontend.with_config_path(path);
+    }
+
+    let reason = frontend.run(&mut emulator).await;
+    match &reason {
+        HaltReason::UserQuit | HaltReason::Exit => {}
+        HaltReason::Error(error) => eprintln!("Emulation stopped: {error}"),
+        other => println!("Emulation stopped: {other:?}"),
+    }
+
+    if options.canaries {
+        print!("{}", emulator.usage());
+    }
+
+    std::process::exit(reason.exit_code());
+}
 
-    emulator.run().await;
+fn load_config(path: Option<PathBuf>) -> (Option<PathBuf>, Config) {
+    let path = path.or_else(Config::default_path);
+    let config = match &path {
+        Some(path) => Config::load(path).unwrap_or_else(|error| {
+            eprintln!("Failed to load {}: {error}", path.display());
+            Config::default()
+        }),
+        None => Config::default(),
+    };
+    (path, config)
 }
@@ -0,0 +1,253 @@
+/// Width of the Mega-CHIP screen.
+pub const MEGACHIP_WIDTH: usize = 256;
+/// Height of the Mega-CHIP screen.
+pub const MEGACHIP_HEIGHT: usize = 192;
+/// Bytes of memory addressable by Mega-CHIP's 24-bit I register.
+pub(crate) const MEGACHIP_MEMORY_BYTES: usize = 0x100_0000;
+
+/// An RGBA color.
+pub type Rgba = [u8; 4];
+
+/// How Mega-CHIP sprite pixels are combined with what's already on screen, set by 080N.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    /// The sprite is drawn at 25% opacity.
+    Quarter,
+    /// The sprite is drawn at 50% opacity.
+    Half,
+    /// The sprite is drawn at 75% opacity.
+    ThreeQuarters,
+    Add,
+    Multiply,
+}
+
+impl BlendMode {
+    fn from_nibble(n: u8) -> BlendMode {
+        match n {
+            1 => BlendMode::Quarter,
+            2 => BlendMode::Half,
+            3 => BlendMode::ThreeQuarters,
+            4 => BlendMode::Add,
+            5 => BlendMode::Multiply,
+            _ => BlendMode::Normal,
+        }
+    }
+
+    fn apply(self, source: Rgba, destination: Rgba) -> Rgba {
+        let mix = |amount: u16| {
+            std::array::from_fn(|channel| {
+                ((source[channel] as u16 * amount + destination[channel] as u16 * (4 - amount)) / 4)
+                    as u8
+            })
+        };
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Quarter => mix(1),
+            BlendMode::Half => mix(2),
+            BlendMode::ThreeQuarters => mix(3),
+            BlendMode::Add => {
+                std::array::from_fn(|channel| source[channel].saturating_add(destination[channel]))
+            }
+            BlendMode::Multiply => std::array::from_fn(|channel| {
+                (source[channel] as u16 * destination[channel] as u16 / 255) as u8
+            }),
+        }
+    }
+}
+
+/// A digitized sound started by 060N.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigitizedSound {
+    pub sample_rate: u16,
+    /// Unsigned 8-bit mono samples.
+    pub samples: Vec<u8>,
+    pub looped: bool,
+}
+
+/// The state of Mega-CHIP mode, which replaces the usual screen with a 256x192 one where each
+/// pixel is a color from a palette the program loads.
+///
+/// Sprites are drawn to a back buffer, which becomes visible when the program executes 00E0.
+#[derive(Clone, Debug)]
+pub(crate) struct MegaChip {
+    pub palette: [Rgba; 256],
+    pub sprite_width: usize,
+    pub sprite_height: usize,
+    /// Opacity of the whole screen, set by 05NN.
+    pub screen_alpha: u8,
+    pub blend_mode: BlendMode,
+    /// Drawing over a pixel of this palette index sets VF. Until the program sets one with 09NN
+    /// it's 0, the transparent index, and nothing collides.
+    pub collision_index: u8,
+    pub sound: Option<DigitizedSound>,
+    /// The palette index last drawn at each pixel, used for collisions.
+    indices: Vec<u8>,
+    back: Vec<Rgba>,
+    front: Vec<Rgba>,
+}
+
+impl MegaChip {
+    pub fn new() -> MegaChip {
+        MegaChip {
+            palette: [[0; 4]; 256],
+            sprite_width: 0,
+            sprite_height: 0,
+            screen_alpha: 255,
+            blend_mode: BlendMode::Normal,
+            collision_index: 0,
+            sound: None,
+            indices: vec![0; MEGACHIP_WIDTH * MEGACHIP_HEIGHT],
+            back: vec![[0, 0, 0, 255]; MEGACHIP_WIDTH * MEGACHIP_HEIGHT],
+            front: vec![[0, 0, 0, 255]; MEGACHIP_WIDTH * MEGACHIP_HEIGHT],
+        }
+    }
+
+    /// The frame shown by the last 00E0, row by row, faded by the screen alpha set when it was
+    /// shown. The colors are opaque; the alpha only darkens them towards black.
+    pub fn frame(&self) -> &[Rgba] {
+        &self.front
+    }
+
+    /// Shows the back buffer and clears it for the next frame.
+    pub fn present(&mut self) {
+        let alpha = self.screen_alpha as u16;
+        for (front, back) in self.front.iter_mut().zip(&self.back) {
+            *front = [
+                (back[0] as u16 * alpha / 255) as u8,
+                (back[1] as u16 * alpha / 255) as u8,
+                (back[2] as u16 * alpha / 255) as u8,
+                255,
+            ];
+        }
+        self.back.fill([0, 0, 0, 255]);
+        self.indices.fill(0);
+    }
+
+    /// Loads ARGB colors into the palette from index 1; index 0 is transparent.
+    pub fn load_palette(&mut self, argb: &[u8]) {
+        for (index, color) in argb.chunks_exact(4).enumerate().take(255) {
+            self.palette[index + 1] = [color[1], color[2], color[3], color[0]];
+        }
+    }
+
+    pub fn set_blend_mode(&mut self, n: u8) {
+        self.blend_mode = BlendMode::from_nibble(n);
+    }
+
+    /// Draws a sprite of palette indices, one byte per pixel, clipping it at the screen edges.
+    /// Index 0 is transparent. Returns whether it drew over the collision color, if one is set.
+    pub fn draw(&mut self, x: usize, y: usize, pixels: &[u8]) -> bool {
+        let mut collided = false;
+        for (offset, index) in pixels.iter().enumerate() {
+            let draw_x = x + offset % self.sprite_width.max(1);
+            let draw_y = y + offset / self.sprite_width.max(1);
+            if *index == 0 || draw_x >= MEGACHIP_WIDTH || draw_y >= MEGACHIP_HEIGHT {
+                continue;
+            }
+
+            let pixel = draw_y * MEGACHIP_WIDTH + draw_x;
+            collided |= self.collides(pixel);
+            self.indices[pixel] = *index;
+            self.back[pixel] = self
+                .blend_mode
+                .apply(self.palette[*index as usize], self.back[pixel]);
+        }
+        collided
+    }
+
+    /// Draws a one-bit font glyph, eight pixels wide, in the last palette color.
+    pub fn draw_glyph(&mut self, x: usize, y: usize, rows: &[u8]) -> bool {
+        let mut collided = false;
+        for (row_offset, row) in rows.iter().enumerate() {
+            for column in 0..8 {
+                let (draw_x, draw_y) = (x + column, y + row_offset);
+                if (row >> (7 - column)) & 1 == 0
+                    || draw_x >= MEGACHIP_WIDTH
+                    || draw_y >= MEGACHIP_HEIGHT
+                {
+                    continue;
+                }
+
+                let pixel = draw_y * MEGACHIP_WIDTH + draw_x;
+                collided |= self.collides(pixel);
+                self.indices[pixel] = 255;
+                self.back[pixel] = self.palette[255];
+            }
+        }
+        collided
+    }
+
+    /// Whether drawing at `pixel` would be drawing over the collision color.
+    fn collides(&self, pixel: usize) -> bool {
+        self.collision_index != 0 && self.indices[pixel] == self.collision_index
+    }
+
+    /// Moves the back buffer by (`dx`, `dy`) pixels, filling the uncovered area with black.
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let mut indices = vec![0; self.indices.len()];
+        let mut back = vec![[0, 0, 0, 255]; self.back.len()];
+        for y in 0..MEGACHIP_HEIGHT {
+            for x in 0..MEGACHIP_WIDTH {
+                let (to_x, to_y) = (x as isize + dx, y as isize + dy);
+                if (0..MEGACHIP_WIDTH as isize).contains(&to_x)
+                    && (0..MEGACHIP_HEIGHT as isize).contains(&to_y)
+                {
+                    let to = to_y as usize * MEGACHIP_WIDTH + to_x as usize;
+                    indices[to] = self.indices[y * MEGACHIP_WIDTH + x];
+                    back[to] = self.back[y * MEGACHIP_WIDTH + x];
+                }
+            }
+        }
+        self.indices = indices;
+        self.back = back;
+    }
+
+    /// Serializes the state for snapshots.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for color in &self.palette {
+            bytes.extend_from_slice(color);
+        }
+        bytes.extend_from_slice(&(self.sprite_width as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.sprite_height as u16).to_le_bytes());
+        bytes.push(self.screen_alpha);
+        bytes.push(self.blend_mode as u8);
+        bytes.push(self.collision_index);
+        bytes.extend_from_slice(&self.indices);
+        for color in self.back.iter().chain(&self.front) {
+            bytes.extend_from_slice(color);
+        }
+        bytes
+    }
+
+    /// Restores state serialized with [`MegaChip::to_bytes`]. Digitized sound isn't restored.
+    pub fn from_bytes(bytes: &[u8]) -> Option<MegaChip> {
+        let pixels = MEGACHIP_WIDTH * MEGACHIP_HEIGHT;
+        if bytes.len() != 256 * 4 + 7 + pixels * 9 {
+            return None;
+        }
+
+        let mut megachip = MegaChip::new();
+        let (palette, rest) = bytes.split_at(256 * 4);
+        for (color, bytes) in megachip.palette.iter_mut().zip(palette.chunks_exact(4)) {
+            color.copy_from_slice(bytes);
+        }
+        megachip.sprite_width = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+        megachip.sprite_height = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        megachip.screen_alpha = rest[4];
+        megachip.blend_mode = BlendMode::from_nibble(rest[5]);
+        megachip.collision_index = rest[6];
+        let (indices, colors) = rest[7..].split_at(pixels);
+        megachip.indices.copy_from_slice(indices);
+        let (back, front) = colors.split_at(pixels * 4);
+        for (color, bytes) in megachip.back.iter_mut().zip(back.chunks_exact(4)) {
+            color.copy_from_slice(bytes);
+        }
+        for (color, bytes) in megachip.front.iter_mut().zip(front.chunks_exact(4)) {
+            color.copy_from_slice(bytes);
+        }
+        Some(megachip)
+    }
+}
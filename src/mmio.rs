@@ -0,0 +1,58 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of bytes mapped when memory-mapped I/O is enabled.
+pub const MMIO_BYTES: usize = 8;
+
+/// The state behind the memory-mapped I/O window, see
+/// [`Emulator::enable_mmio`](crate::Emulator::enable_mmio).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Mmio {
+    pub base: usize,
+    pub frames: u8,
+    pub pointer: Pointer,
+}
+
+/// The pointer state reported to ROMs when memory-mapped I/O is enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pointer {
+    pub x: u8,
+    pub y: u8,
+    /// Bit 0 is the left button, bit 1 the right and bit 2 the middle.
+    pub buttons: u8,
+}
+
+impl Mmio {
+    pub fn new(base: usize) -> Mmio {
+        Mmio {
+            base,
+            frames: 0,
+            pointer: Pointer::default(),
+        }
+    }
+
+    pub fn contains(&self, address: usize) -> bool {
+        (self.base..self.base + MMIO_BYTES).contains(&address)
+    }
+
+    pub fn read(&self, address: usize) -> u8 {
+        match address - self.base {
+            0..=2 => {
+                let seconds = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|time| time.as_secs() % 86400)
+                    .unwrap_or(0);
+                let value = match address - self.base {
+                    0 => seconds / 3600,
+                    1 => seconds / 60 % 60,
+                    _ => seconds % 60,
+                };
+                value as u8
+            }
+            3 => self.frames,
+            4 => self.pointer.x,
+            5 => self.pointer.y,
+            6 => self.pointer.buttons,
+            _ => 0,
+        }
+    }
+}
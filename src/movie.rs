@@ -0,0 +1,155 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use sha1_smol::Sha1;
+
+use crate::{Emulator, HaltReason, NUM_INPUT_KEYS, RPL_FLAGS, error::MovieError, profile::Profile};
+
+/// The extension movie files are saved with.
+pub const MOVIE_EXTENSION: &str = "movie.toml";
+
+/// A recording of the keypad during a windowed session. Replaying it from a restart with the
+/// same profile, RPL flags and seed, as [`Movie::restart`] does, feeds the emulator the same keys
+/// over the same stretches of time, so it plays out exactly as recorded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Movie {
+    /// SHA-1 of the ROM the movie was recorded on.
+    pub rom_sha1: String,
+    /// Seed for the CXNN random number generator.
+    pub seed: u64,
+    /// The platform the movie was recorded on: the variant's quirks, timing and extensions, along
+    /// with any quirks changed on top of them.
+    pub profile: Profile,
+    /// The RPL flags when recording started, which the ROM may have saved in earlier sessions.
+    pub rpl_flags: [u8; RPL_FLAGS],
+    /// How far each update advanced emulated time, in seconds.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frame_times: Vec<f32>,
+    /// Changes to the held keys, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<MovieInput>,
+}
+
+/// From update `frame` onwards, exactly `keys` are held.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovieInput {
+    pub frame: u64,
+    pub keys: Vec<u8>,
+}
+
+impl Movie {
+    /// An empty movie of `rom` running on `emulator`, which should have just been restarted with
+    /// `seed` before recording.
+    pub fn new(rom: &[u8], emulator: &Emulator, seed: u64) -> Movie {
+        Movie {
+            rom_sha1: sha1(rom),
+            seed,
+            profile: *emulator.profile(),
+            rpl_flags: emulator.rpl_flags(),
+            frame_times: Vec::new(),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Movie, MovieError> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MovieError> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Checks that the movie was recorded on `rom`.
+    pub fn check_rom(&self, rom: &[u8]) -> Result<(), MovieError> {
+        let actual = sha1(rom);
+        if actual != self.rom_sha1 {
+            return Err(MovieError::RomMismatch {
+                expected: self.rom_sha1.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Restarts `emulator` on `rom` as the movie was recorded, with its profile, RPL flags and
+    /// seed, ready to play it back. Fails if `rom` isn't the ROM it was recorded on.
+    pub fn restart(&self, emulator: &mut Emulator, rom: &[u8]) -> Result<(), MovieError> {
+        self.check_rom(rom)?;
+        emulator.set_profile(self.profile);
+        emulator.set_rpl_flags(self.rpl_flags);
+        emulator.restart(rom, self.seed)?;
+        Ok(())
+    }
+
+    /// Adds an update that advanced time by `frame_time` with `keys` held.
+    pub fn record(&mut self, frame_time: f32, keys: &[bool; NUM_INPUT_KEYS]) {
+        let keys: Vec<u8> = (0..NUM_INPUT_KEYS as u8)
+            .filter(|key| keys[*key as usize])
+            .collect();
+        let held = self.inputs.last().map_or(&[][..], |input| &input.keys);
+        if keys != held {
+            self.inputs.push(MovieInput {
+                frame: self.frame_times.len() as u64,
+                keys,
+            });
+        }
+        self.frame_times.push(frame_time);
+    }
+
+    /// Plays the movie back update by update.
+    pub fn play(self) -> MoviePlayback {
+        MoviePlayback {
+            movie: self,
+            frame: 0,
+            input: 0,
+            keys: [false; NUM_INPUT_KEYS],
+        }
+    }
+
+    /// Plays the movie back on `emulator` without a window. It should have been restarted on the
+    /// movie's ROM with [`Movie::restart`]. The replay stops early if the emulator halts.
+    pub fn replay(&self, emulator: &mut Emulator) -> Result<(), HaltReason> {
+        for (frame_time, keys) in self.clone().play() {
+            emulator.key_states = keys;
+            emulator.update(frame_time)?;
+        }
+        Ok(())
+    }
+}
+
+/// A movie being played back, giving each update's time and the keys held during it.
+pub struct MoviePlayback {
+    movie: Movie,
+    frame: usize,
+    input: usize,
+    keys: [bool; NUM_INPUT_KEYS],
+}
+
+impl Iterator for MoviePlayback {
+    type Item = (f32, [bool; NUM_INPUT_KEYS]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame_time = *self.movie.frame_times.get(self.frame)?;
+        while let Some(input) = self
+            .movie
+            .inputs
+            .get(self.input)
+            .filter(|input| input.frame <= self.frame as u64)
+        {
+            self.keys = [false; NUM_INPUT_KEYS];
+            for key in &input.keys {
+                if let Some(state) = self.keys.get_mut(*key as usize) {
+                    *state = true;
+                }
+            }
+            self.input += 1;
+        }
+        self.frame += 1;
+        Some((frame_time, self.keys))
+    }
+}
+
+fn sha1(data: &[u8]) -> String {
+    Sha1::from(data).digest().to_string()
+}
@@ -0,0 +1,105 @@
+use std::{fmt, str::FromStr};
+
+use crate::error::UnknownPolicyError;
+
+/// What to do when a program calls a 0NNN machine code routine, which can't be run since the
+/// routine would be code for the original machine's CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MachineCodePolicy {
+    /// Carry on as if the call returned immediately.
+    Ignore,
+    /// Print the call and carry on.
+    Log,
+    /// Stop with [`RuntimeError::MachineCodeCall`](crate::RuntimeError::MachineCodeCall).
+    #[default]
+    Halt,
+    /// Pass the call to the handler registered with
+    /// [`Emulator::set_machine_code_handler`](crate::Emulator::set_machine_code_handler),
+    /// halting if there isn't one or it doesn't handle the call.
+    Handler,
+}
+
+impl MachineCodePolicy {
+    /// The policies that can be chosen on the command line.
+    pub const ALL: [MachineCodePolicy; 3] = [
+        MachineCodePolicy::Ignore,
+        MachineCodePolicy::Log,
+        MachineCodePolicy::Halt,
+    ];
+
+    /// The name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            MachineCodePolicy::Ignore => "ignore",
+            MachineCodePolicy::Log => "log",
+            MachineCodePolicy::Halt => "halt",
+            MachineCodePolicy::Handler => "handler",
+        }
+    }
+}
+
+impl fmt::Display for MachineCodePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for MachineCodePolicy {
+    type Err = UnknownPolicyError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        MachineCodePolicy::ALL
+            .into_iter()
+            .find(|policy| policy.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownPolicyError(name.to_string()))
+    }
+}
+
+/// What to do when the program executes an opcode the interpreter doesn't recognize and no
+/// registered handler takes care of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownOpcodePolicy {
+    /// Print the opcode and carry on with the next instruction.
+    #[default]
+    Log,
+    /// Stop with [`HaltReason::UnknownOpcode`](crate::HaltReason::UnknownOpcode), so that the
+    /// frontend pauses and the program's state can be inspected. Execution resumes after the
+    /// opcode.
+    Pause,
+    /// Stop with [`RuntimeError::UnknownOpcode`](crate::RuntimeError::UnknownOpcode).
+    Abort,
+}
+
+impl UnknownOpcodePolicy {
+    pub const ALL: [UnknownOpcodePolicy; 3] = [
+        UnknownOpcodePolicy::Log,
+        UnknownOpcodePolicy::Pause,
+        UnknownOpcodePolicy::Abort,
+    ];
+
+    /// The name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            UnknownOpcodePolicy::Log => "log",
+            UnknownOpcodePolicy::Pause => "pause",
+            UnknownOpcodePolicy::Abort => "abort",
+        }
+    }
+}
+
+impl fmt::Display for UnknownOpcodePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for UnknownOpcodePolicy {
+    type Err = UnknownPolicyError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        UnknownOpcodePolicy::ALL
+            .into_iter()
+            .find(|policy| policy.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownPolicyError(name.to_string()))
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Chip8Variant, DisplayMode, ETI_660_LOAD_INDEX, MEMORY_BYTES, ROM_LOAD_INDEX,
+    XO_CHIP_MEMORY_BYTES, megachip::MEGACHIP_MEMORY_BYTES, quirks::Quirks, rng::RngAlgorithm,
+    timing::Timing,
+};
+
+/// Describes the platform being emulated, covering guest-visible behavior that differs between
+/// the various CHIP-8 interpreters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub rng: RngAlgorithm,
+    pub quirks: Quirks,
+    pub timing: Timing,
+    /// Enables SUPER-CHIP's 00CN, 00FB, 00FC, 00FD, 00FE and 00FF, which the original
+    /// interpreters would instead run as machine code calls.
+    pub super_chip: bool,
+    /// Enables the XO-CHIP extensions: 64KB of memory, a second display plane, audio patterns
+    /// and the instructions that go with them.
+    pub xo_chip: bool,
+    /// Recognizes programs for the two-page hires CHIP-8 interpreter, which start with 1260,
+    /// and gives them its 64x64 screen.
+    pub two_page_hires: bool,
+    /// Enables the Mega-CHIP extensions: 16MB of memory addressed by a 24-bit I, and a 256x192
+    /// screen with a loadable palette, switched on by 0011.
+    pub megachip: bool,
+    /// Uses the ETI-660's layout: programs load at 0x600 and the screen is 64x48.
+    pub eti_660: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Chip8Variant::default().profile()
+    }
+}
+
+impl Profile {
+    /// Bytes of memory the guest can address.
+    pub fn memory_size(&self) -> usize {
+        if self.megachip {
+            MEGACHIP_MEMORY_BYTES
+        } else if self.xo_chip {
+            XO_CHIP_MEMORY_BYTES
+        } else {
+            MEMORY_BYTES
+        }
+    }
+
+    /// The address programs are loaded and started at.
+    pub fn load_address(&self) -> usize {
+        if self.eti_660 {
+            ETI_660_LOAD_INDEX
+        } else {
+            ROM_LOAD_INDEX
+        }
+    }
+
+    /// The screen geometry programs start with.
+    pub fn display_mode(&self) -> DisplayMode {
+        if self.eti_660 {
+            DisplayMode::Eti660
+        } else {
+            DisplayMode::Lores
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+
+use crate::{Chip8Variant, error::UnknownQuirkError};
+
+/// Toggles for the instruction behaviors that differ between CHIP-8 interpreters. The defaults
+/// match SUPER-CHIP.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0, as on the original COSMAC VIP interpreter.
+    pub vf_reset: bool,
+    /// 8XY6 and 8XYE copy VY into VX before shifting, as on the original COSMAC VIP
+    /// interpreter, rather than shifting VX in place.
+    pub shift_vy: bool,
+    /// FX55 and FX65 leave I pointing past the last register they stored or loaded, i.e.
+    /// incremented by X + 1, as on the original COSMAC VIP interpreter.
+    pub memory_increment: bool,
+    /// With `memory_increment`, FX55 and FX65 increment I by X rather than X + 1, as CHIP-48
+    /// does due to an off-by-one in its implementation.
+    pub memory_increment_by_x: bool,
+    /// BNNN jumps to NNN + V0, as on the original COSMAC VIP interpreter, rather than CHIP-48
+    /// and SUPER-CHIP's BXNN, which jumps to XNN + VX.
+    pub jump_v0: bool,
+    /// DXYN waits for the next 60Hz display refresh, limiting programs to one sprite draw per
+    /// frame, as on the original COSMAC VIP interpreter.
+    pub display_wait: bool,
+    /// Sprite pixels past the right or bottom edge of the screen wrap around to the opposite
+    /// edge instead of being clipped.
+    pub wrap: bool,
+    /// In hires mode, DXYN sets VF to the number of sprite rows which collided or were clipped
+    /// at the bottom of the screen, as SUPER-CHIP 1.1 does, rather than just to 1.
+    pub row_collisions: bool,
+    /// In lores mode, 00CN, 00FB and 00FC scroll by framebuffer pixels, i.e. half a lores pixel
+    /// each, as SUPER-CHIP 1.1 does, rather than by whole lores pixels.
+    pub half_pixel_scroll: bool,
+    /// 8XY4 to 8XY7, 8XY6 and 8XYE set VF before storing their result, so that the result
+    /// rather than the flag is kept when X is F, as in Octo with `vfOrderQuirks`.
+    pub vf_before_result: bool,
+    /// FX1E sets VF to 1 when I + VX goes past 0xFFF and to 0 otherwise, as the Amiga
+    /// interpreter did. Spacefight 2091! relies on this.
+    pub index_overflow: bool,
+    /// FX0A only lets execution continue once the key is released again, as on the original
+    /// COSMAC VIP interpreter, rather than as soon as it's pressed. Turning this off makes a key
+    /// that's held down satisfy every FX0A in turn.
+    pub key_release: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Chip8Variant::default().quirks()
+    }
+}
+
+impl Quirks {
+    /// The names accepted by [`Quirks::set`], as used on the command line.
+    pub const NAMES: &[&str] = &[
+        "vf-reset",
+        "shift-vy",
+        "memory-increment",
+        "memory-increment-by-x",
+        "jump-v0",
+        "display-wait",
+        "wrap",
+        "row-collisions",
+        "half-pixel-scroll",
+        "vf-before-result",
+        "index-overflow",
+        "key-release",
+    ];
+
+    /// Octo's names for quirks, also accepted by [`Quirks::set`], so that settings can be copied
+    /// from Octo projects. Each maps to one of ours and whether it means the opposite.
+    pub const OCTO_NAMES: &[(&str, &str, bool)] = &[
+        ("shiftQuirks", "shift-vy", true),
+        ("loadStoreQuirks", "memory-increment", true),
+        ("jumpQuirks", "jump-v0", true),
+        ("clipQuirks", "wrap", true),
+        ("vfOrderQuirks", "vf-before-result", false),
+    ];
+
+    /// Enables or disables a quirk by name.
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), UnknownQuirkError> {
+        let (name, inverted) = Quirks::resolve(name);
+        *self.flag_mut(name)? = enabled != inverted;
+        Ok(())
+    }
+
+    /// Whether the named quirk is enabled.
+    pub fn get(&self, name: &str) -> Result<bool, UnknownQuirkError> {
+        let (name, inverted) = Quirks::resolve(name);
+        let mut quirks = *self;
+        quirks.flag_mut(name).map(|flag| *flag != inverted)
+    }
+
+    /// Translates an Octo quirk name to ours, along with whether it means the opposite.
+    fn resolve(name: &str) -> (&str, bool) {
+        Quirks::OCTO_NAMES
+            .iter()
+            .find(|(octo, _, _)| *octo == name)
+            .map_or((name, false), |(_, ours, inverted)| (*ours, *inverted))
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Result<&mut bool, UnknownQuirkError> {
+        match name {
+            "vf-reset" => Ok(&mut self.vf_reset),
+            "shift-vy" => Ok(&mut self.shift_vy),
+            "memory-increment" => Ok(&mut self.memory_increment),
+            "memory-increment-by-x" => Ok(&mut self.memory_increment_by_x),
+            "jump-v0" => Ok(&mut self.jump_v0),
+            "display-wait" => Ok(&mut self.display_wait),
+            "wrap" => Ok(&mut self.wrap),
+            "row-collisions" => Ok(&mut self.row_collisions),
+            "half-pixel-scroll" => Ok(&mut self.half_pixel_scroll),
+            "vf-before-result" => Ok(&mut self.vf_before_result),
+            "index-overflow" => Ok(&mut self.index_overflow),
+            "key-release" => Ok(&mut self.key_release),
+            _ => Err(UnknownQuirkError(name.to_string())),
+        }
+    }
+}
+
+/// Written as a table of every quirk by name, like the ROM database's quirks.
+impl Serialize for Quirks {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let quirks = Quirks::NAMES
+            .iter()
+            .map(|name| Ok((*name, self.get(name).map_err(ser::Error::custom)?)))
+            .collect::<Result<BTreeMap<_, _>, S::Error>>()?;
+        quirks.serialize(serializer)
+    }
+}
+
+/// Read from a table of quirks by name, with those left out at their defaults.
+impl<'de> Deserialize<'de> for Quirks {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut quirks = Quirks::default();
+        for (name, enabled) in BTreeMap::<String, bool>::deserialize(deserializer)? {
+            quirks.set(&name, enabled).map_err(de::Error::custom)?;
+        }
+        Ok(quirks)
+    }
+}
@@ -0,0 +1,138 @@
+use std::{fmt, str::FromStr};
+
+use ::rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::error::UnknownRngError;
+
+// Power-on seed for the VIP-style generator. Real hardware always started from the same state,
+// so ROMs that rely on the sequence expect it to be identical on every boot.
+const VIP_LFSR_SEED: u16 = 0xACE1;
+
+/// The algorithm used to produce the random byte for CXNN.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RngAlgorithm {
+    /// Uniformly distributed bytes from a modern PRNG.
+    #[default]
+    Uniform,
+    /// A small 16-bit LFSR approximating the short, correlated sequence produced by the COSMAC
+    /// VIP interpreter. Deterministic from power-on.
+    VipLfsr,
+}
+
+impl RngAlgorithm {
+    pub const ALL: [RngAlgorithm; 2] = [RngAlgorithm::Uniform, RngAlgorithm::VipLfsr];
+
+    /// The name used on the command line and in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            RngAlgorithm::Uniform => "uniform",
+            RngAlgorithm::VipLfsr => "vip-lfsr",
+        }
+    }
+}
+
+impl fmt::Display for RngAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for RngAlgorithm {
+    type Err = UnknownRngError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        RngAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownRngError(name.to_string()))
+    }
+}
+
+/// Where a random number generator is in its sequence, so that it can be picked up again
+/// exactly, such as from a save state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngState {
+    /// The uniform generator's seed and how many 32-bit words of its stream have been used.
+    Uniform { seed: [u8; 32], word_pos: u128 },
+    /// The LFSR's register.
+    VipLfsr(u16),
+}
+
+impl RngState {
+    pub fn algorithm(self) -> RngAlgorithm {
+        match self {
+            RngState::Uniform { .. } => RngAlgorithm::Uniform,
+            RngState::VipLfsr(_) => RngAlgorithm::VipLfsr,
+        }
+    }
+}
+
+// The uniform generator is ChaCha12, as rand's StdRng is, but named directly so that its position
+// can be saved.
+pub(crate) enum GuestRng {
+    Uniform(Box<ChaCha12Rng>),
+    VipLfsr(u16),
+}
+
+impl GuestRng {
+    pub(crate) fn new(algorithm: RngAlgorithm) -> GuestRng {
+        match algorithm {
+            RngAlgorithm::Uniform => GuestRng::Uniform(Box::new(ChaCha12Rng::from_os_rng())),
+            RngAlgorithm::VipLfsr => GuestRng::VipLfsr(VIP_LFSR_SEED),
+        }
+    }
+
+    /// Creates a generator which produces the same sequence for the same seed, for reproducible
+    /// runs.
+    pub(crate) fn seeded(algorithm: RngAlgorithm, seed: u64) -> GuestRng {
+        match algorithm {
+            RngAlgorithm::Uniform => GuestRng::Uniform(Box::new(ChaCha12Rng::seed_from_u64(seed))),
+            // The LFSR is already deterministic; an all-zero state would get stuck. Every bit of
+            // the seed is folded into its 16-bit state so that no two seeds are needlessly alike.
+            RngAlgorithm::VipLfsr => {
+                match (seed ^ (seed >> 16) ^ (seed >> 32) ^ (seed >> 48)) as u16 {
+                    0 => GuestRng::VipLfsr(VIP_LFSR_SEED),
+                    state => GuestRng::VipLfsr(state),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn from_state(state: RngState) -> GuestRng {
+        match state {
+            RngState::Uniform { seed, word_pos } => {
+                let mut rng = ChaCha12Rng::from_seed(seed);
+                rng.set_word_pos(word_pos);
+                GuestRng::Uniform(Box::new(rng))
+            }
+            RngState::VipLfsr(state) => GuestRng::VipLfsr(state),
+        }
+    }
+
+    pub(crate) fn state(&self) -> RngState {
+        match self {
+            GuestRng::Uniform(rng) => RngState::Uniform {
+                seed: rng.get_seed(),
+                word_pos: rng.get_word_pos(),
+            },
+            GuestRng::VipLfsr(state) => RngState::VipLfsr(*state),
+        }
+    }
+
+    pub(crate) fn next_byte(&mut self) -> u8 {
+        match self {
+            GuestRng::Uniform(rng) => rng.random(),
+            GuestRng::VipLfsr(state) => {
+                // Fibonacci LFSR with taps 16, 14, 13, 11 stepped once per bit of output
+                for _ in 0..8 {
+                    let bit = (*state ^ (*state >> 2) ^ (*state >> 3) ^ (*state >> 5)) & 1;
+                    *state = (*state >> 1) | (bit << 15);
+                }
+                (*state & 0x00FF) as u8
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Deserializer, de};
+use sha1_smol::Sha1;
+
+use crate::{
+    NUM_INPUT_KEYS,
+    config::AudioConfig,
+    config_dir,
+    error::{ConfigError, UnknownQuirkError},
+    frontend::{Keymap, Rgb, deserialize_optional_palette},
+    profile::Profile,
+    rng::RngAlgorithm,
+    variant::Chip8Variant,
+};
+
+const BUNDLED: &str = include_str!("rom_db.toml");
+const USER_FILE_NAME: &str = "roms.toml";
+
+/// A ROM known to need particular settings.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct KnownRom {
+    /// SHA-1 of the ROM file, in lowercase hex.
+    pub sha1: String,
+    pub title: String,
+    #[serde(default)]
+    pub variant: Chip8Variant,
+    /// Quirks to set on top of the variant's, by name.
+    #[serde(default)]
+    pub quirks: BTreeMap<String, bool>,
+    /// The random number generator CXNN uses instead of the variant's.
+    #[serde(default)]
+    pub rng: Option<RngAlgorithm>,
+    /// Colors to show the ROM in instead of the configured palette, given like the config's.
+    #[serde(default, deserialize_with = "deserialize_optional_palette")]
+    pub palette: Option<[Rgb; 4]>,
+    /// The beep's frequency in hertz, instead of the configured one.
+    pub frequency: Option<f32>,
+    /// The volume of all sound, instead of the configured one.
+    pub volume: Option<f32>,
+    /// Starts muted, for ROMs that sound the buzzer constantly.
+    #[serde(default)]
+    pub mute: bool,
+    /// Keys to play the ROM with instead of the configured keymap, given like the config's.
+    #[serde(default)]
+    pub keymap: Option<Keymap>,
+    /// The keypad keys the gamepad's stick presses when pushed up, down, left and right,
+    /// instead of the configured ones.
+    #[serde(default, deserialize_with = "deserialize_stick")]
+    pub stick: Option<[u8; 4]>,
+}
+
+/// Reads a ROM's stick keys, checking that they're keypad keys.
+fn deserialize_stick<'de, D>(deserializer: D) -> Result<Option<[u8; 4]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let keys = <[u8; 4]>::deserialize(deserializer)?;
+    if let Some(key) = keys.iter().find(|key| **key as usize >= NUM_INPUT_KEYS) {
+        return Err(de::Error::custom(format!(
+            "stick key {key} isn't a keypad key from 0 to 0xF"
+        )));
+    }
+    Ok(Some(keys))
+}
+
+impl KnownRom {
+    /// The profile the ROM should be run with.
+    pub fn profile(&self) -> Result<Profile, UnknownQuirkError> {
+        let mut profile = self.variant.profile();
+        for (name, enabled) in &self.quirks {
+            profile.quirks.set(name, *enabled)?;
+        }
+        profile.rng = self.rng.unwrap_or(profile.rng);
+        Ok(profile)
+    }
+
+    /// The audio settings to play the ROM with, if it changes any of `audio`'s.
+    pub fn audio(&self, audio: &AudioConfig) -> Option<AudioConfig> {
+        if self.frequency.is_none() && self.volume.is_none() {
+            return None;
+        }
+        Some(AudioConfig {
+            frequency: self.frequency.unwrap_or(audio.frequency),
+            volume: self.volume.unwrap_or(audio.volume),
+            ..audio.clone()
+        })
+    }
+}
+
+/// Maps ROM hashes to the settings they need, so that well-known games run correctly without
+/// picking a variant by hand.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct RomDatabase {
+    #[serde(default, rename = "rom")]
+    roms: Vec<KnownRom>,
+}
+
+impl RomDatabase {
+    /// The database shipped with the emulator.
+    pub fn bundled() -> RomDatabase {
+        toml::from_str(BUNDLED).expect("the bundled ROM database is valid")
+    }
+
+    /// The bundled database, extended with the user's own entries if they have any. The user's
+    /// entries take precedence.
+    pub fn with_user_entries() -> Result<RomDatabase, ConfigError> {
+        let mut database = RomDatabase::bundled();
+        if let Some(path) = config_dir().map(|dir| dir.join(USER_FILE_NAME)) {
+            database.extend(RomDatabase::load(&path)?);
+        }
+        Ok(database)
+    }
+
+    /// Loads a database file, which is empty if the file doesn't exist.
+    pub fn load(path: &Path) -> Result<RomDatabase, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Ok(RomDatabase::default())
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Adds the entries of `other`, which take precedence over any for the same ROM.
+    pub fn extend(&mut self, other: RomDatabase) {
+        let mut roms = other.roms;
+        roms.append(&mut self.roms);
+        self.roms = roms;
+    }
+
+    /// Finds the entry for `rom`, if it's a known one.
+    pub fn lookup(&self, rom: &[u8]) -> Option<&KnownRom> {
+        let sha1 = Sha1::from(rom).digest().to_string();
+        self.roms
+            .iter()
+            .find(|known| known.sha1.eq_ignore_ascii_case(&sha1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: [u8; 4] = [0x00, 0xE0, 0x12, 0x02];
+
+    fn database() -> RomDatabase {
+        let sha1 = Sha1::from(ROM).digest().to_string();
+        toml::from_str(&format!(
+            "[[rom]]\nsha1 = \"{sha1}\"\ntitle = \"Test\"\nvariant = \"schip-legacy\"\n\
+             quirks = {{ vf-reset = true }}\nrng = \"vip-lfsr\"\n"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn bundled_database_is_valid() {
+        RomDatabase::bundled();
+    }
+
+    #[test]
+    fn known_hash_resolves_to_its_variant() {
+        let database = database();
+        let known = database.lookup(&ROM).unwrap();
+        assert_eq!(known.variant, Chip8Variant::SuperChipLegacy);
+
+        let profile = known.profile().unwrap();
+        let mut expected = Chip8Variant::SuperChipLegacy.profile();
+        expected.quirks.vf_reset = true;
+        expected.rng = RngAlgorithm::VipLfsr;
+        assert_eq!(profile, expected);
+    }
+
+    #[test]
+    fn unknown_hash_resolves_to_nothing() {
+        assert!(database().lookup(&[0x12, 0x00]).is_none());
+    }
+
+    #[test]
+    fn user_entries_take_precedence() {
+        let mut database = RomDatabase::bundled();
+        database.extend(self::database());
+        let mut user = self::database();
+        user.roms[0].variant = Chip8Variant::XoChip;
+        database.extend(user);
+        assert_eq!(database.lookup(&ROM).unwrap().variant, Chip8Variant::XoChip);
+    }
+}
@@ -0,0 +1,279 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Emulator, error::ParseConditionError};
+
+/// A config-defined trigger: once all of its conditions hold, its actions are performed. Rules
+/// are edge triggered, so they fire when the conditions become true rather than on every check
+/// while they stay true.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Identifies the rule in logs and notifications.
+    pub name: String,
+    pub when: Vec<Condition>,
+    pub then: Vec<RuleAction>,
+    /// Whether the rule is disabled after it first fires.
+    #[serde(default)]
+    pub once: bool,
+}
+
+/// Something the frontend does when a rule fires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Screenshot,
+    SaveState,
+    Pause,
+    /// Prints the rule's name.
+    Log,
+    Sound,
+}
+
+/// A check against the machine state, written like `V3 == 5`, `[0x2F0] >= 0x10`, `I != 0` or
+/// `pixel(10, 4)`. Memory and registers can be compared with `==`, `!=`, `<`, `<=`, `>` and `>=`;
+/// a pixel condition holds while the pixel is lit, or while it's unlit if prefixed with `!`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Condition {
+    Compare {
+        operand: Operand,
+        comparison: Comparison,
+        value: u16,
+    },
+    Pixel {
+        x: usize,
+        y: usize,
+        lit: bool,
+    },
+}
+
+/// The part of the machine state a comparison reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(usize),
+    Index,
+    ProgramCounter,
+    DelayTimer,
+    SoundTimer,
+    Memory(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    const SYMBOLS: [(&str, Comparison); 6] = [
+        // Two character operators come first so `<=` isn't read as `<`
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+
+    fn symbol(self) -> &'static str {
+        Self::SYMBOLS
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .map(|(symbol, _)| *symbol)
+            .expect("every comparison has a symbol")
+    }
+
+    fn holds(self, left: u16, right: u16) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+impl Condition {
+    pub fn holds(&self, emulator: &Emulator) -> bool {
+        match *self {
+            Condition::Compare {
+                operand,
+                comparison,
+                value,
+            } => {
+                let current = match operand {
+                    Operand::Register(register) => emulator.registers()[register] as u16,
+                    Operand::Index => emulator.index_register() as u16,
+                    Operand::ProgramCounter => emulator.program_counter() as u16,
+                    Operand::DelayTimer => emulator.delay_timer() as u16,
+                    Operand::SoundTimer => emulator.sound_timer() as u16,
+                    Operand::Memory(address) => {
+                        emulator.memory().get(address).copied().unwrap_or(0) as u16
+                    }
+                };
+                comparison.holds(current, value)
+            }
+            Condition::Pixel { x, y, lit } => emulator.pixel(x, y) == lit,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Compare {
+                operand,
+                comparison,
+                value,
+            } => {
+                match operand {
+                    Operand::Register(register) => write!(f, "V{register:X}")?,
+                    Operand::Index => write!(f, "I")?,
+                    Operand::ProgramCounter => write!(f, "PC")?,
+                    Operand::DelayTimer => write!(f, "DT")?,
+                    Operand::SoundTimer => write!(f, "ST")?,
+                    Operand::Memory(address) => write!(f, "[{address:#05X}]")?,
+                }
+                write!(f, " {} {value:#X}", comparison.symbol())
+            }
+            Condition::Pixel { x, y, lit } => {
+                let prefix = if *lit { "" } else { "!" };
+                write!(f, "{prefix}pixel({x}, {y})")
+            }
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseConditionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseConditionError {
+            text: text.to_string(),
+            reason,
+        };
+        let trimmed = text.trim();
+
+        let (lit, pixel) = match trimmed.strip_prefix('!') {
+            Some(rest) => (false, rest.trim_start()),
+            None => (true, trimmed),
+        };
+        if let Some(arguments) = pixel
+            .strip_prefix("pixel(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let (x, y) = arguments
+                .split_once(',')
+                .ok_or(error("expected pixel(x, y)"))?;
+            let x = parse_number(x).ok_or(error("invalid x coordinate"))?;
+            let y = parse_number(y).ok_or(error("invalid y coordinate"))?;
+            return Ok(Condition::Pixel {
+                x: x as usize,
+                y: y as usize,
+                lit,
+            });
+        }
+
+        let (operand, comparison, value) = Comparison::SYMBOLS
+            .iter()
+            .find_map(|(symbol, comparison)| {
+                trimmed
+                    .split_once(symbol)
+                    .map(|(operand, value)| (operand.trim(), *comparison, value))
+            })
+            .ok_or(error("expected a comparison such as =="))?;
+
+        let operand = match operand.to_ascii_uppercase().as_str() {
+            "I" => Operand::Index,
+            "PC" => Operand::ProgramCounter,
+            "DT" => Operand::DelayTimer,
+            "ST" => Operand::SoundTimer,
+            register if register.len() == 2 && register.starts_with('V') => Operand::Register(
+                usize::from_str_radix(&register[1..], 16).map_err(|_| error("invalid register"))?,
+            ),
+            _ => {
+                let address = operand
+                    .strip_prefix('[')
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .and_then(parse_number)
+                    .ok_or(error("expected V0-VF, I, PC, DT, ST or [address]"))?;
+                if address as usize >= crate::XO_CHIP_MEMORY_BYTES {
+                    return Err(error("address out of bounds"));
+                }
+                Operand::Memory(address as usize)
+            }
+        };
+
+        Ok(Condition::Compare {
+            operand,
+            comparison,
+            value: parse_number(value).ok_or(error("invalid value"))?,
+        })
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = ParseConditionError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.to_string()
+    }
+}
+
+/// Parses a decimal number, or a hex one with a `0x` prefix.
+fn parse_number(text: &str) -> Option<u16> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Tracks which rules currently hold so they only fire on the transition.
+#[derive(Clone, Debug, Default)]
+pub struct RuleEngine {
+    rules: Vec<Rule>,
+    holding: Vec<bool>,
+    spent: Vec<bool>,
+}
+
+impl RuleEngine {
+    pub fn new(rules: Vec<Rule>) -> RuleEngine {
+        RuleEngine {
+            holding: vec![false; rules.len()],
+            spent: vec![false; rules.len()],
+            rules,
+        }
+    }
+
+    /// Evaluates every rule against the emulator, returning the ones which just fired.
+    pub fn check(&mut self, emulator: &Emulator) -> Vec<Rule> {
+        let mut fired = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if self.spent[index] {
+                continue;
+            }
+
+            let holds = rule.when.iter().all(|condition| condition.holds(emulator));
+            if holds && !self.holding[index] {
+                fired.push(rule.clone());
+                self.spent[index] = rule.once;
+            }
+            self.holding[index] = holds;
+        }
+        fired
+    }
+}
@@ -0,0 +1,41 @@
+use std::io::{self, Write};
+
+use crate::Emulator;
+
+/// The characters [`screen_text`] draws each pixel color with: unlit, lit on the first plane,
+/// lit on XO-CHIP's second plane and lit on both.
+pub const SCREEN_TEXT_CHARS: [char; 4] = ['.', '#', 'o', '@'];
+
+/// Draws the screen as text, one line per row at [`Emulator::screen_resolution`], for bug reports
+/// and diffing screens in scripts. See [`SCREEN_TEXT_CHARS`].
+pub fn screen_text(emulator: &Emulator) -> String {
+    let (width, height) = emulator.screen_resolution();
+    let mut text = String::with_capacity((width + 1) * height);
+    for y in 0..height {
+        for x in 0..width {
+            text.push(SCREEN_TEXT_CHARS[emulator.screen_color(x, y) as usize & 0b11]);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes the screen to `writer` as a binary PBM image at [`Emulator::screen_resolution`], with
+/// pixels lit on either plane black and unlit pixels white.
+pub fn write_pbm(emulator: &Emulator, mut writer: impl Write) -> io::Result<()> {
+    let (width, height) = emulator.screen_resolution();
+    write!(writer, "P4\n{width} {height}\n")?;
+
+    // Each row is padded to a whole number of bytes, with the leftmost pixel in the high bit
+    let mut row = vec![0u8; width.div_ceil(8)];
+    for y in 0..height {
+        row.fill(0);
+        for x in 0..width {
+            if emulator.screen_color(x, y) != 0 {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        writer.write_all(&row)?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,383 @@
+use crate::{
+    AUDIO_PATTERN_BYTES, DisplayMode,
+    error::SnapshotError,
+    profile::Profile,
+    quirks::Quirks,
+    rng::{RngAlgorithm, RngState},
+    timing::Timing,
+};
+
+const MAGIC: &[u8; 5] = b"HACHI";
+const VERSION: u8 = 1;
+
+/// A copy of the complete machine state, used for save states and for inspecting the emulator
+/// from the outside.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    /// The platform the machine was running as, which decides how much memory it has, for
+    /// instance.
+    pub profile: Profile,
+    pub memory: Vec<u8>,
+    pub registers: [u8; 16],
+    pub index_register: usize,
+    pub program_counter: usize,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// The XO-CHIP audio pattern, if one has been loaded.
+    pub audio_pattern: Option<[u8; AUDIO_PATTERN_BYTES]>,
+    pub pitch: u8,
+    /// One entry per framebuffer pixel, row by row. The framebuffer is always
+    /// [`HIRES_WIDTH`](crate::HIRES_WIDTH) by [`HIRES_HEIGHT`](crate::HIRES_HEIGHT), with lores
+    /// pixels covering 2x2 blocks.
+    pub screen: Vec<bool>,
+    /// XO-CHIP's second plane, laid out like `screen`.
+    pub second_plane: Vec<bool>,
+    /// The planes selected with FN01, as a bitmask.
+    pub selected_planes: u8,
+    pub display_mode: DisplayMode,
+    /// Mega-CHIP's screen and drawing state, if its mode is on. The format is private to the
+    /// emulator.
+    pub megachip: Option<Vec<u8>>,
+    pub cycles: u64,
+    /// Where CXNN's random number generator is in its sequence.
+    pub rng: RngState,
+    pub key_wait: KeyWait,
+    /// Time left until the delay and sound timers next tick, in seconds.
+    pub timer_time: f32,
+    /// Time left until the next instruction is due, in seconds.
+    pub update_time: f32,
+    /// How long the last instruction took, which the next one waits for with
+    /// [`Timing::Vip`](crate::Timing::Vip), in seconds.
+    pub step_time: f32,
+    /// Whether a DXYN is waiting for the next display refresh, see
+    /// [`Quirks::display_wait`](crate::Quirks::display_wait).
+    pub awaiting_vblank: bool,
+}
+
+/// What an FX0A instruction is currently blocked on, if anything.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyWait {
+    #[default]
+    None,
+    /// Waiting for any key to be pressed, which will be stored in the given register.
+    Press { register: usize },
+    /// The key has been stored and execution resumes once it's released.
+    Release { key: u8 },
+}
+
+impl Snapshot {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes(MAGIC);
+        writer.u8(VERSION);
+
+        write_profile(&mut writer, &self.profile);
+        writer.u32(self.memory.len() as u32);
+        writer.bytes(&self.memory);
+        writer.bytes(&self.registers);
+        writer.u32(self.index_register as u32);
+        writer.u32(self.program_counter as u32);
+        writer.u32(self.stack.len() as u32);
+        for value in &self.stack {
+            writer.u16(*value);
+        }
+        writer.u8(self.delay_timer);
+        writer.u8(self.sound_timer);
+        writer.bits(&self.screen);
+        writer.u64(self.cycles);
+        match self.rng {
+            RngState::Uniform { seed, word_pos } => {
+                writer.u8(0);
+                writer.bytes(&seed);
+                writer.bytes(&word_pos.to_le_bytes());
+            }
+            RngState::VipLfsr(state) => {
+                writer.u8(1);
+                writer.u16(state);
+            }
+        }
+        match self.key_wait {
+            KeyWait::None => writer.bytes(&[0, 0]),
+            KeyWait::Press { register } => writer.bytes(&[1, register as u8]),
+            KeyWait::Release { key } => writer.bytes(&[2, key]),
+        }
+        writer.f32(self.timer_time);
+        writer.f32(self.update_time);
+        writer.f32(self.step_time);
+        writer.u8(self.awaiting_vblank as u8);
+        writer.u8(match self.display_mode {
+            DisplayMode::Lores => 0,
+            DisplayMode::Hires => 1,
+            DisplayMode::TwoPage => 2,
+            DisplayMode::Eti660 => 3,
+        });
+        writer.bits(&self.second_plane);
+        writer.u8(self.selected_planes);
+        match self.audio_pattern {
+            None => writer.u8(0),
+            Some(pattern) => {
+                writer.u8(1);
+                writer.bytes(&pattern);
+            }
+        }
+        writer.u8(self.pitch);
+        match &self.megachip {
+            None => writer.u8(0),
+            Some(state) => {
+                writer.u8(1);
+                writer.u32(state.len() as u32);
+                writer.bytes(state);
+            }
+        }
+
+        writer.data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Snapshot, SnapshotError> {
+        let mut reader = Reader { data };
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(SnapshotError::InvalidHeader);
+        }
+
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let profile = read_profile(&mut reader)?;
+        let memory_len = reader.u32()? as usize;
+        let memory = reader.bytes(memory_len)?.to_vec();
+        let registers = reader.bytes(16)?.try_into().expect("read 16 bytes");
+        let index_register = reader.u32()? as usize;
+        let program_counter = reader.u32()? as usize;
+        let stack_len = reader.u32()? as usize;
+        let stack = (0..stack_len)
+            .map(|_| reader.u16())
+            .collect::<Result<_, _>>()?;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let screen = reader.bits()?;
+        let cycles = reader.u64()?;
+        let rng = match reader.u8()? {
+            0 => RngState::Uniform {
+                seed: reader.bytes(32)?.try_into().expect("read 32 bytes"),
+                word_pos: u128::from_le_bytes(reader.bytes(16)?.try_into().expect("read 16 bytes")),
+            },
+            1 => RngState::VipLfsr(reader.u16()?),
+            _ => return Err(SnapshotError::Invalid("unknown random number generator")),
+        };
+        let key_wait = match reader.bytes(2)? {
+            [0, _] => KeyWait::None,
+            [1, register] => KeyWait::Press {
+                register: *register as usize,
+            },
+            [2, key] => KeyWait::Release { key: *key },
+            _ => return Err(SnapshotError::Invalid("unknown key wait state")),
+        };
+        let timer_time = reader.f32()?;
+        let update_time = reader.f32()?;
+        let step_time = reader.f32()?;
+        let awaiting_vblank = reader.u8()? != 0;
+        let display_mode = match reader.u8()? {
+            0 => DisplayMode::Lores,
+            1 => DisplayMode::Hires,
+            2 => DisplayMode::TwoPage,
+            3 => DisplayMode::Eti660,
+            _ => return Err(SnapshotError::Invalid("unknown display mode")),
+        };
+        let second_plane = reader.bits()?;
+        let selected_planes = reader.u8()?;
+        let audio_pattern = match reader.u8()? {
+            0 => None,
+            _ => Some(
+                reader
+                    .bytes(AUDIO_PATTERN_BYTES)?
+                    .try_into()
+                    .expect("read a whole pattern"),
+            ),
+        };
+        let pitch = reader.u8()?;
+        let megachip = match reader.u8()? {
+            0 => None,
+            _ => {
+                let len = reader.u32()? as usize;
+                Some(reader.bytes(len)?.to_vec())
+            }
+        };
+
+        Ok(Snapshot {
+            profile,
+            memory,
+            registers,
+            index_register,
+            program_counter,
+            stack,
+            delay_timer,
+            sound_timer,
+            audio_pattern,
+            pitch,
+            screen,
+            second_plane,
+            selected_planes,
+            cycles,
+            rng,
+            key_wait,
+            timer_time,
+            update_time,
+            step_time,
+            awaiting_vblank,
+            display_mode,
+            megachip,
+        })
+    }
+}
+
+/// Writes the random number generator, timing and platform flags as bytes, and the quirks as a
+/// bitmask in the order of [`Quirks::NAMES`].
+fn write_profile(writer: &mut Writer, profile: &Profile) {
+    writer.u8(match profile.rng {
+        RngAlgorithm::Uniform => 0,
+        RngAlgorithm::VipLfsr => 1,
+    });
+    let quirks = Quirks::NAMES
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| profile.quirks.get(name) == Ok(true))
+        .fold(0, |quirks, (bit, _)| quirks | (1 << bit));
+    writer.u32(quirks);
+    writer.u8(match profile.timing {
+        Timing::Fixed => 0,
+        Timing::Vip => 1,
+    });
+    writer.u8(profile.xo_chip as u8
+        | (profile.two_page_hires as u8) << 1
+        | (profile.megachip as u8) << 2
+        | (profile.eti_660 as u8) << 3
+        | (profile.super_chip as u8) << 4);
+}
+
+fn read_profile(reader: &mut Reader) -> Result<Profile, SnapshotError> {
+    let rng = match reader.u8()? {
+        0 => RngAlgorithm::Uniform,
+        1 => RngAlgorithm::VipLfsr,
+        _ => return Err(SnapshotError::Invalid("unknown random number generator")),
+    };
+    let quirk_bits = reader.u32()?;
+    let mut quirks = Quirks::default();
+    for (bit, name) in Quirks::NAMES.iter().enumerate() {
+        quirks
+            .set(name, quirk_bits & (1 << bit) != 0)
+            .expect("quirk names are valid");
+    }
+    let timing = match reader.u8()? {
+        0 => Timing::Fixed,
+        1 => Timing::Vip,
+        _ => return Err(SnapshotError::Invalid("unknown timing")),
+    };
+    let flags = reader.u8()?;
+    Ok(Profile {
+        rng,
+        quirks,
+        timing,
+        super_chip: flags & 1 << 4 != 0,
+        xo_chip: flags & 1 != 0,
+        two_page_hires: flags & 1 << 1 != 0,
+        megachip: flags & 1 << 2 != 0,
+        eti_660: flags & 1 << 3 != 0,
+    })
+}
+
+#[derive(Default)]
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Writes a length followed by the bits packed eight to a byte.
+    fn bits(&mut self, bits: &[bool]) {
+        self.u32(bits.len() as u32);
+        for chunk in bits.chunks(8) {
+            let byte = chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (bit, on)| byte | ((*on as u8) << bit));
+            self.u8(byte);
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.data.len() < count {
+            return Err(SnapshotError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(
+            self.bytes(2)?.try_into().expect("read 2 bytes"),
+        ))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(
+            self.bytes(4)?.try_into().expect("read 4 bytes"),
+        ))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(
+            self.bytes(8)?.try_into().expect("read 8 bytes"),
+        ))
+    }
+
+    fn f32(&mut self) -> Result<f32, SnapshotError> {
+        Ok(f32::from_le_bytes(
+            self.bytes(4)?.try_into().expect("read 4 bytes"),
+        ))
+    }
+
+    fn bits(&mut self) -> Result<Vec<bool>, SnapshotError> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len.div_ceil(8))?;
+        Ok((0..len)
+            .map(|bit| (bytes[bit / 8] >> (bit % 8)) & 1 == 1)
+            .collect())
+    }
+}
@@ -0,0 +1,68 @@
+/// The program run when the emulator is started without a ROM: the HachiEmu logo over a blinking
+/// "DROP A ROM", which runs on every variant's 64x32 screen.
+pub const SPLASH_ROM: &[u8] = &[
+    0x00, 0xE0, // 0x200: CLS
+    0x61, 0x04, // 0x202: LD V1, 4
+    0x60, 0x08, // 0x204: LD V0, 8
+    0xA2, 0x60, // 0x206: LD I, 0x260
+    0xD0, 0x1A, // 0x208: DRW V0, V1, 10
+    0x60, 0x12, // 0x20A: LD V0, 18
+    0xA2, 0x6A, // 0x20C: LD I, 0x26A
+    0xD0, 0x1A, // 0x20E: DRW V0, V1, 10
+    0x60, 0x1C, // 0x210: LD V0, 28
+    0xA2, 0x74, // 0x212: LD I, 0x274
+    0xD0, 0x1A, // 0x214: DRW V0, V1, 10
+    0x60, 0x26, // 0x216: LD V0, 38
+    0xA2, 0x60, // 0x218: LD I, 0x260
+    0xD0, 0x1A, // 0x21A: DRW V0, V1, 10
+    0x60, 0x30, // 0x21C: LD V0, 48
+    0xA2, 0x7E, // 0x21E: LD I, 0x27E
+    0xD0, 0x1A, // 0x220: DRW V0, V1, 10
+    // Blink the text by drawing it again every half second, which erases it
+    0x62, 0x14, // 0x222: LD V2, 20
+    0x63, 0x07, // 0x224: LD V3, 7
+    0xA2, 0x88, // 0x226: LD I, 0x288
+    0xD3, 0x25, // 0x228: DRW V3, V2, 5
+    0x63, 0x0C, // 0x22A: LD V3, 12
+    0xA2, 0x8D, // 0x22C: LD I, 0x28D
+    0xD3, 0x25, // 0x22E: DRW V3, V2, 5
+    0x63, 0x11, // 0x230: LD V3, 17
+    0xA2, 0x92, // 0x232: LD I, 0x292
+    0xD3, 0x25, // 0x234: DRW V3, V2, 5
+    0x63, 0x16, // 0x236: LD V3, 22
+    0xA2, 0x97, // 0x238: LD I, 0x297
+    0xD3, 0x25, // 0x23A: DRW V3, V2, 5
+    0x63, 0x20, // 0x23C: LD V3, 32
+    0xA2, 0x9C, // 0x23E: LD I, 0x29C
+    0xD3, 0x25, // 0x240: DRW V3, V2, 5
+    0x63, 0x2A, // 0x242: LD V3, 42
+    0xA2, 0x8D, // 0x244: LD I, 0x28D
+    0xD3, 0x25, // 0x246: DRW V3, V2, 5
+    0x63, 0x2F, // 0x248: LD V3, 47
+    0xA2, 0x92, // 0x24A: LD I, 0x292
+    0xD3, 0x25, // 0x24C: DRW V3, V2, 5
+    0x63, 0x34, // 0x24E: LD V3, 52
+    0xA2, 0xA1, // 0x250: LD I, 0x2A1
+    0xD3, 0x25, // 0x252: DRW V3, V2, 5
+    0x64, 0x1E, // 0x254: LD V4, 30
+    0xF4, 0x15, // 0x256: LD DT, V4
+    0xF5, 0x07, // 0x258: LD V5, DT
+    0x35, 0x00, // 0x25A: SE V5, 0
+    0x12, 0x58, // 0x25C: JP 0x258
+    0x12, 0x22, // 0x25E: JP 0x222
+    // 0x260: logo H
+    0b11000011, 0b11000011, 0b11000011, 0b11000011, 0b11111111, 0b11111111, 0b11000011, 0b11000011,
+    0b11000011, 0b11000011, // 0x26A: logo A
+    0b00111100, 0b01111110, 0b11000011, 0b11000011, 0b11000011, 0b11111111, 0b11111111, 0b11000011,
+    0b11000011, 0b11000011, // 0x274: logo C
+    0b00111110, 0b01111111, 0b11100000, 0b11000000, 0b11000000, 0b11000000, 0b11000000, 0b11100000,
+    0b01111111, 0b00111110, // 0x27E: logo I
+    0b01111110, 0b01111110, 0b00011000, 0b00011000, 0b00011000, 0b00011000, 0b00011000, 0b00011000,
+    0b01111110, 0b01111110, // 0x288: text D
+    0b11100000, 0b10010000, 0b10010000, 0b10010000, 0b11100000, // 0x28D: text R
+    0b11100000, 0b10010000, 0b11100000, 0b10100000, 0b10010000, // 0x292: text O
+    0b01100000, 0b10010000, 0b10010000, 0b10010000, 0b01100000, // 0x297: text P
+    0b11100000, 0b10010000, 0b11100000, 0b10000000, 0b10000000, // 0x29C: text A
+    0b01100000, 0b10010000, 0b11110000, 0b10010000, 0b10010000, // 0x2A1: text M
+    0b10010000, 0b11110000, 0b11110000, 0b10010000, 0b10010000,
+];
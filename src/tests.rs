@@ -0,0 +1,544 @@
+//! Runs instructions on small programs and checks the machine state they leave.
+
+use crate::{
+    BIG_FONT, BIG_FONT_LOAD_INDEX, Chip8Variant, Emulator, ExecutedInstruction, HaltReason,
+    KeyWait, MachineCodePolicy, Movie, NUM_INPUT_KEYS, Quirks, RuntimeError, STANDARD_FONT,
+    Snapshot, Timing, state_hash,
+};
+
+const FRAME: f32 = 1.0 / 60.0;
+
+/// An emulator for `variant` with the fonts and `program` loaded. The program is a list of
+/// instruction words, followed by any data it uses.
+fn emulator(variant: Chip8Variant, words: &[u16], data: &[u8]) -> Emulator {
+    let mut emulator = Emulator::new().with_variant(variant);
+    emulator.load_font(&STANDARD_FONT);
+    emulator.load_big_font(&BIG_FONT);
+    let mut program: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes()).collect();
+    program.extend_from_slice(data);
+    // ROMs have to be whole instructions long
+    program.resize(program.len().next_multiple_of(2), 0);
+    emulator.load_program(&program).unwrap();
+    emulator
+}
+
+/// The address `data` starts at in a program of `words` instructions.
+fn data_address(words: usize) -> u16 {
+    0x200 + words as u16 * 2
+}
+
+fn run(emulator: &mut Emulator, steps: usize) {
+    for _ in 0..steps {
+        emulator.step().unwrap();
+    }
+}
+
+#[test]
+fn hires_and_lores_switch_the_screen_size() {
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &[0x00FF, 0x00FE], &[]);
+    run(&mut emulator, 1);
+    assert!(emulator.hires());
+    assert_eq!(emulator.display_size(), (128, 64));
+    run(&mut emulator, 1);
+    assert!(!emulator.hires());
+    assert_eq!(emulator.display_size(), (64, 32));
+}
+
+#[test]
+fn dxy0_draws_a_16x16_sprite_in_hires() {
+    let words = [0x00FF, 0xA000 | data_address(4), 0xD010, 0xD010];
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &words, &[0xFF; 32]);
+    run(&mut emulator, 3);
+    for y in 0..16 {
+        for x in 0..16 {
+            assert!(emulator.pixel(x, y), "({x}, {y}) is unlit");
+        }
+    }
+    assert!(!emulator.pixel(16, 0));
+    assert!(!emulator.pixel(0, 16));
+    assert_eq!(emulator.registers()[0xF], 0);
+
+    run(&mut emulator, 1);
+    assert!(!emulator.pixel(0, 0));
+    assert_eq!(emulator.registers()[0xF], 1);
+}
+
+#[test]
+fn scrolling_moves_the_screen() {
+    // Lights the top left pixel, then scrolls down 2, right 4 and left 4
+    let words = [
+        0x00FF,
+        0xA000 | data_address(6),
+        0xD011,
+        0x00C2,
+        0x00FB,
+        0x00FC,
+    ];
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &words, &[0x80]);
+    run(&mut emulator, 4);
+    assert!(emulator.pixel(0, 2));
+    assert!(!emulator.pixel(0, 0));
+    run(&mut emulator, 1);
+    assert!(emulator.pixel(4, 2));
+    run(&mut emulator, 1);
+    assert!(emulator.pixel(0, 2));
+    assert!(!emulator.pixel(4, 2));
+}
+
+#[test]
+fn lores_scrolls_by_whole_pixels() {
+    let words = [0xA000 | data_address(3), 0xD011, 0x00C1];
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &words, &[0x80]);
+    run(&mut emulator, 3);
+    assert!(emulator.pixel(0, 1));
+    assert!(!emulator.pixel(0, 0));
+}
+
+#[test]
+fn xo_chip_scrolls_up() {
+    let words = [0x6102, 0xA000 | data_address(4), 0xD011, 0x00D1];
+    let mut emulator = emulator(Chip8Variant::XoChip, &words, &[0x80]);
+    run(&mut emulator, 4);
+    assert!(emulator.pixel(0, 1));
+    assert!(!emulator.pixel(0, 2));
+}
+
+#[test]
+fn f000_loads_a_16_bit_address() {
+    let mut emulator = emulator(Chip8Variant::XoChip, &[0xF000, 0xABCD, 0x6001], &[]);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.index_register(), 0xABCD);
+    assert_eq!(emulator.program_counter(), 0x204);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.registers()[0], 1);
+}
+
+#[test]
+fn skips_jump_over_all_of_f000_nnnn() {
+    let mut emulator = emulator(Chip8Variant::XoChip, &[0x3000, 0xF000, 0xABCD, 0x6101], &[]);
+    run(&mut emulator, 2);
+    assert_eq!(emulator.index_register(), 0);
+    assert_eq!(emulator.registers()[1], 1);
+}
+
+#[test]
+fn plane_masks_select_the_planes_drawn_on() {
+    // Draws on the second plane only, then with the same row on both
+    let words = [
+        0xA000 | data_address(6),
+        0xF201,
+        0xD011,
+        0xF301,
+        0x6008,
+        0xD011,
+    ];
+    let mut emulator = emulator(Chip8Variant::XoChip, &words, &[0x80, 0x80]);
+    run(&mut emulator, 3);
+    assert_eq!(emulator.screen_color(0, 0), 0b10);
+    run(&mut emulator, 3);
+    assert_eq!(emulator.screen_color(8, 0), 0b11);
+    assert_eq!(emulator.screen_color(0, 0), 0b10);
+}
+
+#[test]
+fn f002_and_fx3a_set_the_audio_pattern_and_pitch() {
+    let pattern: Vec<u8> = (0..16).collect();
+    let words = [0xA000 | data_address(4), 0xF002, 0x6A70, 0xFA3A];
+    let mut emulator = emulator(Chip8Variant::XoChip, &words, &pattern);
+    run(&mut emulator, 2);
+    assert_eq!(emulator.audio_pattern().map(Vec::from), Some(pattern));
+    run(&mut emulator, 2);
+    assert_eq!(emulator.pitch(), 0x70);
+}
+
+#[test]
+fn xo_chip_stores_and_loads_register_ranges() {
+    // Stores V1 to V3, then loads them back in reverse into V4 to V6
+    let words = [0x6111, 0x6222, 0x6333, 0xA300, 0x5132, 0x5643];
+    let mut emulator = emulator(Chip8Variant::XoChip, &words, &[]);
+    run(&mut emulator, 5);
+    assert_eq!(emulator.memory()[0x300..0x303], [0x11, 0x22, 0x33]);
+    assert_eq!(emulator.index_register(), 0x300);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.registers()[4..7], [0x33, 0x22, 0x11]);
+}
+
+#[test]
+fn rpl_flags_save_and_restore_registers() {
+    let words = [
+        0x6001, 0x6102, 0x6203, 0xF275, 0x6000, 0x6100, 0x6200, 0xF285,
+    ];
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &words, &[]);
+    run(&mut emulator, 4);
+    assert_eq!(emulator.rpl_flags()[..4], [1, 2, 3, 0]);
+    run(&mut emulator, 4);
+    assert_eq!(emulator.registers()[..3], [1, 2, 3]);
+}
+
+#[test]
+fn fx30_points_at_the_big_font() {
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &[0x6003, 0xF030], &[]);
+    run(&mut emulator, 2);
+    assert_eq!(emulator.index_register(), BIG_FONT_LOAD_INDEX + 30);
+}
+
+#[test]
+fn megachip_draws_palette_sprites_and_shows_them_on_00e0() {
+    let words = [
+        0x0011,
+        0xA000 | data_address(9),
+        0x0201, // One palette color, opaque red
+        0x0301,
+        0x0401,
+        0xA000 | (data_address(9) + 4),
+        0xD010, // A 1x1 sprite of that color
+        0xD010,
+        0x00E0,
+    ];
+    let mut emulator = emulator(Chip8Variant::MegaChip, &words, &[0xFF, 0xFF, 0, 0, 1]);
+    run(&mut emulator, 7);
+    assert_eq!(emulator.megachip_frame().unwrap()[0], [0, 0, 0, 255]);
+    run(&mut emulator, 1);
+    // Nothing collides until a collision color is set
+    assert_eq!(emulator.registers()[0xF], 0);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.megachip_frame().unwrap()[0], [0xFF, 0, 0, 255]);
+}
+
+#[test]
+fn megachip_collides_with_the_collision_color() {
+    let words = [
+        0x0011,
+        0xA000 | data_address(7),
+        0x0301,
+        0x0401,
+        0x0901,
+        0xD010,
+        0xD010,
+    ];
+    let mut emulator = emulator(Chip8Variant::MegaChip, &words, &[1]);
+    run(&mut emulator, 6);
+    assert_eq!(emulator.registers()[0xF], 0);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.registers()[0xF], 1);
+}
+
+#[test]
+fn lfsr_seeds_differing_above_16_bits_give_different_numbers() {
+    let numbers = [0x1234, 0x5600_1234].map(|seed| {
+        let mut emulator = emulator(Chip8Variant::OriginalChip8, &[0xC0FF], &[]);
+        emulator.seed_rng(seed);
+        run(&mut emulator, 1);
+        emulator.registers()[0]
+    });
+    assert_ne!(numbers[0], numbers[1]);
+}
+
+#[test]
+fn states_saved_with_i_past_the_end_of_memory_load() {
+    let mut emulator = emulator(
+        Chip8Variant::SuperChipModern,
+        &[0xAFFF, 0x60FF, 0xF01E],
+        &[],
+    );
+    run(&mut emulator, 3);
+    assert_eq!(emulator.index_register(), 0xFFF + 0xFF);
+
+    let snapshot = Snapshot::from_bytes(&emulator.snapshot().to_bytes()).unwrap();
+    let mut restored = Emulator::new();
+    restored.restore(&snapshot).unwrap();
+    assert_eq!(restored.index_register(), 0xFFF + 0xFF);
+}
+
+#[test]
+fn restored_states_resume_exactly() {
+    // Draws a random number, waits for a key, then draws another and counts in a loop
+    let words = [0xC0FF, 0xF10A, 0xC2FF, 0x7301, 0x1206];
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &words, &[]);
+    let mut profile = *emulator.profile();
+    profile.timing = Timing::Vip;
+    emulator.set_profile(profile);
+    emulator.seed_rng(7);
+    emulator.update(FRAME).unwrap();
+    assert_eq!(emulator.key_wait(), KeyWait::Press { register: 1 });
+
+    let snapshot = Snapshot::from_bytes(&emulator.snapshot().to_bytes()).unwrap();
+    let mut restored = Emulator::new();
+    restored.restore(&snapshot).unwrap();
+    assert_eq!(restored.profile(), emulator.profile());
+
+    for emulator in [&mut emulator, &mut restored] {
+        emulator.press_key(5);
+        emulator.update(FRAME).unwrap();
+        emulator.release_key(5);
+        for _ in 0..10 {
+            emulator.update(FRAME).unwrap();
+        }
+    }
+    assert_eq!(restored.registers()[1], 5);
+    assert_eq!(restored.snapshot(), emulator.snapshot());
+}
+
+#[test]
+fn fx0a_waits_for_the_key_to_be_released() {
+    let mut emulator = emulator(Chip8Variant::OriginalChip8, &[0xF10A, 0x6201], &[]);
+    assert!(emulator.quirks().key_release);
+    run(&mut emulator, 1);
+    emulator.press_key(5);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.registers()[1], 5);
+    assert_eq!(emulator.key_wait(), KeyWait::Release { key: 5 });
+    run(&mut emulator, 1);
+    assert_eq!(emulator.registers()[2], 0);
+
+    emulator.release_key(5);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.key_wait(), KeyWait::None);
+    assert_eq!(emulator.registers()[2], 1);
+}
+
+#[test]
+fn fx0a_continues_on_the_press_without_key_release() {
+    let mut emulator = emulator(Chip8Variant::OriginalChip8, &[0xF10A, 0x6201], &[]);
+    let mut quirks = *emulator.quirks();
+    quirks.key_release = false;
+    emulator.set_quirks(quirks);
+    run(&mut emulator, 1);
+    emulator.press_key(5);
+    run(&mut emulator, 1);
+    assert_eq!(emulator.registers()[1], 5);
+    assert_eq!(emulator.key_wait(), KeyWait::None);
+    // The key is still held down
+    run(&mut emulator, 1);
+    assert_eq!(emulator.registers()[2], 1);
+}
+
+#[test]
+fn default_quirks_match_the_default_variant() {
+    assert_eq!(Quirks::default(), Chip8Variant::default().quirks());
+    assert!(Quirks::default().key_release);
+    let quirks: Quirks = toml::from_str("vf-reset = true").unwrap();
+    assert!(quirks.vf_reset && quirks.key_release);
+}
+
+#[test]
+fn super_chip_screen_instructions_are_machine_code_calls_on_chip_8() {
+    let mut chip_8 = emulator(Chip8Variant::OriginalChip8, &[0x00FE], &[]);
+    chip_8.set_machine_code_policy(MachineCodePolicy::Halt);
+    assert_eq!(
+        chip_8.step(),
+        Err(HaltReason::Error(RuntimeError::MachineCodeCall {
+            address: 0x200,
+            instruction: 0x00FE,
+        }))
+    );
+
+    let mut super_chip = emulator(Chip8Variant::SuperChipModern, &[0x00FF], &[]);
+    super_chip.set_machine_code_policy(MachineCodePolicy::Halt);
+    run(&mut super_chip, 1);
+    assert!(super_chip.hires());
+}
+
+#[test]
+fn headless_runs_return_why_they_stopped() {
+    let mut exits = emulator(Chip8Variant::SuperChipModern, &[0x6001, 0x00FD], &[]);
+    assert_eq!(exits.run_headless(100), HaltReason::Exit);
+
+    let mut loops = emulator(Chip8Variant::SuperChipModern, &[0x1200], &[]);
+    assert_eq!(loops.run_headless(100), HaltReason::Timeout);
+    assert_eq!(loops.cycles(), 100);
+
+    let mut breaks = emulator(Chip8Variant::SuperChipModern, &[0x6001, 0x6102], &[]);
+    breaks.add_breakpoint(0x202);
+    assert_eq!(breaks.run_headless(100), HaltReason::Breakpoint(0x202));
+    assert_eq!(breaks.registers()[1], 0);
+}
+
+#[test]
+fn traces_yield_each_instruction_until_a_key_wait() {
+    let mut emulator = emulator(
+        Chip8Variant::SuperChipModern,
+        &[0x6012, 0x7001, 0xF20A],
+        &[],
+    );
+    let mut trace = emulator.trace();
+    let executed: Vec<_> = trace.by_ref().collect();
+    assert_eq!(trace.halt_reason(), None);
+    assert_eq!(
+        executed,
+        [
+            ExecutedInstruction {
+                address: 0x200,
+                opcode: 0x6012,
+                disassembly: String::from("LD V0, 0x12"),
+                cycle: 0,
+            },
+            ExecutedInstruction {
+                address: 0x202,
+                opcode: 0x7001,
+                disassembly: String::from("ADD V0, 0x01"),
+                cycle: 1,
+            },
+            ExecutedInstruction {
+                address: 0x204,
+                opcode: 0xF20A,
+                disassembly: String::from("LD V2, K"),
+                cycle: 2,
+            },
+        ]
+    );
+    assert_eq!(emulator.registers()[0], 0x13);
+}
+
+#[test]
+fn traces_end_with_the_halt_reason() {
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &[0x6001, 0x00FD], &[]);
+    let mut trace = emulator.trace();
+    assert_eq!(trace.by_ref().count(), 1);
+    assert_eq!(trace.halt_reason(), Some(&HaltReason::Exit));
+}
+
+#[test]
+fn vf_reset_clears_vf_after_logic_ops() {
+    let words = [0x6F05, 0x6003, 0x6106, 0x8011];
+    let mut vip = emulator(Chip8Variant::OriginalChip8, &words, &[]);
+    run(&mut vip, 4);
+    assert_eq!(vip.registers()[0], 0x07);
+    assert_eq!(vip.registers()[0xF], 0);
+
+    let mut super_chip = emulator(Chip8Variant::SuperChipModern, &words, &[]);
+    run(&mut super_chip, 4);
+    assert_eq!(super_chip.registers()[0], 0x07);
+    assert_eq!(super_chip.registers()[0xF], 5);
+}
+
+#[test]
+fn shift_vy_shifts_vy_into_vx() {
+    let words = [0x6003, 0x6106, 0x8016];
+    let mut vip = emulator(Chip8Variant::OriginalChip8, &words, &[]);
+    run(&mut vip, 3);
+    assert_eq!(vip.registers()[0], 0x03);
+    assert_eq!(vip.registers()[0xF], 0);
+
+    let mut super_chip = emulator(Chip8Variant::SuperChipModern, &words, &[]);
+    run(&mut super_chip, 3);
+    assert_eq!(super_chip.registers()[0], 0x01);
+    assert_eq!(super_chip.registers()[0xF], 1);
+}
+
+#[test]
+fn memory_increment_leaves_i_past_the_registers() {
+    let words = [0xA300, 0xF255, 0xA310, 0xF265];
+    for (variant, increment) in [
+        (Chip8Variant::OriginalChip8, 3),
+        (Chip8Variant::Chip48, 2),
+        (Chip8Variant::SuperChipModern, 0),
+    ] {
+        let mut emulator = emulator(variant, &words, &[]);
+        run(&mut emulator, 2);
+        assert_eq!(emulator.index_register(), 0x300 + increment, "{variant}");
+        run(&mut emulator, 2);
+        assert_eq!(emulator.index_register(), 0x310 + increment, "{variant}");
+    }
+}
+
+#[test]
+fn jump_v0_offsets_bnnn_by_v0_rather_than_vx() {
+    let words = [0x6010, 0x6320, 0xB300];
+    let mut vip = emulator(Chip8Variant::OriginalChip8, &words, &[]);
+    run(&mut vip, 3);
+    assert_eq!(vip.program_counter(), 0x310);
+
+    let mut super_chip = emulator(Chip8Variant::SuperChipModern, &words, &[]);
+    run(&mut super_chip, 3);
+    assert_eq!(super_chip.program_counter(), 0x320);
+}
+
+#[test]
+fn usage_reports_the_stack_depth_and_memory_touched() {
+    // Stores to 0x400, then calls a subroutine and loops
+    let words = [0xA400, 0x6001, 0xF055, 0x220A, 0x1208, 0x00EE];
+    let mut emulator = emulator(Chip8Variant::SuperChipModern, &words, &[]);
+    emulator.set_canaries(true);
+    let before = emulator.usage();
+    assert_eq!(before.highest_address_used, Some(0x20B));
+
+    run(&mut emulator, 6);
+    let after = emulator.usage();
+    assert_eq!(after.max_stack_depth, 1);
+    assert_eq!(after.highest_address_used, Some(0x400));
+    assert_eq!(after.memory_used, before.memory_used.map(|used| used + 1));
+}
+
+#[test]
+fn display_wait_holds_dxyn_until_the_next_frame() {
+    // Draws and counts the draws in a loop
+    let words = [0xD115, 0x7001, 0x1200];
+    let mut vip = emulator(Chip8Variant::OriginalChip8, &words, &[]);
+    run(&mut vip, 10);
+    assert_eq!(vip.registers()[0], 0);
+    vip.update(FRAME).unwrap();
+    assert_eq!(vip.registers()[0], 1);
+
+    let mut super_chip = emulator(Chip8Variant::SuperChipModern, &words, &[]);
+    run(&mut super_chip, 10);
+    assert!(super_chip.registers()[0] > 1);
+}
+
+#[test]
+fn wrap_draws_past_the_edges_on_the_other_side() {
+    // An 8x2 sprite drawn 4 pixels from the bottom right corner
+    let words = [0x603C, 0x611F, 0xA000 | data_address(4), 0xD012];
+    for wrap in [false, true] {
+        let mut emulator = emulator(Chip8Variant::SuperChipModern, &words, &[0xFF, 0xFF]);
+        let mut quirks = *emulator.quirks();
+        quirks.wrap = wrap;
+        emulator.set_quirks(quirks);
+        run(&mut emulator, 4);
+        assert!(emulator.pixel(63, 31));
+        assert_eq!(emulator.pixel(0, 31), wrap);
+        assert_eq!(emulator.pixel(63, 0), wrap);
+        assert_eq!(emulator.pixel(3, 0), wrap);
+        assert!(!emulator.pixel(4, 0));
+    }
+}
+
+#[test]
+fn replayed_movies_end_on_the_same_frame() {
+    // Draws the digit for each key pressed at a random column
+    let words: [u16; 5] = [0xF10A, 0xC23F, 0xF129, 0xD235, 0x1200];
+    let rom: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes()).collect();
+    let mut emulator = emulator(Chip8Variant::OriginalChip8, &words, &[]);
+    emulator.restart(&rom, 42).unwrap();
+    let mut movie = Movie::new(&rom, &emulator, 42);
+
+    let mut keys = [false; NUM_INPUT_KEYS];
+    for frame in 0..12 {
+        match frame {
+            2 => keys[0x7] = true,
+            4 => keys[0x7] = false,
+            6 => keys[0xA] = true,
+            8 => keys[0xA] = false,
+            _ => {}
+        }
+        for (key, held) in keys.iter().enumerate() {
+            if *held {
+                emulator.press_key(key as u8);
+            } else {
+                emulator.release_key(key as u8);
+            }
+        }
+        movie.record(FRAME, &keys);
+        emulator.update(FRAME).unwrap();
+    }
+    assert_eq!(emulator.registers()[1], 0xA);
+
+    // As saved and loaded again
+    let movie: Movie = toml::from_str(&toml::to_string_pretty(&movie).unwrap()).unwrap();
+    let mut replayed = Emulator::new();
+    replayed.load_font(&STANDARD_FONT);
+    replayed.load_big_font(&BIG_FONT);
+    movie.restart(&mut replayed, &rom).unwrap();
+    movie.replay(&mut replayed).unwrap();
+    assert_eq!(state_hash(&replayed), state_hash(&emulator));
+}
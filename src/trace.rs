@@ -0,0 +1,67 @@
+use crate::{Emulator, HaltReason, KeyWait, disassembler::disassemble};
+
+/// An instruction that was executed by the emulator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutedInstruction {
+    /// The address the instruction was fetched from.
+    pub address: usize,
+    pub opcode: u16,
+    /// The instruction as an assembly mnemonic.
+    pub disassembly: String,
+    /// Zero-based index of the instruction within the emulator's lifetime.
+    pub cycle: u64,
+}
+
+/// Iterator which runs the emulator headlessly, yielding each instruction as it executes.
+///
+/// The iterator ends when the emulator halts (see [`Trace::halt_reason`]) or when it's blocked
+/// waiting for a key, since no further progress can be made without input from the host.
+pub struct Trace<'a> {
+    emulator: &'a mut Emulator,
+    halt_reason: Option<HaltReason>,
+}
+
+impl<'a> Trace<'a> {
+    pub(crate) fn new(emulator: &'a mut Emulator) -> Trace<'a> {
+        Trace {
+            emulator,
+            halt_reason: None,
+        }
+    }
+
+    /// Why the trace stopped, if it was because the emulator halted.
+    pub fn halt_reason(&self) -> Option<&HaltReason> {
+        self.halt_reason.as_ref()
+    }
+}
+
+impl Iterator for Trace<'_> {
+    type Item = ExecutedInstruction;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.halt_reason.is_some() {
+            return None;
+        }
+
+        let cycle = self.emulator.cycles();
+        while self.emulator.cycles() == cycle {
+            if let Err(reason) = self.emulator.step_headless() {
+                self.halt_reason = Some(reason);
+                return None;
+            }
+
+            // Waits for the display resolve by themselves as the timers tick, key waits don't
+            if self.emulator.cycles() == cycle && self.emulator.key_wait() != KeyWait::None {
+                return None;
+            }
+        }
+
+        let (address, opcode) = self.emulator.last_instruction();
+        Some(ExecutedInstruction {
+            address,
+            opcode,
+            disassembly: disassemble(opcode),
+            cycle,
+        })
+    }
+}
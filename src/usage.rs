@@ -0,0 +1,34 @@
+use std::fmt;
+
+/// The byte canaries fill `address` with. It varies with the address so that programs which
+/// happen to store a constant pattern aren't mistaken for unused memory.
+pub(crate) fn canary(address: usize) -> u8 {
+    (address as u8) ^ 0xA5
+}
+
+/// High-water marks of the resources a program has used, to show ROM authors how close they are
+/// to the machine's limits.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UsageReport {
+    /// The deepest the call stack has been since the last reset.
+    pub max_stack_depth: usize,
+    /// Number of bytes no longer holding their canary, if canaries are enabled.
+    pub memory_used: Option<usize>,
+    /// The highest address no longer holding its canary, if canaries are enabled.
+    pub highest_address_used: Option<usize>,
+    pub memory_size: usize,
+}
+
+impl fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Max stack depth: {}", self.max_stack_depth)?;
+        match self.memory_used {
+            Some(used) => writeln!(f, "Memory used: {used} of {} bytes", self.memory_size)?,
+            None => writeln!(f, "Memory used: unknown (canaries disabled)")?,
+        }
+        if let Some(address) = self.highest_address_used {
+            writeln!(f, "Highest address used: {address:#05X}")?;
+        }
+        Ok(())
+    }
+}
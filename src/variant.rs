@@ -0,0 +1,221 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::UnknownVariantError, profile::Profile, quirks::Quirks, rng::RngAlgorithm, timing::Timing,
+};
+
+/// Named presets for the interpreters ROMs are commonly written for, each bundling the full set
+/// of quirks that interpreter exhibits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Chip8Variant {
+    /// The original interpreter on the COSMAC VIP.
+    OriginalChip8,
+    /// CHIP-48 on the HP-48 calculators, which shifts VX in place, jumps with BXNN and leaves I
+    /// incremented by X after FX55 and FX65.
+    Chip48,
+    /// SUPER-CHIP 1.1 as it ran on the HP-48.
+    SuperChipLegacy,
+    /// SUPER-CHIP as implemented by most modern interpreters.
+    #[default]
+    SuperChipModern,
+    /// Octo's XO-CHIP extension.
+    XoChip,
+    /// Mega-CHIP, which adds a 256x192 indexed-color screen to SUPER-CHIP.
+    MegaChip,
+    /// The interpreter on the ETI-660 learning computer, which loads programs at 0x600 and has a
+    /// 64x48 screen.
+    Eti660,
+}
+
+impl Chip8Variant {
+    pub const ALL: [Chip8Variant; 7] = [
+        Chip8Variant::OriginalChip8,
+        Chip8Variant::Chip48,
+        Chip8Variant::SuperChipLegacy,
+        Chip8Variant::SuperChipModern,
+        Chip8Variant::XoChip,
+        Chip8Variant::MegaChip,
+        Chip8Variant::Eti660,
+    ];
+
+    /// The name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Chip8Variant::OriginalChip8 => "chip8",
+            Chip8Variant::Chip48 => "chip48",
+            Chip8Variant::SuperChipLegacy => "schip-legacy",
+            Chip8Variant::SuperChipModern => "schip-modern",
+            Chip8Variant::XoChip => "xochip",
+            Chip8Variant::MegaChip => "megachip",
+            Chip8Variant::Eti660 => "eti660",
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Chip8Variant::OriginalChip8 => Quirks {
+                vf_reset: true,
+                shift_vy: true,
+                memory_increment: true,
+                memory_increment_by_x: false,
+                jump_v0: true,
+                display_wait: true,
+                wrap: false,
+                row_collisions: false,
+                half_pixel_scroll: false,
+                vf_before_result: false,
+                index_overflow: false,
+                key_release: true,
+            },
+            Chip8Variant::Chip48 => Quirks {
+                vf_reset: false,
+                shift_vy: false,
+                memory_increment: true,
+                memory_increment_by_x: true,
+                jump_v0: false,
+                display_wait: false,
+                wrap: false,
+                row_collisions: false,
+                half_pixel_scroll: false,
+                vf_before_result: false,
+                index_overflow: false,
+                key_release: true,
+            },
+            Chip8Variant::SuperChipLegacy => Quirks {
+                vf_reset: false,
+                shift_vy: false,
+                memory_increment: false,
+                memory_increment_by_x: false,
+                jump_v0: false,
+                display_wait: true,
+                wrap: false,
+                row_collisions: true,
+                half_pixel_scroll: true,
+                vf_before_result: false,
+                index_overflow: false,
+                key_release: true,
+            },
+            Chip8Variant::SuperChipModern => Quirks {
+                vf_reset: false,
+                shift_vy: false,
+                memory_increment: false,
+                memory_increment_by_x: false,
+                jump_v0: false,
+                display_wait: false,
+                wrap: false,
+                row_collisions: false,
+                half_pixel_scroll: false,
+                vf_before_result: false,
+                index_overflow: false,
+                key_release: true,
+            },
+            Chip8Variant::XoChip => Quirks {
+                vf_reset: false,
+                shift_vy: true,
+                memory_increment: true,
+                memory_increment_by_x: false,
+                jump_v0: true,
+                display_wait: false,
+                wrap: true,
+                row_collisions: false,
+                half_pixel_scroll: false,
+                vf_before_result: false,
+                index_overflow: false,
+                key_release: true,
+            },
+            Chip8Variant::Eti660 => Quirks {
+                vf_reset: true,
+                shift_vy: true,
+                memory_increment: true,
+                memory_increment_by_x: false,
+                jump_v0: true,
+                display_wait: true,
+                wrap: false,
+                row_collisions: false,
+                half_pixel_scroll: false,
+                vf_before_result: false,
+                index_overflow: false,
+                key_release: true,
+            },
+            Chip8Variant::MegaChip => Quirks {
+                vf_reset: false,
+                shift_vy: false,
+                memory_increment: false,
+                memory_increment_by_x: false,
+                jump_v0: false,
+                display_wait: false,
+                wrap: false,
+                row_collisions: false,
+                half_pixel_scroll: false,
+                vf_before_result: false,
+                index_overflow: false,
+                key_release: true,
+            },
+        }
+    }
+
+    pub fn profile(self) -> Profile {
+        let rng = match self {
+            Chip8Variant::OriginalChip8 => RngAlgorithm::VipLfsr,
+            _ => RngAlgorithm::Uniform,
+        };
+        Profile {
+            rng,
+            quirks: self.quirks(),
+            timing: Timing::Fixed,
+            super_chip: matches!(
+                self,
+                Chip8Variant::SuperChipLegacy
+                    | Chip8Variant::SuperChipModern
+                    | Chip8Variant::XoChip
+                    | Chip8Variant::MegaChip
+            ),
+            xo_chip: self == Chip8Variant::XoChip,
+            two_page_hires: self == Chip8Variant::OriginalChip8,
+            megachip: self == Chip8Variant::MegaChip,
+            eti_660: self == Chip8Variant::Eti660,
+        }
+    }
+}
+
+impl fmt::Display for Chip8Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Variant::OriginalChip8 => write!(f, "CHIP-8"),
+            Chip8Variant::Chip48 => write!(f, "CHIP-48"),
+            Chip8Variant::SuperChipLegacy => write!(f, "SUPER-CHIP (legacy)"),
+            Chip8Variant::SuperChipModern => write!(f, "SUPER-CHIP (modern)"),
+            Chip8Variant::XoChip => write!(f, "XO-CHIP"),
+            Chip8Variant::MegaChip => write!(f, "Mega-CHIP"),
+            Chip8Variant::Eti660 => write!(f, "ETI-660"),
+        }
+    }
+}
+
+impl FromStr for Chip8Variant {
+    type Err = UnknownVariantError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Chip8Variant::ALL
+            .into_iter()
+            .find(|variant| variant.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownVariantError(name.to_string()))
+    }
+}
+
+impl TryFrom<String> for Chip8Variant {
+    type Error = UnknownVariantError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<Chip8Variant> for String {
+    fn from(variant: Chip8Variant) -> Self {
+        variant.name().to_string()
+    }
+}
@@ -0,0 +1,72 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A single byte of memory accessed by the running program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub kind: AccessKind,
+    pub address: usize,
+    /// The byte that was read, or the byte that was written.
+    pub value: u8,
+    /// The address of the instruction performing the access.
+    pub program_counter: usize,
+}
+
+/// Identifies a registered observer so that it can be removed later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WatchId(usize);
+
+struct Watch {
+    id: WatchId,
+    kind: AccessKind,
+    range: Range<usize>,
+    callback: Box<dyn FnMut(&MemoryAccess)>,
+}
+
+#[derive(Default)]
+pub(crate) struct Watches {
+    watches: Vec<Watch>,
+    next_id: usize,
+}
+
+impl Watches {
+    pub(crate) fn add(
+        &mut self,
+        kind: AccessKind,
+        range: Range<usize>,
+        callback: Box<dyn FnMut(&MemoryAccess)>,
+    ) -> WatchId {
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.watches.push(Watch {
+            id,
+            kind,
+            range,
+            callback,
+        });
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: WatchId) -> bool {
+        let count = self.watches.len();
+        self.watches.retain(|watch| watch.id != id);
+        self.watches.len() != count
+    }
+
+    pub(crate) fn notify(&mut self, access: &MemoryAccess) {
+        for watch in &mut self.watches {
+            if watch.kind == access.kind && watch.range.contains(&access.address) {
+                (watch.callback)(access);
+            }
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+}